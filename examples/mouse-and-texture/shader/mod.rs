@@ -21,7 +21,7 @@ impl Pipeline for MouseTexPipeline {
         FragmentDesc::Some {
             module,
             entry_point: "fs_main",
-            targets: vec![Some(state.config.format.clone().into())]
+            targets: vec![Some(state.config().format.clone().into())]
         }
     }
 
@@ -40,7 +40,7 @@ impl Pipeline for MouseTexPipeline {
                 conservative: false
             },
             depth_stencil: None,
-            multisample: Default::default(),
+            multisample: Self::multisample(state),
             fragment,
             multiview: None
         }
@@ -0,0 +1,91 @@
+//! Two windows drawing the same triangle, sharing one device and one pipeline.
+
+#[path = "../quick-triangle/shader/mod.rs"]
+mod shader;
+
+use winit::{
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    window::Window,
+};
+use wgpu_quick::{pipelines::make_pipline, Backends, State, renderable::{model::Model, Indices, RenderObject}};
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::shader::TrianglePipe;
+
+async fn run(event_loop: EventLoop<()>, windows: Vec<Window>) {
+
+    // Initialize wgpu with the first window, then add the others to the same state.
+    let mut state = State::new_winit(&windows[0], None, Backends::ALL)
+        .await
+        .expect("Could not create wgpu surface!");
+    for window in &windows[1..] {
+        state.add_window(window).expect("Could not add window!");
+    }
+
+    // The pipeline and render object are made once and used in every window.
+    let triangle_pipe = make_pipline::<TrianglePipe>(&state, &[], &[]);
+    let triangle_obj = RenderObject{
+        pipeline: Arc::clone(&triangle_pipe.cell),
+        bind_groups: vec![],
+        model: Model::<()>::empty(Indices::Ranged { vertices: 0..3, instances: 0..1 })
+    };
+
+    let mut windows: HashMap<_, _> = windows.into_iter().map(|window| (window.id(), window)).collect();
+
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Wait;
+
+        // Reconfigure whichever window was resized.
+        state.handle_event(&event);
+
+        match event {
+            Event::RedrawRequested(window_id) => {
+                let frame = match state.acquire_window_frame(window_id).expect("Failed to acquire next swap chain texture") {
+                    Some(frame) => frame,
+                    None => return,
+                };
+                let mut encoder = state.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                {
+                    let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor{
+                        label: None,
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                            view: &frame.view,
+                            resolve_target: frame.resolve_target.as_ref(),
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                                store: true,
+                            },
+                        })],
+                        depth_stencil_attachment: None,
+                    });
+                    triangle_obj.render_this(&mut rpass);
+                }
+                state.queue.submit(Some(encoder.finish()));
+                frame.present();
+            }
+
+            Event::WindowEvent {
+                window_id,
+                event: WindowEvent::CloseRequested,
+            } => {
+                // Drop the surface before the window it belongs to.
+                state.remove_window(window_id);
+                windows.remove(&window_id);
+                if windows.is_empty() {
+                    *control_flow = ControlFlow::Exit;
+                }
+            }
+            _ => {}
+        }
+    });
+}
+
+fn main() {
+    let event_loop = EventLoop::new();
+    let windows = vec![
+        winit::window::Window::new(&event_loop).unwrap(),
+        winit::window::Window::new(&event_loop).unwrap(),
+    ];
+    pollster::block_on(run(event_loop, windows));
+}
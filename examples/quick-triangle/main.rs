@@ -24,7 +24,7 @@ async fn run(event_loop: EventLoop<()>, window: &Window) {
 
     // Make a RenderObject that uses this pipeline.
     let triangle_obj = RenderObject{
-        pipeline: Arc::clone(&triangle_pipe.pipeline),
+        pipeline: Arc::clone(&triangle_pipe.cell),
         bind_groups: vec![],
         model: Model::<()>::empty(Indices::Ranged { vertices: 0..3, instances: 0..1 }) 
     };
@@ -37,29 +37,27 @@ async fn run(event_loop: EventLoop<()>, window: &Window) {
         *control_flow = ControlFlow::Wait;
         match event {
             Event::WindowEvent {
-                event: WindowEvent::Resized(size),
+                event: ref window_event @ (WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. }),
                 ..
             } => {
-                // Reconfigure the surface with the new size.
-                state.resize(size);
+                // Reconfigure the surface with the new size and scale factor.
+                state.handle_window_event(window_event);
             }
 
             // Only render on redraw request events.
             Event::RedrawRequested(_) => {
-                let frame = state
-                    .surface
-                    .get_current_texture()
-                    .expect("Failed to acquire next swap chain texture");
-                let view = frame
-                    .texture
-                    .create_view(&wgpu::TextureViewDescriptor::default());
+                let frame = match state.acquire_frame().expect("Failed to acquire next swap chain texture") {
+                    Some(frame) => frame,
+                    // The surface is not ready, skip this frame.
+                    None => return,
+                };
                 let mut encoder = state.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
                 {
                     let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor{
                         label: None,
                         color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                            view: &view,
-                            resolve_target: None,
+                            view: &frame.view,
+                            resolve_target: frame.resolve_target.as_ref(),
                             ops: wgpu::Operations {
                                 load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
                                 store: true,
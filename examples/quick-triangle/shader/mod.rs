@@ -23,7 +23,7 @@ impl Pipeline for TrianglePipe {
         FragmentDesc::Some {
             module,
             entry_point: "fs_main",
-            targets: vec![Some(state.config.format.clone().into())]
+            targets: vec![Some(state.config().format.clone().into())]
         }
     }
 
@@ -42,7 +42,7 @@ impl Pipeline for TrianglePipe {
                 conservative: false
             },
             depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
+            multisample: Self::multisample(state),
             fragment,
             multiview: None
         }
@@ -30,20 +30,16 @@ async fn run() {
 
     // Make a RenderObject that uses this pipeline.
     let triangle_obj = RenderObject{
-        pipeline: Arc::clone(&triangle_pipe.pipeline),
+        pipeline: Arc::clone(&triangle_pipe.cell),
         bind_groups: vec![],
         model: Model::<()>::empty(Indices::Ranged { vertices: 0..3, instances: 0..1 })
     };
 
-    // Fetch the surface texture. 
-    let frame = state.surface
-        .get_current_texture()
-        .expect("Failed to acquire next swap chain texture");
-
-    // Create a view from the surface texture.
-    let view = frame
-        .texture
-        .create_view(&wgpu::TextureViewDescriptor::default());
+    // Fetch the surface texture and a view of it.
+    let frame = state
+        .acquire_frame()
+        .expect("Failed to acquire next swap chain texture")
+        .expect("The surface was not ready");
 
     // Begin a render pass with a command encoder.
     let mut encoder =
@@ -53,8 +49,8 @@ async fn run() {
         let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: None,
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view,
-                resolve_target: None,
+                view: &frame.view,
+                resolve_target: frame.resolve_target.as_ref(),
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(wgpu::Color::GREEN),
                     store: true,
@@ -76,8 +72,8 @@ async fn run() {
     event_loop.run(move |e, _, ctrl|{
         match e {
             Event::WindowEvent { window_id, event } => match event {
-                WindowEvent::Resized(s) => {
-                    state.resize(s);
+                WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                    state.handle_window_event(&event);
                 },
                 WindowEvent::CloseRequested => {
                     *ctrl = ControlFlow::Exit
@@ -20,7 +20,7 @@ impl Pipeline for TrianglePipe {
     }
 
     fn fragment_desc<'a>(state: &State, module: &'a ShaderModule) -> FragmentDesc<'a> {
-        let coltgt: ColorTargetState = state.config.format.clone().into();
+        let coltgt: ColorTargetState = state.config().format.clone().into();
         FragmentDesc::Some {
             module,
             entry_point: "fs_main",
@@ -43,7 +43,7 @@ impl Pipeline for TrianglePipe {
                 conservative: false
             },
             depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
+            multisample: Self::multisample(state),
             fragment,
             multiview: None
         }
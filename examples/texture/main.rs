@@ -7,7 +7,7 @@ use winit::{
     event_loop::{ControlFlow, EventLoop},
     window::Window,
 };
-use wgpu_quick::{pipelines::{Pipeline, VertexDesc, FragmentDesc, make_pipline}, State, Backends, renderable::RenderObject};
+use wgpu_quick::{pipelines::{Pipeline, VertexDesc, FragmentDesc, make_pipline_preprocessed}, State, Backends, renderable::RenderObject};
 use wgpu_quick::renderable::{model::{Model}, Indices, Renderable};
 use std::sync::Arc;
 use wgpu_quick::texture::Texture;
@@ -78,12 +78,13 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         }
     ]);
 
-    // Load a pipeline that uses the binding's layout.
-    let mousetex_pipe = make_pipline::<TexPipeline>(&state, &[&bindings.bind_layout], &[]);
+    // Load a pipeline that uses the binding's layout, from the shader with its includes resolved.
+    let shader = shader::processed().expect("Could not preprocess the shader");
+    let mousetex_pipe = make_pipline_preprocessed::<TexPipeline>(&state, &shader, &[&bindings.bind_layout], &[]).expect("Invalid shader");
 
     // Create a render object that uses the pipeline with our compatible binding.
     let mousetex_obj = RenderObject{
-        pipeline: Arc::clone(&mousetex_pipe.pipeline),
+        pipeline: Arc::clone(&mousetex_pipe.cell),
         bind_groups: vec![Arc::clone(&bindings.bind_groups[0])],
         model: Model::from_vertices(&state, &VERTICES, Indices::from_indices(&state, &INDICES, wgpu::IndexFormat::Uint16, 0..1)), 
     };
@@ -99,9 +100,9 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         match event {
             Event::WindowEvent { window_id, event } => {
                 match event {
-                    WindowEvent::Resized(size) => {
+                    WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
                         // Change configuration on resize.
-                        state.resize(size);
+                        state.handle_window_event(&event);
                     }
 
                     WindowEvent::CloseRequested => {
@@ -111,8 +112,8 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     WindowEvent::CursorMoved { device_id, position, modifiers } => {
 
                         // Calculate mouse position in screen units
-                        (*mouse_pos)[0] = (position.x as f32)/(state.config.width as f32);
-                        (*mouse_pos)[1] = (position.y as f32)/(state.config.height as f32);
+                        (*mouse_pos)[0] = (position.x as f32)/(state.config().width as f32);
+                        (*mouse_pos)[1] = (position.y as f32)/(state.config().height as f32);
 
                         // Check if it is time to render a frame.
                         match framerate.check() {
@@ -134,12 +135,11 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
             }
             Event::RedrawRequested(_) => {
                 // Get the surface texture and create a render pass.
-                let frame = state.surface
-                    .get_current_texture()
-                    .expect("Failed to acquire next swap chain texture");
-                let view = frame
-                    .texture
-                    .create_view(&wgpu::TextureViewDescriptor::default());
+                let frame = match state.acquire_frame().expect("Failed to acquire next swap chain texture") {
+                    Some(frame) => frame,
+                    // The surface is not ready, skip this frame.
+                    None => return,
+                };
                 let mut encoder =
                     state.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
                 
@@ -147,8 +147,8 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                         label: None,
                         color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                            view: &view,
-                            resolve_target: None,
+                            view: &frame.view,
+                            resolve_target: frame.resolve_target.as_ref(),
                             ops: wgpu::Operations {
                                 load: wgpu::LoadOp::Clear(wgpu::Color::GREEN),
                                 store: true,
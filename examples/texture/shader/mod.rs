@@ -1,14 +1,20 @@
-use wgpu_quick::{pipelines::{Pipeline, VertexDesc, FragmentDesc}, buffer::vertex::VertexType};
+use wgpu_quick::{pipelines::{Pipeline, VertexDesc, FragmentDesc, ProcessedShader, ShaderPreprocessor}, buffer::vertex::VertexType};
 use std::ops::Index;
 use wgpu::{ShaderSource, ShaderModule, PipelineLayout, FragmentState, VertexState, RenderPipelineDescriptor, ColorTargetState};
-use std::borrow::Cow;
 use wgpu_quick::State;
 
 pub struct TexPipeline;
 
+/// The texture shader with the fullscreen quad's vertex stage included.
+pub fn processed() -> anyhow::Result<ProcessedShader> {
+    ShaderPreprocessor::new()
+        .file("quad.wgsl", include_str!("quad.wgsl"))
+        .process_source("shader.wgsl", include_str!("shader.wgsl"))
+}
+
 impl Pipeline for TexPipeline {
     fn shader_src<'a>(state: &State) -> ShaderSource<'a> {
-        ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader.wgsl")))
+        processed().expect("Could not preprocess the texture shader").shader_source()
     }
 
     fn vertex_state<'a>(state: &State, module: &'a ShaderModule) -> VertexDesc<'a> {
@@ -20,7 +26,7 @@ impl Pipeline for TexPipeline {
     }
 
     fn fragment_desc<'a>(state: &State, module: &'a ShaderModule) -> FragmentDesc<'a> {
-        let coltgt: ColorTargetState = state.config.format.clone().into();
+        let coltgt: ColorTargetState = state.config().format.clone().into();
         FragmentDesc::Some {
             module,
             entry_point: "fs_main",
@@ -43,7 +49,7 @@ impl Pipeline for TexPipeline {
                 conservative: false
             },
             depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
+            multisample: Self::multisample(state),
             fragment,
             multiview: None
         }
@@ -30,6 +30,8 @@ impl<'a> Binder<'a> {
 
 pub struct Bindings {
     pub bind_layout: wgpu::BindGroupLayout,
+    /// The entries `bind_layout` was created with, for checking it against a shader.
+    pub entries: Vec<wgpu::BindGroupLayoutEntry>,
     pub bind_groups: Vec<Arc<wgpu::BindGroup>>,
 }
 
@@ -82,6 +84,7 @@ impl Bindings {
         Self {
 
             bind_layout,
+            entries: layout_entries,
             bind_groups
         }
 
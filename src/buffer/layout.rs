@@ -0,0 +1,421 @@
+use std::fmt;
+
+/// A type whose bytes match its WGSL counterpart's memory layout, so it can be copied into buffers as is.\
+/// Implemented for `f32`, `i32`, `u32`, the vector and matrix types here, arrays of them and structs
+/// declared with `wgsl_struct!`.
+///
+/// # Safety
+/// `SIZE` must be the Rust size, and every field must be at the offset WGSL gives it.
+pub unsafe trait WgslType: bytemuck::Pod {
+    /// WGSL's `AlignOf`.
+    const ALIGN: usize;
+    /// WGSL's `SizeOf`.
+    const SIZE: usize;
+    /// Arrays and structs, which the uniform address space aligns to 16 bytes.
+    const COMPOSITE: bool = false;
+    /// Whether the type follows the uniform address space's extra rules: array strides that are
+    /// multiples of 16 and struct or array members at offsets that are multiples of 16.
+    const UNIFORM: bool = true;
+    /// Whether the Rust layout can match, arrays of 12 byte `Vec3`s can't reach WGSL's 16 byte stride.
+    const LAYOUT_MATCHES: bool = true;
+}
+
+/// The scalars vectors can hold.
+///
+/// # Safety
+/// Only for 4 byte scalars WGSL has, the vector layouts assume it.
+pub unsafe trait WgslScalar: WgslType {}
+
+unsafe impl WgslType for f32 {
+    const ALIGN: usize = 4;
+    const SIZE: usize = 4;
+}
+
+unsafe impl WgslType for i32 {
+    const ALIGN: usize = 4;
+    const SIZE: usize = 4;
+}
+
+unsafe impl WgslType for u32 {
+    const ALIGN: usize = 4;
+    const SIZE: usize = 4;
+}
+
+unsafe impl WgslScalar for f32 {}
+unsafe impl WgslScalar for i32 {}
+unsafe impl WgslScalar for u32 {}
+
+/// WGSL's `vec2<S>`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vec2<S>(pub [S; 2]);
+
+/// WGSL's `vec3<S>`. It is 16 byte aligned but only 12 bytes long, so a scalar can follow it without padding.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vec3<S>(pub [S; 3]);
+
+/// WGSL's `vec4<S>`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vec4<S>(pub [S; 4]);
+
+macro_rules! impl_vector {
+    ($vector:ident, $len:literal, $align:literal) => {
+        unsafe impl<S: WgslScalar> bytemuck::Zeroable for $vector<S> {}
+        unsafe impl<S: WgslScalar> bytemuck::Pod for $vector<S> {}
+
+        unsafe impl<S: WgslScalar> WgslType for $vector<S> {
+            const ALIGN: usize = $align * S::SIZE;
+            const SIZE: usize = $len * S::SIZE;
+        }
+
+        impl<S> From<[S; $len]> for $vector<S> {
+            fn from(components: [S; $len]) -> Self {
+                Self(components)
+            }
+        }
+    };
+}
+
+impl_vector!(Vec2, 2, 2);
+impl_vector!(Vec3, 3, 4);
+impl_vector!(Vec4, 4, 4);
+
+/// WGSL's `mat2x2<f32>`, two columns.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Mat2x2(pub [[f32; 2]; 2]);
+
+/// WGSL's `mat3x3<f32>`. Its columns are `vec3`s with a 16 byte stride, the padding is kept here.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Mat3x3([[f32; 4]; 3]);
+
+/// WGSL's `mat4x4<f32>`, four columns.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Mat4x4(pub [[f32; 4]; 4]);
+
+impl Mat3x3 {
+    pub fn columns(&self) -> [[f32; 3]; 3] {
+        self.0.map(|column| [column[0], column[1], column[2]])
+    }
+}
+
+impl From<[[f32; 3]; 3]> for Mat3x3 {
+    fn from(columns: [[f32; 3]; 3]) -> Self {
+        Self(columns.map(|column| [column[0], column[1], column[2], 0.0]))
+    }
+}
+
+impl From<[[f32; 2]; 2]> for Mat2x2 {
+    fn from(columns: [[f32; 2]; 2]) -> Self {
+        Self(columns)
+    }
+}
+
+impl From<[[f32; 4]; 4]> for Mat4x4 {
+    fn from(columns: [[f32; 4]; 4]) -> Self {
+        Self(columns)
+    }
+}
+
+macro_rules! impl_matrix {
+    ($matrix:ident, $align:literal, $size:literal) => {
+        unsafe impl bytemuck::Zeroable for $matrix {}
+        unsafe impl bytemuck::Pod for $matrix {}
+
+        unsafe impl WgslType for $matrix {
+            const ALIGN: usize = $align;
+            const SIZE: usize = $size;
+        }
+    };
+}
+
+impl_matrix!(Mat2x2, 8, 16);
+impl_matrix!(Mat3x3, 16, 48);
+impl_matrix!(Mat4x4, 16, 64);
+
+/// Arrays whose element size isn't a multiple of its alignment, like `[Vec3<f32>; N]`, have a WGSL stride
+/// larger than the Rust one. Their `LAYOUT_MATCHES` is `false` and using their `SIZE` fails to compile.
+unsafe impl<T: WgslType, const N: usize> WgslType for [T; N] {
+    const ALIGN: usize = T::ALIGN;
+    const SIZE: usize = {
+        assert!(T::SIZE.is_multiple_of(T::ALIGN), "array elements need a size that is a multiple of their alignment, `vec3` arrays have a 16 byte stride");
+        N * T::SIZE
+    };
+    const COMPOSITE: bool = true;
+    const UNIFORM: bool = T::UNIFORM && round_up(T::ALIGN, T::SIZE).is_multiple_of(16);
+    const LAYOUT_MATCHES: bool = T::LAYOUT_MATCHES && T::SIZE.is_multiple_of(T::ALIGN);
+}
+
+/// Padding that `wgsl_struct!` inserts between fields, always zero.
+#[doc(hidden)]
+#[repr(transparent)]
+#[derive(Clone, Copy)]
+pub struct Pad<const N: usize>([u8; N]);
+
+unsafe impl<const N: usize> bytemuck::Zeroable for Pad<N> {}
+unsafe impl<const N: usize> bytemuck::Pod for Pad<N> {}
+
+impl<const N: usize> Default for Pad<N> {
+    fn default() -> Self {
+        Self([0; N])
+    }
+}
+
+impl<const N: usize> fmt::Debug for Pad<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Pad<{}>", N)
+    }
+}
+
+impl<const N: usize> PartialEq for Pad<N> {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+/// The layout of a struct member, for the const functions `wgsl_struct!` computes padding with.
+#[doc(hidden)]
+#[derive(Clone, Copy)]
+pub struct Member {
+    align: usize,
+    size: usize,
+    composite: bool,
+    uniform: bool,
+}
+
+impl Member {
+    pub const fn of<T: WgslType>() -> Self {
+        Self {
+            align: T::ALIGN,
+            size: T::SIZE,
+            composite: T::COMPOSITE,
+            uniform: T::UNIFORM,
+        }
+    }
+}
+
+const fn round_up(k: usize, n: usize) -> usize {
+    n.div_ceil(k) * k
+}
+
+/// The offset of a member, each one aligned after the end of the one before.
+const fn offset(members: &[Member], index: usize) -> usize {
+    let mut offset = 0;
+    let mut i = 0;
+    while i <= index {
+        if i > 0 {
+            offset += members[i - 1].size;
+        }
+        offset = round_up(members[i].align, offset);
+        i += 1;
+    }
+    offset
+}
+
+/// The padding between the last member and the one before it.
+#[doc(hidden)]
+pub const fn padding_before(members: &[Member]) -> usize {
+    let last = members.len() - 1;
+    if last == 0 {
+        return 0;
+    }
+    offset(members, last) - offset(members, last - 1) - members[last - 1].size
+}
+
+#[doc(hidden)]
+pub const fn struct_align(members: &[Member]) -> usize {
+    let mut align = 1;
+    let mut i = 0;
+    while i < members.len() {
+        if members[i].align > align {
+            align = members[i].align;
+        }
+        i += 1;
+    }
+    align
+}
+
+#[doc(hidden)]
+pub const fn struct_size(members: &[Member]) -> usize {
+    let last = members.len() - 1;
+    round_up(struct_align(members), offset(members, last) + members[last].size)
+}
+
+/// The padding after the last member up to the struct's size.
+#[doc(hidden)]
+pub const fn trailing_padding(members: &[Member]) -> usize {
+    let last = members.len() - 1;
+    struct_size(members) - offset(members, last) - members[last].size
+}
+
+/// Whether a struct with these members follows the uniform address space's rules.
+#[doc(hidden)]
+pub const fn uniform_compatible(members: &[Member]) -> bool {
+    let mut i = 0;
+    while i < members.len() {
+        let member = members[i];
+        if !member.uniform {
+            return false;
+        }
+        if member.composite {
+            let start = offset(members, i);
+            if !start.is_multiple_of(round_up(16, member.align)) {
+                return false;
+            }
+            if i + 1 < members.len() && offset(members, i + 1) - start < round_up(16, member.size) {
+                return false;
+            }
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Declare a `#[repr(C)]` struct laid out like the WGSL struct with the same fields, with padding inserted
+/// between the fields and at the end where WGSL has it. Field types have to implement `WgslType`, e.g.
+/// `f32`, `Vec3<f32>`, `Mat4x4`, arrays and other `wgsl_struct!`s.\
+/// The struct gets a `new` function taking every field, since the padding fields are private.
+/// Layouts Rust can't match, like arrays of `Vec3`, fail to compile.
+///
+/// ```
+/// use wgpu_quick::buffer::layout::{Vec3, Vec4};
+///
+/// wgpu_quick::wgsl_struct! {
+///     /// `struct Light { position: vec3<f32>, intensity: f32, color: vec4<f32> }` in WGSL.
+///     pub struct Light {
+///         pub position: Vec3<f32>,
+///         pub intensity: f32,
+///         pub color: Vec4<f32>,
+///     }
+/// }
+///
+/// let light = Light::new([0.0, 1.0, 0.0].into(), 2.0, [1.0; 4].into());
+/// assert_eq!(std::mem::size_of::<Light>(), 32);
+/// ```
+#[macro_export]
+macro_rules! wgsl_struct {
+    // Add each field after its padding, which depends on the fields before it.
+    (@fields $header:tt $params:tt [$($out:tt)*] [$($prev:ty,)*] $(#[$fattr:meta])* $fvis:vis $field:ident : $ty:ty, $($rest:tt)*) => {
+        $crate::wgsl_struct!(@fields $header $params [
+            $($out)*
+            [<__pad_ $field>]: $crate::buffer::layout::Pad<{
+                $crate::buffer::layout::padding_before(&[$($crate::buffer::layout::Member::of::<$prev>(),)* $crate::buffer::layout::Member::of::<$ty>()])
+            }>,
+            $(#[$fattr])* $fvis $field: $ty,
+        ] [$($prev,)* $ty,] $($rest)*);
+    };
+    (@fields [$(#[$attr:meta])* $vis:vis struct $name:ident] [$($pfield:ident: $pty:ty),+] [$($out:tt)*] [$($ty:ty,)+]) => {
+        $crate::paste::paste! {
+            $(#[$attr])*
+            #[repr(C)]
+            #[derive(Clone, Copy)]
+            $vis struct $name {
+                $($out)*
+                __pad_end: $crate::buffer::layout::Pad<{
+                    $crate::buffer::layout::trailing_padding(&[$($crate::buffer::layout::Member::of::<$ty>()),+])
+                }>,
+            }
+        }
+
+        unsafe impl $crate::bytemuck::Zeroable for $name {}
+        unsafe impl $crate::bytemuck::Pod for $name {}
+
+        unsafe impl $crate::buffer::layout::WgslType for $name {
+            const ALIGN: usize = $crate::buffer::layout::struct_align(&[$($crate::buffer::layout::Member::of::<$ty>()),+]);
+            const SIZE: usize = $crate::buffer::layout::struct_size(&[$($crate::buffer::layout::Member::of::<$ty>()),+]);
+            const COMPOSITE: bool = true;
+            const UNIFORM: bool = $crate::buffer::layout::uniform_compatible(&[$($crate::buffer::layout::Member::of::<$ty>()),+]);
+        }
+
+        const _: () = {
+            $(
+                assert!(
+                    <$pty as $crate::buffer::layout::WgslType>::LAYOUT_MATCHES,
+                    concat!("`", stringify!($name), "::", stringify!($pfield), "` can't match its WGSL layout: array elements need a size that is a multiple of their alignment, `vec3` arrays have a 16 byte stride. Use `Vec4` or a `wgsl_struct!` element.")
+                );
+            )+
+            // The padding fields make every offset WGSL's, so Rust must not add any of its own.
+            assert!(
+                std::mem::size_of::<$name>() == <$name as $crate::buffer::layout::WgslType>::SIZE,
+                concat!("`", stringify!($name), "` doesn't match its WGSL layout")
+            );
+        };
+
+        impl $name {
+            #[allow(dead_code, clippy::too_many_arguments)]
+            pub fn new($($pfield: $pty),+) -> Self {
+                Self {
+                    $($pfield,)+
+                    ..$crate::bytemuck::Zeroable::zeroed()
+                }
+            }
+        }
+    };
+    ($(#[$attr:meta])* $vis:vis struct $name:ident { $($(#[$fattr:meta])* $fvis:vis $field:ident : $ty:ty),+ $(,)? }) => {
+        $crate::wgsl_struct!(@fields [$(#[$attr])* $vis struct $name] [$($field: $ty),+] [] [] $($(#[$fattr])* $fvis $field : $ty,)+);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::wgsl_struct! {
+        struct Light {
+            position: Vec3<f32>,
+            intensity: f32,
+            color: Vec3<f32>,
+        }
+    }
+
+    crate::wgsl_struct! {
+        struct Scene {
+            ambient: f32,
+            lights: [Light; 2],
+            transform: Mat3x3,
+            count: u32,
+        }
+    }
+
+    #[test]
+    fn structs_are_padded_like_wgsl() {
+        assert_eq!(std::mem::size_of::<Light>(), 32);
+        assert_eq!(std::mem::offset_of!(Light, intensity), 12);
+        assert_eq!(std::mem::offset_of!(Light, color), 16);
+        assert_eq!(<Light as WgslType>::ALIGN, 16);
+        const _: () = assert!(<Light as WgslType>::UNIFORM);
+
+        assert_eq!(std::mem::offset_of!(Scene, lights), 16);
+        assert_eq!(std::mem::offset_of!(Scene, transform), 80);
+        assert_eq!(std::mem::offset_of!(Scene, count), 128);
+        assert_eq!(std::mem::size_of::<Scene>(), 144);
+        const _: () = assert!(<Scene as WgslType>::UNIFORM);
+    }
+
+    #[test]
+    fn uniform_rules_are_checked() {
+        const _: () = assert!(!<[f32; 4] as WgslType>::UNIFORM);
+        const _: () = assert!(<[Vec4<f32>; 4] as WgslType>::UNIFORM);
+        const _: () = assert!(!<[Vec3<f32>; 4] as WgslType>::LAYOUT_MATCHES);
+
+        // A struct member of a uniform struct has to start at a multiple of 16.
+        crate::wgsl_struct! {
+            struct Pair {
+                a: f32,
+                b: f32,
+            }
+        }
+        crate::wgsl_struct! {
+            struct Packed {
+                x: f32,
+                pair: Pair,
+            }
+        }
+        assert_eq!(std::mem::offset_of!(Packed, pair), 4);
+        const _: () = assert!(!<Packed as WgslType>::UNIFORM);
+    }
+}
@@ -1,2 +1,4 @@
+pub mod layout;
+pub mod storage;
 pub mod uniform;
 pub mod vertex;
\ No newline at end of file
@@ -0,0 +1,298 @@
+use std::future::Future;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut, Range};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use anyhow::Result;
+use wgpu::util::DeviceExt;
+
+use crate::bindings::Binder;
+
+/// How shaders may access a storage buffer, `var<storage, read>` or `var<storage, read_write>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageAccess {
+    ReadOnly,
+    ReadWrite,
+}
+
+fn create_buffer(device: &wgpu::Device, contents: &[u8]) -> Arc<wgpu::Buffer> {
+    Arc::new(device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
+        label: None,
+        contents,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC
+    }))
+}
+
+fn storage_binder(buffer: &wgpu::Buffer, access: StorageAccess, binding: u32, visibility: wgpu::ShaderStages) -> Binder<'_> {
+    Binder {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: access == StorageAccess::ReadOnly },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+        resources: vec![buffer.as_entire_binding()],
+    }
+}
+
+/// The result of `map_async` once it called back, and the task waiting for it.
+type MapState = (Option<Result<(), wgpu::BufferAsyncError>>, Option<Waker>);
+
+/// Resolves when a `map_async` callback arrives.
+struct Mapping {
+    shared: Arc<Mutex<MapState>>,
+}
+
+impl Mapping {
+    fn read(slice: wgpu::BufferSlice) -> Self {
+        let shared: Arc<Mutex<MapState>> = Arc::new(Mutex::new((None, None)));
+        let callback_shared = Arc::clone(&shared);
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let mut shared = callback_shared.lock().unwrap();
+            shared.0 = Some(result);
+            if let Some(waker) = shared.1.take() {
+                waker.wake();
+            }
+        });
+        Self { shared }
+    }
+}
+
+impl Future for Mapping {
+    type Output = Result<(), wgpu::BufferAsyncError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = self.shared.lock().unwrap();
+        match shared.0.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                shared.1 = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Copy `len` elements of `T` from the start of `buffer` into a staging buffer and start mapping it.\
+/// The copy is submitted right away, the returned future reads the elements once the mapping is done.
+fn read_buffer<T: bytemuck::Zeroable + bytemuck::Pod>(device: &wgpu::Device, queue: &wgpu::Queue, buffer: &wgpu::Buffer, len: usize) -> impl Future<Output = Result<Vec<T>>> + 'static {
+    let mut data = vec![T::zeroed(); len];
+    let size = std::mem::size_of_val(data.as_slice()) as u64;
+    let reading = (size != 0).then(|| {
+        // Copies are in multiples of 4 bytes, `create_buffer_init` pads the storage buffer to match.
+        let copy_size = wgpu::util::align_to(size, wgpu::COPY_BUFFER_ALIGNMENT);
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("wgpu_quick storage readback"),
+            size: copy_size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.copy_buffer_to_buffer(buffer, 0, &staging, 0, copy_size);
+        queue.submit(Some(encoder.finish()));
+        let mapping = Mapping::read(staging.slice(..));
+        (staging, mapping)
+    });
+
+    async move {
+        if let Some((staging, mapping)) = reading {
+            mapping.await?;
+            // The mapped bytes may not be aligned for `T`, so copy them into the vector's bytes.
+            bytemuck::cast_slice_mut::<T, u8>(&mut data).copy_from_slice(&staging.slice(..).get_mapped_range()[..size as usize]);
+            staging.unmap();
+        }
+        Ok(data)
+    }
+}
+
+/// Wait for a `read_buffer` future by blocking on the device until the GPU is done.
+fn wait_for<T>(device: &wgpu::Device, reading: impl Future<Output = Result<T>>) -> Result<T> {
+    device.poll(wgpu::Maintain::Wait);
+    pollster::block_on(reading)
+}
+
+/// A smart pointer that synchronizes a storage buffer holding one value.\
+/// Unlike a `Uniform` it can be larger, written by shaders with `StorageAccess::ReadWrite` and read back.
+pub struct StorageBuffer<T: bytemuck::Zeroable + bytemuck::Pod> {
+    buffer: Arc<wgpu::Buffer>,
+    access: StorageAccess,
+    data: T,
+}
+
+impl<T: bytemuck::Zeroable + bytemuck::Pod> Deref for StorageBuffer<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl<T: bytemuck::Zeroable + bytemuck::Pod> DerefMut for StorageBuffer<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data
+    }
+}
+
+impl<T: bytemuck::Zeroable + bytemuck::Pod> StorageBuffer<T> {
+    pub fn new(device: &wgpu::Device, data: T, access: StorageAccess) -> Self {
+        Self {
+            buffer: create_buffer(device, bytemuck::cast_slice(&[data])),
+            access,
+            data
+        }
+    }
+
+    pub fn get_buffer(&self) -> &wgpu::Buffer {
+        self.buffer.as_ref()
+    }
+
+    pub fn sync(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(self.buffer.as_ref(), 0, bytemuck::cast_slice(&[self.data]))
+    }
+
+    /// A `Binder` for the whole buffer with its access.
+    pub fn binder(&self, binding: u32, visibility: wgpu::ShaderStages) -> Binder<'_> {
+        storage_binder(&self.buffer, self.access, binding, visibility)
+    }
+
+    /// Read the value the GPU holds, e.g. after a compute shader wrote it. Doesn't change the CPU copy.\
+    /// The future doesn't poll the device, on native it resolves once something else does,
+    /// e.g. `device.poll(wgpu::Maintain::Poll)` every frame. `read_back_blocking` waits instead.
+    pub fn read_back(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> impl Future<Output = Result<T>> + 'static {
+        let reading = read_buffer::<T>(device, queue, &self.buffer, 1);
+        async move { Ok(reading.await?[0]) }
+    }
+
+    /// Like `read_back`, but blocks the thread until the GPU has finished its work.
+    pub fn read_back_blocking(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<T> {
+        wait_for(device, self.read_back(device, queue))
+    }
+}
+
+/// Creates a storage buffer that can be buffered to in sized chunks, from any index in the data to any index in the buffer.
+pub struct StorageChunk<T: bytemuck::Zeroable + bytemuck::Pod> {
+    buffer: Arc<wgpu::Buffer>,
+    access: StorageAccess,
+    data: Vec<T>,
+}
+
+impl<T: bytemuck::Zeroable + bytemuck::Pod> Deref for StorageChunk<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl<T: bytemuck::Zeroable + bytemuck::Pod> DerefMut for StorageChunk<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data
+    }
+}
+
+impl<T: bytemuck::Zeroable + bytemuck::Pod> StorageChunk<T> {
+    pub fn new(device: &wgpu::Device, data: Vec<T>, access: StorageAccess) -> Self {
+        Self {
+            buffer: create_buffer(device, bytemuck::cast_slice(data.as_slice())),
+            access,
+            data
+        }
+    }
+
+    pub fn get_buffer(&self) -> &wgpu::Buffer {
+        self.buffer.as_ref()
+    }
+
+    pub fn sync(&self, queue: &wgpu::Queue, data_index: usize, storage_index: usize) {
+        queue.write_buffer(self.buffer.as_ref(), (storage_index * std::mem::size_of::<T>()) as u64, bytemuck::cast_slice(&[self.data.as_slice()[data_index]]))
+    }
+
+    pub fn sync_range(&self, queue: &wgpu::Queue, data_range: Range<usize>, storage_range: Range<usize>) {
+        queue.write_buffer(self.buffer.as_ref(), (storage_range.start * std::mem::size_of::<T>()) as u64, bytemuck::cast_slice(&self.data.as_slice()[data_range]))
+    }
+
+    pub fn sync_all(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(self.buffer.as_ref(), 0, bytemuck::cast_slice(self.data.as_slice()))
+    }
+
+    /// A `Binder` for the whole buffer with its access.
+    pub fn binder(&self, binding: u32, visibility: wgpu::ShaderStages) -> Binder<'_> {
+        storage_binder(&self.buffer, self.access, binding, visibility)
+    }
+
+    /// Read as many elements as the CPU copy has from the GPU. Doesn't change the CPU copy,
+    /// assign the result to it to keep both in sync.\
+    /// The future doesn't poll the device, see `StorageBuffer::read_back`.
+    pub fn read_back(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> impl Future<Output = Result<Vec<T>>> + 'static {
+        read_buffer(device, queue, &self.buffer, self.data.len())
+    }
+
+    /// Like `read_back`, but blocks the thread until the GPU has finished its work.
+    pub fn read_back_blocking(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Vec<T>> {
+        wait_for(device, self.read_back(device, queue))
+    }
+}
+
+/// Creates a storage buffer in GPU memory but does not store a copy on the CPU side.\
+/// Handy for compute shader output, which is only read back when needed.
+pub struct StorageRemote<T: bytemuck::Zeroable + bytemuck::Pod> {
+    buffer: Arc<wgpu::Buffer>,
+    access: StorageAccess,
+    len: usize,
+    datatype: PhantomData<T>
+}
+
+impl<T: bytemuck::Zeroable + bytemuck::Pod> StorageRemote<T> {
+    pub fn new(device: &wgpu::Device, data: &[T], access: StorageAccess) -> Self {
+        Self {
+            buffer: create_buffer(device, bytemuck::cast_slice(data)),
+            access,
+            len: data.len(),
+            datatype: Default::default()
+        }
+    }
+
+    /// A buffer of `len` zeroed elements.
+    pub fn zeroed(device: &wgpu::Device, len: usize, access: StorageAccess) -> Self {
+        Self::new(device, &vec![T::zeroed(); len], access)
+    }
+
+    pub fn get_buffer(&self) -> &wgpu::Buffer {
+        self.buffer.as_ref()
+    }
+
+    /// The number of elements in the buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Writes to the buffer, the offset is sized as if indexing `&[T]`\
+    /// `queue.write_buffer()` Fails here if the size of `data` overruns the size of the buffer.
+    pub fn write(&self, queue: &wgpu::Queue, index_offset: usize, data: &[T]) {
+        queue.write_buffer(self.buffer.as_ref(), (index_offset * std::mem::size_of::<T>()) as u64, bytemuck::cast_slice(data))
+    }
+
+    /// A `Binder` for the whole buffer with its access.
+    pub fn binder(&self, binding: u32, visibility: wgpu::ShaderStages) -> Binder<'_> {
+        storage_binder(&self.buffer, self.access, binding, visibility)
+    }
+
+    /// Read every element from the GPU.\
+    /// The future doesn't poll the device, see `StorageBuffer::read_back`.
+    pub fn read_back(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> impl Future<Output = Result<Vec<T>>> + 'static {
+        read_buffer(device, queue, &self.buffer, self.len)
+    }
+
+    /// Like `read_back`, but blocks the thread until the GPU has finished its work.
+    pub fn read_back_blocking(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Vec<T>> {
+        wait_for(device, self.read_back(device, queue))
+    }
+}
@@ -3,6 +3,8 @@ use std::ops::{Deref, DerefMut, Index, IndexMut, Range};
 use std::sync::Arc;
 use wgpu::util::DeviceExt;
 
+use super::layout::WgslType;
+
 /// A smart pointer that synchronizes a uniform buffer.
 pub struct Uniform<T: bytemuck::Zeroable + bytemuck::Pod> {
     buffer: Arc<wgpu::Buffer>,
@@ -37,6 +39,31 @@ impl<T: bytemuck::Zeroable + bytemuck::Pod> Uniform<T> {
         }
     }
 
+    /// Like `new`, but fails to compile unless `T` matches its WGSL layout and follows the uniform address space's rules.
+    ///
+    /// ```compile_fail
+    /// # use wgpu_quick::buffer::{layout::Vec3, uniform::Uniform};
+    /// // `array<vec3<f32>, 4>` has a 16 byte stride, the Rust array a 12 byte one.
+    /// fn points(device: &wgpu::Device) -> Uniform<[Vec3<f32>; 4]> {
+    ///     Uniform::new_wgsl(device, [Vec3([1.0, 2.0, 3.0]); 4])
+    /// }
+    /// # fn main() { std::hint::black_box(points as fn(&wgpu::Device) -> _); }
+    /// ```
+    ///
+    /// ```compile_fail
+    /// # use wgpu_quick::buffer::uniform::Uniform;
+    /// // Uniform arrays need a stride that is a multiple of 16.
+    /// fn weights(device: &wgpu::Device) -> Uniform<[f32; 4]> {
+    ///     Uniform::new_wgsl(device, [1.0; 4])
+    /// }
+    /// # fn main() { std::hint::black_box(weights as fn(&wgpu::Device) -> _); }
+    /// ```
+    pub fn new_wgsl(device: &wgpu::Device, data: T) -> Self where T: WgslType {
+        const { assert!(T::LAYOUT_MATCHES, "the type can't match its WGSL layout, e.g. an array of `Vec3`") };
+        const { assert!(T::UNIFORM, "the type breaks the uniform address space's layout rules, e.g. an array stride that isn't a multiple of 16") };
+        Self::new(device, data)
+    }
+
     pub fn get_buffer(&self) -> &wgpu::Buffer {
         self.buffer.as_ref()
     }
@@ -46,10 +73,65 @@ impl<T: bytemuck::Zeroable + bytemuck::Pod> Uniform<T> {
     }
 }
 
-/// Creates a uniform that can be buffered to in sized chunks, from any index in the data to any index in the uniform.
+/// Elements in a uniform array need a 16 byte stride and a Rust size equal to it.
+const fn assert_uniform_array<T: WgslType>() {
+    const { assert!(<[T; 1] as WgslType>::UNIFORM && <[T; 1] as WgslType>::LAYOUT_MATCHES, "uniform array elements need a size that is a multiple of 16 bytes, e.g. `Vec4` instead of `Vec3` or `f32`") };
+}
+
+/// Which elements of a `UniformChunk` changed since the last flush, one bit per element.
+#[derive(Clone, Debug, Default)]
+struct DirtySet {
+    words: Vec<u64>,
+}
+
+impl DirtySet {
+    fn mark(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        let last_word = (range.end - 1) / 64;
+        if self.words.len() <= last_word {
+            self.words.resize(last_word + 1, 0);
+        }
+        for index in range {
+            self.words[index / 64] |= 1 << (index % 64);
+        }
+    }
+
+    /// The dirty elements as sorted ranges, merging ranges at most `merge_gap` clean elements apart.
+    fn ranges(&self, merge_gap: usize) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = vec![];
+        for (w, &word) in self.words.iter().enumerate() {
+            let mut word = word;
+            while word != 0 {
+                let index = w * 64 + word.trailing_zeros() as usize;
+                word &= word - 1;
+                match ranges.last_mut() {
+                    Some(last) if index <= last.end + merge_gap => last.end = index + 1,
+                    _ => ranges.push(index..index + 1),
+                }
+            }
+        }
+        ranges
+    }
+
+    fn clear(&mut self) {
+        self.words.iter_mut().for_each(|word| *word = 0);
+    }
+}
+
+/// Creates a uniform that can be buffered to in sized chunks, from any index in the data to any index in the uniform.\
+/// Writing through indexing (`chunk[i] = ..`, `chunk[a..b].copy_from_slice(..)`) records the changed elements,
+/// and `flush` uploads just those. Other mutable access through `DerefMut`, like `iter_mut` or `push`, marks every
+/// element the data holds at the next flush.
 pub struct UniformChunk<T: bytemuck::Zeroable + bytemuck::Pod> {
     buffer: Arc<wgpu::Buffer>,
+    usage: wgpu::BufferUsages,
     data: Vec<T>,
+    dirty: DirtySet,
+    /// Set by `DerefMut`, whose changes (including the length) are only known once it's done.
+    dirty_all: bool,
+    merge_gap: usize,
 }
 
 impl<T: bytemuck::Zeroable + bytemuck::Pod> Deref for UniformChunk<T> {
@@ -63,23 +145,87 @@ impl<T: bytemuck::Zeroable + bytemuck::Pod> Deref for UniformChunk<T> {
 
 impl<T: bytemuck::Zeroable + bytemuck::Pod> DerefMut for UniformChunk<T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
+        self.dirty_all = true;
         &mut self.data
     }
 }
 
+impl<T: bytemuck::Zeroable + bytemuck::Pod> Index<usize> for UniformChunk<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.data[index]
+    }
+}
+
+impl<T: bytemuck::Zeroable + bytemuck::Pod> IndexMut<usize> for UniformChunk<T> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        self.dirty.mark(index..index + 1);
+        &mut self.data[index]
+    }
+}
+
+impl<T: bytemuck::Zeroable + bytemuck::Pod> Index<Range<usize>> for UniformChunk<T> {
+    type Output = [T];
+
+    fn index(&self, range: Range<usize>) -> &Self::Output {
+        &self.data[range]
+    }
+}
+
+impl<T: bytemuck::Zeroable + bytemuck::Pod> IndexMut<Range<usize>> for UniformChunk<T> {
+    fn index_mut(&mut self, range: Range<usize>) -> &mut Self::Output {
+        self.dirty.mark(range.clone());
+        &mut self.data[range]
+    }
+}
+
+/// What `UniformChunk::flush` did.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flush {
+    /// The changed elements were uploaded with this many `write_buffer` calls.
+    Written(usize),
+    /// The data outgrew the buffer, so it was replaced by a larger one holding every element.\
+    /// Bind groups made with the old buffer have to be recreated from `get_buffer`.
+    Reallocated,
+}
+
 impl<T: bytemuck::Zeroable + bytemuck::Pod> UniformChunk<T> {
     pub fn new(device: &wgpu::Device, data: Vec<T>) -> Self {
+        Self::with_usage(device, data, wgpu::BufferUsages::empty())
+    }
+
+    /// Like `new`, with usages on top of `UNIFORM | COPY_DST`, e.g. `COPY_SRC` to copy the buffer out.
+    pub fn with_usage(device: &wgpu::Device, data: Vec<T>, usage: wgpu::BufferUsages) -> Self {
+        let usage = usage | wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST;
         let buffer = Arc::new(device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
             label: None,
             contents: bytemuck::cast_slice(data.as_slice()),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+            usage
         }));
         Self {
             buffer,
-            data
+            usage,
+            data,
+            dirty: DirtySet::default(),
+            dirty_all: false,
+            merge_gap: 8,
         }
     }
 
+    /// Like `new`, but fails to compile unless the data is a valid `array<T>` in the uniform address space.
+    pub fn new_wgsl(device: &wgpu::Device, data: Vec<T>) -> Self where T: WgslType {
+        assert_uniform_array::<T>();
+        Self::new(device, data)
+    }
+
+    /// How many unchanged elements `flush` may upload to merge two changed ranges into one write, 8 by default.\
+    /// Larger gaps mean fewer `write_buffer` calls but more bytes uploaded.
+    pub fn merge_gap(mut self, merge_gap: usize) -> Self {
+        self.merge_gap = merge_gap;
+        self
+    }
+
     pub fn get_buffer(&self) -> &wgpu::Buffer {
         self.buffer.as_ref()
     }
@@ -96,6 +242,57 @@ impl<T: bytemuck::Zeroable + bytemuck::Pod> UniformChunk<T> {
         queue.write_buffer(self.buffer.as_ref(), 0, bytemuck::cast_slice(self.data.as_slice()))
     }
 
+    /// The ranges of elements `flush` would upload, changed elements merged by the merge gap.
+    pub fn dirty_ranges(&self) -> Vec<Range<usize>> {
+        if self.dirty_all {
+            return Some(0..self.data.len()).filter(|all| !all.is_empty()).into_iter().collect();
+        }
+        self.dirty.ranges(self.merge_gap)
+    }
+
+    /// Forget the changes recorded for `flush`, e.g. after uploading them with `sync_all`.
+    pub fn clear_dirty(&mut self) {
+        self.dirty.clear();
+        self.dirty_all = false;
+    }
+
+    /// The number of elements the buffer has room for.
+    pub fn capacity(&self) -> usize {
+        (self.buffer.size() / std::mem::size_of::<T>().max(1) as u64) as usize
+    }
+
+    /// Upload the elements changed since the last flush to the same indices in the uniform, in as few
+    /// `write_buffer` calls as the merge gap allows, then clear the changes.\
+    /// If elements were pushed past the buffer's capacity, the buffer is replaced by one twice as large
+    /// (or as large as the data) holding every element, see `Flush::Reallocated`.
+    pub fn flush(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> Flush {
+        if self.data.len() > self.capacity() {
+            let capacity = self.data.len().max(self.capacity() * 2);
+            let mut contents = vec![T::zeroed(); capacity];
+            contents[..self.data.len()].copy_from_slice(&self.data);
+            self.buffer = Arc::new(device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
+                label: None,
+                contents: bytemuck::cast_slice(contents.as_slice()),
+                usage: self.usage
+            }));
+            self.clear_dirty();
+            return Flush::Reallocated;
+        }
+
+        let mut writes = 0;
+        for range in self.dirty_ranges() {
+            // Elements removed from the data since they were changed have nothing left to upload.
+            let range = range.start..range.end.min(self.data.len());
+            if range.is_empty() {
+                continue;
+            }
+            queue.write_buffer(self.buffer.as_ref(), (range.start * std::mem::size_of::<T>()) as u64, bytemuck::cast_slice(&self.data[range]));
+            writes += 1;
+        }
+        self.clear_dirty();
+        Flush::Written(writes)
+    }
+
 }
 
 
@@ -121,6 +318,12 @@ impl<T: bytemuck::Zeroable + bytemuck::Pod> UniformRemote<T> {
         }
     }
 
+    /// Like `new`, but fails to compile unless the data is a valid `array<T>` in the uniform address space.
+    pub fn new_wgsl(device: &wgpu::Device, data: &[T]) -> Self where T: WgslType {
+        assert_uniform_array::<T>();
+        Self::new(device, data)
+    }
+
     pub fn get_buffer(&self) -> &wgpu::Buffer {
         self.buffer.as_ref()
     }
@@ -132,3 +335,22 @@ impl<T: bytemuck::Zeroable + bytemuck::Pod> UniformRemote<T> {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dirty_ranges_merge_within_the_gap() {
+        let mut dirty = DirtySet::default();
+        dirty.mark(3..4);
+        dirty.mark(5..7);
+        dirty.mark(63..66);
+        dirty.mark(200..201);
+        assert_eq!(dirty.ranges(0), vec![3..4, 5..7, 63..66, 200..201]);
+        assert_eq!(dirty.ranges(1), vec![3..7, 63..66, 200..201]);
+        assert_eq!(dirty.ranges(56), vec![3..66, 200..201]);
+        dirty.clear();
+        assert!(dirty.ranges(0).is_empty());
+    }
+}
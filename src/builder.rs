@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+use winit::dpi::PhysicalSize;
+
+use crate::{Attachments, BackendSelection, Backends, Error, GpuContext, HeadlessTarget, RenderTarget, State, WindowSurface, RWH};
+use crate::window::raw_window_id;
+
+/// Collects the adapter, device and surface options used to create a `State`.\
+/// `State::new_winit`, `State::new_raw` and `State::new_headless` are shorthands for a default builder.
+pub struct StateBuilder {
+    backends: Vec<Backends>,
+    power_preference: wgpu::PowerPreference,
+    force_fallback_adapter: bool,
+    required_features: wgpu::Features,
+    optional_features: wgpu::Features,
+    limits: Option<wgpu::Limits>,
+    present_mode: Option<wgpu::PresentMode>,
+    alpha_mode: Option<wgpu::CompositeAlphaMode>,
+    format_preference: Vec<wgpu::TextureFormat>,
+    device_label: Option<String>,
+    attachments: Attachments,
+    surface_usage: wgpu::TextureUsages,
+}
+
+impl Default for StateBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Requested unless `StateBuilder::optional_features` replaces them, so compressed textures are used as they are where possible.
+const DEFAULT_OPTIONAL_FEATURES: wgpu::Features = wgpu::Features::TEXTURE_COMPRESSION_BC
+    .union(wgpu::Features::TEXTURE_COMPRESSION_ETC2)
+    .union(wgpu::Features::TEXTURE_COMPRESSION_ASTC_LDR);
+
+impl StateBuilder {
+
+    pub fn new() -> Self {
+        Self {
+            backends: vec![Backends::ALL],
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter: false,
+            required_features: wgpu::Features::empty(),
+            optional_features: DEFAULT_OPTIONAL_FEATURES,
+            limits: None,
+            present_mode: None,
+            alpha_mode: None,
+            format_preference: vec![],
+            device_label: None,
+            attachments: Attachments::default(),
+            surface_usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        }
+    }
+
+    /// The backends to request an adapter from.
+    pub fn backend(mut self, backend: Backends) -> Self {
+        self.backends = vec![backend];
+        self
+    }
+
+    /// An ordered preference list of backends, e.g. `[Backends::VULKAN, Backends::GL]`.\
+    /// Each one is tried in turn until an adapter and device are created,\
+    /// `GpuContext::backend` records which one was chosen and why the earlier ones were rejected.
+    pub fn backends(mut self, backends: &[Backends]) -> Self {
+        self.backends = backends.to_vec();
+        self
+    }
+
+    pub fn power_preference(mut self, power_preference: wgpu::PowerPreference) -> Self {
+        self.power_preference = power_preference;
+        self
+    }
+
+    /// Only use the fallback (software) adapter, e.g. for reproducible rendering in tests.
+    pub fn force_fallback_adapter(mut self, force_fallback_adapter: bool) -> Self {
+        self.force_fallback_adapter = force_fallback_adapter;
+        self
+    }
+
+    /// Features the device must have, device creation fails without them.
+    pub fn required_features(mut self, features: wgpu::Features) -> Self {
+        self.required_features |= features;
+        self
+    }
+
+    /// Features that are enabled only if the adapter supports them, replacing the default texture compression features
+    /// (BC, ETC2 and ASTC LDR) that let compressed textures be uploaded without decoding them on the CPU.\
+    /// Check `GpuContext::optional_features` for the ones that were enabled.
+    pub fn optional_features(mut self, features: wgpu::Features) -> Self {
+        self.optional_features = features;
+        self
+    }
+
+    /// The device limits, defaults to `wgpu::Limits::downlevel_webgl2_defaults()` with the adapter's resolution.
+    pub fn limits(mut self, limits: wgpu::Limits) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+
+    /// The surface present mode, defaults to the first one the surface supports.\
+    /// Building fails with `Error::UnsupportedPresentMode` if the surface doesn't support it.
+    pub fn present_mode(mut self, present_mode: wgpu::PresentMode) -> Self {
+        self.present_mode = Some(present_mode);
+        self
+    }
+
+    /// The surface alpha mode, defaults to the first one the surface supports.\
+    /// Building fails with `Error::UnsupportedAlphaMode` if the surface doesn't support it.
+    pub fn alpha_mode(mut self, alpha_mode: wgpu::CompositeAlphaMode) -> Self {
+        self.alpha_mode = Some(alpha_mode);
+        self
+    }
+
+    /// Prefer a surface format, formats are tried in the order they are added.\
+    /// Falls back to the first format the surface supports.
+    pub fn prefer_surface_format(mut self, format: wgpu::TextureFormat) -> Self {
+        self.format_preference.push(format);
+        self
+    }
+
+    /// Give every target a depth buffer of this format, e.g. `DepthTexture::DEFAULT_FORMAT`.\
+    /// It is recreated whenever the target is resized.
+    pub fn depth_format(mut self, format: wgpu::TextureFormat) -> Self {
+        self.attachments.depth_format = Some(format);
+        self
+    }
+
+    /// Render with this many samples per pixel, resolving into the surface.\
+    /// Validated against the adapter's format features when the `State` is built. Counts other than 1 and 4
+    /// need `wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES` in the required or optional features,
+    /// building fails with `Error::UnsupportedSampleCount` without it.
+    pub fn sample_count(mut self, sample_count: u32) -> Self {
+        self.attachments.sample_count = sample_count;
+        self
+    }
+
+    /// Extra usages for window surfaces, e.g. `COPY_SRC` to capture frames with `Frame::capture`.\
+    /// Not every backend supports more than `RENDER_ATTACHMENT`.
+    pub fn surface_usage(mut self, usage: wgpu::TextureUsages) -> Self {
+        self.surface_usage |= usage;
+        self
+    }
+
+    pub fn device_label(mut self, label: &str) -> Self {
+        self.device_label = Some(label.to_string());
+        self
+    }
+
+    /// Request an adapter and device, retrying with the fallback (software) adapter if no hardware adapter is found.
+    async fn request_device(&self, instance: &wgpu::Instance, compatible_surface: Option<&wgpu::Surface>) -> Result<(wgpu::Adapter, wgpu::Device, wgpu::Queue, wgpu::Features), Error> {
+        let mut adapter = None;
+        let attempts: &[bool] = if self.force_fallback_adapter { &[true] } else { &[false, true] };
+        for &force_fallback_adapter in attempts {
+            adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: self.power_preference,
+                    force_fallback_adapter,
+                    // Request an adapter which can render to our surface, if there is one
+                    compatible_surface,
+                })
+                .await;
+            if adapter.is_some() {
+                break;
+            }
+        }
+        let adapter = adapter.ok_or(Error::NoAdapter)?;
+
+        let optional_features = self.optional_features & adapter.features();
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: self.device_label.as_deref(),
+                    features: self.required_features | optional_features,
+                    limits: match &self.limits {
+                        Some(limits) => limits.clone(),
+                        None => wgpu::Limits::downlevel_webgl2_defaults()
+                            .using_resolution(adapter.limits()),
+                    },
+                },
+                None,
+            )
+            .await?;
+
+        Ok((adapter, device, queue, optional_features))
+    }
+
+    /// Create a surface, adapter and device on a single backend and configure the surface.
+    async fn try_backend(&self, backend: &Backends, target: &TargetSource<'_>) -> Result<State, Error> {
+        let instance = wgpu::Instance::new(backend.clone().gen_instance_desc());
+
+        let (size, scalefactor) = match target {
+            TargetSource::Winit(window) => (window.inner_size(), window.scale_factor()),
+            TargetSource::Raw(_, size, scalefactor) => (*size, *scalefactor),
+            TargetSource::Headless(size, _) => (*size, 1.0),
+        };
+
+        let surface = match target {
+            TargetSource::Winit(window) => Some(unsafe { instance.create_surface(*window) }?),
+            TargetSource::Raw(handle, _, _) => Some(unsafe { instance.create_surface(handle) }?),
+            TargetSource::Headless(_, _) => None,
+        };
+
+        let (adapter, device, queue, optional_features) = self.request_device(&instance, surface.as_ref()).await?;
+
+        let context = GpuContext {
+            backend: BackendSelection {
+                requested: backend.clone(),
+                backend: adapter.get_info().backend,
+                rejected: vec![],
+            },
+            instance,
+            adapter,
+            device,
+            queue,
+            optional_features,
+        };
+
+        let config = match (&surface, target) {
+            (Some(surface), _) => self.surface_config(surface, &context.adapter, size)?,
+            (None, TargetSource::Headless(_, format)) => wgpu::SurfaceConfiguration {
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::TEXTURE_BINDING,
+                format: *format,
+                width: size.width,
+                height: size.height,
+                present_mode: wgpu::PresentMode::Fifo,
+                alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+                view_formats: vec![],
+            },
+            (None, _) => unreachable!(),
+        };
+
+        self.attachments.validate(&context.adapter, &context.device, config.format)?;
+
+        let mut windows = HashMap::new();
+        let target = match surface {
+            Some(surface) => {
+                let id = match target {
+                    TargetSource::Winit(window) => window.id(),
+                    _ => raw_window_id(),
+                };
+                windows.insert(id, WindowSurface::new(&context.device, surface, config, scalefactor, &self.attachments));
+                RenderTarget::Window(id)
+            }
+            None => RenderTarget::Headless(Box::new(HeadlessTarget::new(&context.device, config, &self.attachments))),
+        };
+
+        Ok(State {
+            context,
+            windows,
+            target,
+            attachments: self.attachments,
+        })
+    }
+
+    fn surface_config(&self, surface: &wgpu::Surface, adapter: &wgpu::Adapter, size: PhysicalSize<u32>) -> Result<wgpu::SurfaceConfiguration, Error> {
+        let swapchain_capabilities = surface.get_capabilities(adapter);
+        if swapchain_capabilities.formats.is_empty()
+            || swapchain_capabilities.present_modes.is_empty()
+            || swapchain_capabilities.alpha_modes.is_empty() {
+            return Err(Error::EmptySurfaceCapabilities);
+        }
+
+        let present_mode = match self.present_mode {
+            Some(requested) if !swapchain_capabilities.present_modes.contains(&requested) => {
+                return Err(Error::UnsupportedPresentMode {
+                    requested,
+                    supported: swapchain_capabilities.present_modes,
+                });
+            }
+            Some(requested) => requested,
+            None => swapchain_capabilities.present_modes[0],
+        };
+
+        let alpha_mode = match self.alpha_mode {
+            Some(requested) if !swapchain_capabilities.alpha_modes.contains(&requested) => {
+                return Err(Error::UnsupportedAlphaMode {
+                    requested,
+                    supported: swapchain_capabilities.alpha_modes,
+                });
+            }
+            Some(requested) => requested,
+            None => swapchain_capabilities.alpha_modes[0],
+        };
+
+        let format = self.format_preference
+            .iter()
+            .find(|format| swapchain_capabilities.formats.contains(format))
+            .cloned()
+            .unwrap_or(swapchain_capabilities.formats[0]);
+
+        Ok(wgpu::SurfaceConfiguration {
+            usage: self.surface_usage,
+            format,
+            width: size.width,
+            height: size.height,
+            present_mode,
+            alpha_mode,
+            view_formats: vec![],
+        })
+    }
+
+    /// Try each backend in order until one of them gives a working `State`.
+    async fn build(self, target: TargetSource<'_>) -> Result<State, Error> {
+        let mut rejected = vec![];
+        for backend in &self.backends {
+            match self.try_backend(backend, &target).await {
+                Ok(mut state) => {
+                    state.context.backend.rejected = rejected;
+                    return Ok(state);
+                }
+                Err(e) => rejected.push((backend.clone(), e)),
+            }
+        }
+        if rejected.len() == 1 {
+            Err(rejected.remove(0).1)
+        } else {
+            Err(Error::NoBackend(rejected))
+        }
+    }
+
+    /// Create a `State` that renders to a winit window.
+    pub async fn build_winit(self, window: &winit::window::Window) -> Result<State, Error> {
+        self.build(TargetSource::Winit(window)).await
+    }
+
+    /// Create a `State` that renders to a raw window handle with window size and scale factor.
+    pub async fn build_raw(self, handle: raw_window_handle::RawWindowHandle, win_size: (u32,u32), scalefactor: f64) -> Result<State, Error> {
+        let size = PhysicalSize::new(win_size.0,win_size.1);
+        self.build(TargetSource::Raw(RWH {handle}, size, scalefactor)).await
+    }
+
+    /// Create a windowless `State` that renders into an offscreen texture of the given size and format.\
+    /// The present mode, alpha mode and surface format preference are ignored.
+    pub async fn build_headless(self, width: u32, height: u32, format: wgpu::TextureFormat) -> Result<State, Error> {
+        self.build(TargetSource::Headless(PhysicalSize::new(width, height), format)).await
+    }
+}
+
+/// What a `State` is being built for.
+enum TargetSource<'a> {
+    Winit(&'a winit::window::Window),
+    Raw(RWH, PhysicalSize<u32>, f64),
+    Headless(PhysicalSize<u32>, wgpu::TextureFormat),
+}
@@ -0,0 +1,170 @@
+use anyhow::*;
+
+use crate::frame::Frame;
+use crate::texture::Texture;
+use crate::{RenderTarget, State};
+
+/// Bytes per row of a texture copy, padded to `wgpu::COPY_BYTES_PER_ROW_ALIGNMENT`.
+pub(crate) fn padded_bytes_per_row(width: u32, bytes_per_pixel: u32) -> u32 {
+    wgpu::util::align_to(width * bytes_per_pixel, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+}
+
+/// How the texels of a readable format are turned into 8-bit RGBA.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Texels {
+    Rgba8,
+    Bgra8,
+    /// Clamped to `0.0..=1.0` and quantized.
+    Rgba32Float,
+}
+
+/// Strip the row padding from a mapped copy of a texture and convert its texels to 8-bit RGBA.
+fn unpad_rows(padded: &[u8], width: u32, height: u32, padded_row: u32, texels: Texels) -> Vec<u8> {
+    let bytes_per_pixel = if texels == Texels::Rgba32Float { 16 } else { 4 };
+    let row = (width * bytes_per_pixel) as usize;
+    let mut pixels = Vec::with_capacity(row * height as usize);
+    for y in 0..height as usize {
+        let start = y * padded_row as usize;
+        pixels.extend_from_slice(&padded[start..start + row]);
+    }
+    match texels {
+        Texels::Rgba8 => pixels,
+        Texels::Bgra8 => {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+            pixels
+        }
+        Texels::Rgba32Float => pixels.chunks_exact(4)
+            .map(|v| (f32::from_le_bytes([v[0], v[1], v[2], v[3]]).clamp(0.0, 1.0) * 255.0).round() as u8)
+            .collect(),
+    }
+}
+
+/// Copy a mip level of a texture's first layer into a mapped buffer and read it back as an image.\
+/// The texture needs `COPY_SRC` usage and an 8-bit RGBA or BGRA format, or `Rgba32Float`.
+pub(crate) fn read_texture(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture, level: u32) -> Result<image::RgbaImage> {
+    let (texels, bytes_per_pixel) = match texture.format() {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => (Texels::Rgba8, 4),
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => (Texels::Bgra8, 4),
+        wgpu::TextureFormat::Rgba32Float => (Texels::Rgba32Float, 16),
+        format => bail!("Can't read back a texture of format {:?}, only 8-bit RGBA and BGRA and Rgba32Float are supported", format),
+    };
+    if !texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+        bail!("Can't read back a texture without COPY_SRC usage");
+    }
+    if level >= texture.mip_level_count() {
+        bail!("Can't read back mip level {} of a texture with {} levels", level, texture.mip_level_count());
+    }
+
+    let width = (texture.width() >> level).max(1);
+    let height = (texture.height() >> level).max(1);
+    let padded_row = padded_bytes_per_row(width, bytes_per_pixel);
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("wgpu_quick readback buffer"),
+        size: (padded_row * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: level,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(padded_row),
+                rows_per_image: std::num::NonZeroU32::new(height),
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1
+        },
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver.recv()??;
+
+    let pixels = unpad_rows(&slice.get_mapped_range(), width, height, padded_row, texels);
+    buffer.unmap();
+
+    image::RgbaImage::from_raw(width, height, pixels).context("Readback size does not match the texture size")
+}
+
+impl Texture {
+
+    /// Read the texture back to the CPU.\
+    /// Needs `COPY_SRC` usage and an 8-bit RGBA or BGRA format, or `Rgba32Float`. BGRA is swizzled to RGBA,
+    /// floats are clamped to `0.0..=1.0`.
+    pub fn read_to_image(&self, state: &State) -> Result<image::RgbaImage> {
+        read_texture(&state.device, &state.queue, &self.texture, 0)
+    }
+
+    /// Like `read_to_image`, for one mip level of the first layer.
+    pub fn read_level_to_image(&self, state: &State, level: u32) -> Result<image::RgbaImage> {
+        read_texture(&state.device, &state.queue, &self.texture, level)
+    }
+}
+
+impl Frame {
+
+    /// Read this frame back to the CPU, after its commands are submitted and before it is presented.\
+    /// Window surfaces need `COPY_SRC` usage, see `StateBuilder::surface_usage`.
+    pub fn capture(&self, state: &State) -> Result<image::RgbaImage> {
+        match self.surface_texture() {
+            Some(surface_texture) => read_texture(&state.device, &state.queue, &surface_texture.texture, 0),
+            None => state.capture_frame(),
+        }
+    }
+}
+
+impl State {
+
+    /// Read the last rendered frame of a headless state back to the CPU.\
+    /// Window surfaces can't be read after presenting, use `Frame::capture` for them instead.
+    pub fn capture_frame(&self) -> Result<image::RgbaImage> {
+        match &self.target {
+            RenderTarget::Headless(headless) => read_texture(&self.device, &self.queue, &headless.texture, 0),
+            RenderTarget::Window(_) => bail!("A window's frame can only be captured before it is presented, use `Frame::capture`"),
+            RenderTarget::Closed(_) => bail!("The state has no windows left to capture"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_are_padded_to_256_bytes() {
+        assert_eq!(padded_bytes_per_row(1, 4), 256);
+        assert_eq!(padded_bytes_per_row(64, 4), 256);
+        assert_eq!(padded_bytes_per_row(65, 4), 512);
+    }
+
+    #[test]
+    fn unpadding_drops_padding_and_swizzles_bgra() {
+        let padded_row = padded_bytes_per_row(2, 4);
+        let mut padded = vec![0xffu8; (padded_row * 2) as usize];
+        padded[..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        padded[padded_row as usize..padded_row as usize + 8].copy_from_slice(&[9, 10, 11, 12, 13, 14, 15, 16]);
+
+        assert_eq!(unpad_rows(&padded, 2, 2, padded_row, Texels::Rgba8), vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16]);
+        assert_eq!(unpad_rows(&padded, 2, 2, padded_row, Texels::Bgra8), vec![3, 2, 1, 4, 7, 6, 5, 8, 11, 10, 9, 12, 15, 14, 13, 16]);
+    }
+}
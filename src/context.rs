@@ -0,0 +1,14 @@
+use crate::BackendSelection;
+
+/// The instance, adapter, device and queue of a `State`.\
+/// Shared by every window, so resources created once (textures, uniforms, pipelines) can be used in all of them.
+pub struct GpuContext {
+    pub instance: wgpu::Instance,
+    pub adapter: wgpu::Adapter,
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    /// The optional features requested through `StateBuilder` that the adapter supported.
+    pub optional_features: wgpu::Features,
+    /// The backend this context was created with.
+    pub backend: BackendSelection,
+}
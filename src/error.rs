@@ -0,0 +1,91 @@
+use std::fmt;
+
+/// Errors that can occur while initializing or rendering with a `State`.
+#[derive(Debug)]
+pub enum Error {
+    /// The window surface could not be created.
+    CreateSurface(wgpu::CreateSurfaceError),
+    /// No adapter was found for the requested backends, including the fallback adapter.
+    NoAdapter,
+    /// The adapter refused to create a device, usually because of unsupported features or limits.
+    RequestDevice(wgpu::RequestDeviceError),
+    /// The requested present mode is not supported by the surface.
+    UnsupportedPresentMode {
+        requested: wgpu::PresentMode,
+        supported: Vec<wgpu::PresentMode>,
+    },
+    /// The requested alpha mode is not supported by the surface.
+    UnsupportedAlphaMode {
+        requested: wgpu::CompositeAlphaMode,
+        supported: Vec<wgpu::CompositeAlphaMode>,
+    },
+    /// The surface does not support the format the `State` renders with.
+    UnsupportedSurfaceFormat {
+        requested: wgpu::TextureFormat,
+        supported: Vec<wgpu::TextureFormat>,
+    },
+    /// The adapter can't multisample (and resolve) this format with this many samples.
+    UnsupportedSampleCount {
+        count: u32,
+        format: wgpu::TextureFormat,
+    },
+    /// The surface reported no formats, present modes or alpha modes for the adapter.
+    EmptySurfaceCapabilities,
+    /// Every backend in the preference list failed, in the order they were tried.
+    NoBackend(Vec<(crate::Backends, Error)>),
+    /// There is no memory left to acquire a surface frame.
+    SurfaceOutOfMemory,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::CreateSurface(e) => write!(f, "could not create surface: {}", e),
+            Error::NoAdapter => write!(f, "failed to find an appropriate adapter"),
+            Error::RequestDevice(e) => write!(f, "failed to create device: {}", e),
+            Error::UnsupportedPresentMode { requested, supported } => {
+                write!(f, "present mode {:?} is not supported, the surface supports {:?}", requested, supported)
+            }
+            Error::UnsupportedAlphaMode { requested, supported } => {
+                write!(f, "alpha mode {:?} is not supported, the surface supports {:?}", requested, supported)
+            }
+            Error::UnsupportedSurfaceFormat { requested, supported } => {
+                write!(f, "surface format {:?} is not supported, the surface supports {:?}", requested, supported)
+            }
+            Error::UnsupportedSampleCount { count, format } => {
+                write!(f, "{} samples per pixel are not supported for {:?}", count, format)
+            }
+            Error::EmptySurfaceCapabilities => write!(f, "the surface is not compatible with the adapter"),
+            Error::NoBackend(rejected) => {
+                write!(f, "no backend could be initialized")?;
+                for (backend, e) in rejected {
+                    write!(f, "\n  {:?}: {}", backend, e)?;
+                }
+                Ok(())
+            }
+            Error::SurfaceOutOfMemory => write!(f, "out of memory while acquiring a surface frame"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::CreateSurface(e) => Some(e),
+            Error::RequestDevice(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<wgpu::CreateSurfaceError> for Error {
+    fn from(e: wgpu::CreateSurfaceError) -> Self {
+        Error::CreateSurface(e)
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for Error {
+    fn from(e: wgpu::RequestDeviceError) -> Self {
+        Error::RequestDevice(e)
+    }
+}
@@ -0,0 +1,111 @@
+use winit::window::WindowId;
+
+use crate::texture::{DepthTexture, MsaaTexture};
+use crate::{Error, RenderTarget, State, WindowSurface};
+
+/// A frame acquired from `State::acquire_frame`.\
+/// Owns the surface texture and a view of it, and presents it when dropped or on `present()`.
+pub struct Frame {
+    surface_texture: Option<wgpu::SurfaceTexture>,
+    /// A view to render to. This is the multisampled target when the `State` uses MSAA, otherwise the frame's texture.
+    pub view: wgpu::TextureView,
+    /// The frame's texture when the `State` uses MSAA, pass it as the color attachment's `resolve_target`.
+    pub resolve_target: Option<wgpu::TextureView>,
+    /// A view of the target's depth buffer, if it has one.
+    pub depth_view: Option<wgpu::TextureView>,
+}
+
+impl Frame {
+
+    fn new(surface_texture: Option<wgpu::SurfaceTexture>, target: wgpu::TextureView, depth: &Option<DepthTexture>, msaa: &Option<MsaaTexture>) -> Self {
+        let (view, resolve_target) = match msaa {
+            Some(msaa) => (msaa.texture.create_view(&wgpu::TextureViewDescriptor::default()), Some(target)),
+            None => (target, None),
+        };
+        Self {
+            surface_texture,
+            view,
+            resolve_target,
+            depth_view: depth.as_ref().map(|depth| depth.texture.create_view(&wgpu::TextureViewDescriptor::default())),
+        }
+    }
+
+    /// The acquired surface texture, `None` for a headless state.
+    pub fn surface_texture(&self) -> Option<&wgpu::SurfaceTexture> {
+        self.surface_texture.as_ref()
+    }
+
+    /// Present the frame now instead of when it is dropped.
+    pub fn present(mut self) {
+        if let Some(surface_texture) = self.surface_texture.take() {
+            surface_texture.present();
+        }
+    }
+}
+
+impl Drop for Frame {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            return;
+        }
+        if let Some(surface_texture) = self.surface_texture.take() {
+            surface_texture.present();
+        }
+    }
+}
+
+impl WindowSurface {
+
+    /// Acquire the next frame of this window.\
+    /// A lost or outdated surface is reconfigured and acquired again, a timeout skips the frame by returning `Ok(None)`.\
+    /// A minimized window also returns `Ok(None)`. Only running out of memory is an error.
+    pub fn acquire_frame(&self, device: &wgpu::Device) -> Result<Option<Frame>, Error> {
+        // The surface isn't configured while the window has no area.
+        if self.size.width == 0 || self.size.height == 0 {
+            return Ok(None);
+        }
+
+        let surface_texture = match self.surface.get_current_texture() {
+            Ok(surface_texture) => surface_texture,
+            Err(wgpu::SurfaceError::Lost) | Err(wgpu::SurfaceError::Outdated) => {
+                self.surface.configure(device, &self.config);
+                match self.surface.get_current_texture() {
+                    Ok(surface_texture) => surface_texture,
+                    Err(wgpu::SurfaceError::OutOfMemory) => return Err(Error::SurfaceOutOfMemory),
+                    // Still not usable, try again next frame.
+                    Err(_) => return Ok(None),
+                }
+            }
+            Err(wgpu::SurfaceError::Timeout) => return Ok(None),
+            Err(wgpu::SurfaceError::OutOfMemory) => return Err(Error::SurfaceOutOfMemory),
+        };
+
+        let view = surface_texture.texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Ok(Some(Frame::new(Some(surface_texture), view, &self.depth, &self.msaa)))
+    }
+}
+
+impl State {
+
+    /// Acquire the next frame of the main target, see `WindowSurface::acquire_frame`.\
+    /// A headless state always returns its offscreen target, a state without windows left `Ok(None)`.
+    pub fn acquire_frame(&self) -> Result<Option<Frame>, Error> {
+        match &self.target {
+            RenderTarget::Window(id) => self.acquire_window_frame(*id),
+            RenderTarget::Headless(headless) => {
+                let view = headless.texture.create_view(&wgpu::TextureViewDescriptor::default());
+                Ok(Some(Frame::new(None, view, &headless.depth, &headless.msaa)))
+            }
+            RenderTarget::Closed(_) => Ok(None),
+        }
+    }
+
+    /// Acquire the next frame of a window, `Ok(None)` if the state has no window with this id.
+    pub fn acquire_window_frame(&self, id: WindowId) -> Result<Option<Frame>, Error> {
+        match self.windows.get(&id) {
+            Some(window) => window.acquire_frame(&self.device),
+            None => Ok(None),
+        }
+    }
+}
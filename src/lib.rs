@@ -1,7 +1,12 @@
 use raw_window_handle::{RawDisplayHandle, UiKitDisplayHandle, AppKitDisplayHandle, OrbitalDisplayHandle, XcbDisplayHandle, WaylandDisplayHandle, DrmDisplayHandle, GbmDisplayHandle, WindowsDisplayHandle, WebDisplayHandle, AndroidDisplayHandle, HaikuDisplayHandle, XlibDisplayHandle};
-use winit::{dpi::PhysicalSize};
+use std::collections::HashMap;
+use std::ops::Deref;
+use texture::{DepthTexture, MsaaTexture};
+use winit::{dpi::{LogicalSize, PhysicalSize}, event::{Event, WindowEvent}, window::WindowId};
 
 pub use pollster;
+#[doc(hidden)]
+pub use paste;
 pub mod bytemuck;
 pub mod renderable;
 pub mod bindings;
@@ -14,8 +19,20 @@ pub mod utils;
 #[macro_use]
 pub mod init;
 pub mod pipelines;
+pub mod builder;
+pub mod error;
+pub mod frame;
+pub mod context;
+pub mod window;
+pub mod capture;
+pub mod testing;
 // pub mod init;
 
+pub use builder::StateBuilder;
+pub use error::Error;
+pub use context::GpuContext;
+pub use window::WindowSurface;
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -25,14 +42,139 @@ mod tests {
     }
 }
 
+/// A `GpuContext` and the windows (or offscreen texture) it renders to.\
+/// Dereferences to the `GpuContext`, so `state.device` and `state.queue` work directly.
 pub struct State {
-    pub scalefactor: f64,
-    pub instance: wgpu::Instance,
+    pub context: GpuContext,
+    /// Every window surface, by the id of its window.
+    pub windows: HashMap<WindowId, WindowSurface>,
+    /// The main target, which `config()`, `resize()` and `acquire_frame()` refer to.
+    pub target: RenderTarget,
+    /// The attachments every target gets alongside its color texture.
+    pub attachments: Attachments,
+}
+
+/// The attachments created and resized alongside every target of a `State`.
+#[derive(Clone, Copy, Debug)]
+pub struct Attachments {
+    /// The format of the depth buffer, `None` for no depth buffer.
+    pub depth_format: Option<wgpu::TextureFormat>,
+    /// The number of samples per pixel, above 1 renders to a multisampled target that resolves into the surface.
+    pub sample_count: u32,
+}
+
+impl Default for Attachments {
+    fn default() -> Self {
+        Self {
+            depth_format: None,
+            sample_count: 1,
+        }
+    }
+}
+
+impl Attachments {
+    fn depth_texture(&self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Option<DepthTexture> {
+        self.depth_format.map(|format| DepthTexture::new(device, config.width, config.height, format, self.sample_count))
+    }
+
+    fn msaa_texture(&self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Option<MsaaTexture> {
+        if self.sample_count > 1 {
+            Some(MsaaTexture::new(device, config.width, config.height, config.format, self.sample_count))
+        } else {
+            None
+        }
+    }
+
+    /// Check that the adapter can multisample the color and depth formats with `sample_count`.
+    fn validate(&self, adapter: &wgpu::Adapter, device: &wgpu::Device, color_format: wgpu::TextureFormat) -> Result<(), Error> {
+        if self.sample_count == 1 {
+            return Ok(());
+        }
+        // Counts other than 1 and 4 are only usable with adapter specific format features.
+        let adapter_specific = device.features().contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+        let supported = |format: wgpu::TextureFormat, resolve: bool| {
+            let flags = adapter.get_texture_format_features(format).flags;
+            flags.sample_count_supported(self.sample_count)
+                && (!resolve || flags.contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE))
+                && (self.sample_count == 4 || adapter_specific)
+        };
+        if !supported(color_format, true) {
+            return Err(Error::UnsupportedSampleCount { count: self.sample_count, format: color_format });
+        }
+        if let Some(depth_format) = self.depth_format {
+            if !supported(depth_format, false) {
+                return Err(Error::UnsupportedSampleCount { count: self.sample_count, format: depth_format });
+            }
+        }
+        Ok(())
+    }
+
+    /// A `MultisampleState` matching `sample_count`.
+    pub fn multisample_state(&self) -> wgpu::MultisampleState {
+        wgpu::MultisampleState {
+            count: self.sample_count,
+            ..Default::default()
+        }
+    }
+}
+
+impl Deref for State {
+    type Target = GpuContext;
+
+    fn deref(&self) -> &Self::Target {
+        &self.context
+    }
+}
+
+/// Where a `State` draws its frames to.
+pub enum RenderTarget {
+    /// One of the state's windows.
+    Window(WindowId),
+    /// An offscreen texture, used when there is no window.
+    Headless(Box<HeadlessTarget>),
+    /// Every window was removed. The last one's configuration is kept for `config()`,
+    /// the next window added becomes the main one.
+    Closed(wgpu::SurfaceConfiguration),
+}
+
+/// The offscreen texture that stands in for a surface in a headless `State`.
+pub struct HeadlessTarget {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
     pub config: wgpu::SurfaceConfiguration,
-    pub surface: wgpu::Surface,
-    pub adapter: wgpu::Adapter,
-    pub device: wgpu::Device,
-    pub queue: wgpu::Queue,
+    /// The depth buffer, if the `State` was built with a depth format.
+    pub depth: Option<DepthTexture>,
+    /// The multisampled color target, if the `State` was built with a sample count above 1.
+    pub msaa: Option<MsaaTexture>,
+}
+
+impl HeadlessTarget {
+    fn new(device: &wgpu::Device, config: wgpu::SurfaceConfiguration, attachments: &Attachments) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("wgpu_quick headless target"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: config.usage,
+            view_formats: &config.view_formats,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let depth = attachments.depth_texture(device, &config);
+        let msaa = attachments.msaa_texture(device, &config);
+        Self {
+            texture,
+            view,
+            config,
+            depth,
+            msaa
+        }
+    }
 }
 
 struct RWH {
@@ -68,6 +210,7 @@ unsafe impl raw_window_handle::HasRawDisplayHandle for RWH {
 }
 
 
+#[derive(Clone, Debug)]
 pub enum Backends {
     ALL,
     VULKAN,
@@ -78,6 +221,17 @@ pub enum Backends {
     BROWSER_WEBGPU
 }
 
+/// The outcome of trying a list of `Backends` in order.
+#[derive(Debug)]
+pub struct BackendSelection {
+    /// The entry of the preference list that succeeded.
+    pub requested: Backends,
+    /// The backend of the adapter that was picked.
+    pub backend: wgpu::Backend,
+    /// The entries tried before it, and why each one failed.
+    pub rejected: Vec<(Backends, Error)>,
+}
+
 impl Backends {
     fn gen_instance_desc(self) -> wgpu::InstanceDescriptor {
         wgpu::InstanceDescriptor {
@@ -100,98 +254,192 @@ impl Backends {
 
 impl State {
 
-    async fn _new(instance: wgpu::Instance, surface: wgpu::Surface, size: PhysicalSize<u32>, scalefactor: f64, preferred_mode: Option<wgpu::PresentMode>) -> Self {
-        // request adapter
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                force_fallback_adapter: false,
-                // Request an adapter which can render to our surface
-                compatible_surface: Some(&surface),
-            })
-            .await
-            .expect("Failed to find an appropriate adapter");
-
-        let swapchain_capabilities= surface.get_capabilities(&adapter);
-
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: None,
-                    features: wgpu::Features::empty(),
-                    limits: wgpu::Limits::downlevel_webgl2_defaults()
-                        .using_resolution(adapter.limits()),
-                },
-                None,
-            )
-            .await
-            .expect("Failed to create device");
-
-        let present_mode = match preferred_mode {
-            Some(p) => p,
-            None => swapchain_capabilities.present_modes[0],
-        };
+    /// Initialize a `wgpu` state from a winit window.\
+    /// Use `StateBuilder` to set specific adapter parameters.
+    pub async fn new_winit(window: &winit::window::Window, preferred_mode: Option<wgpu::PresentMode>, backend: Backends) -> Result<Self, Error> {
+        let mut builder = StateBuilder::new().backend(backend);
+        if let Some(mode) = preferred_mode {
+            builder = builder.present_mode(mode);
+        }
+        builder.build_winit(window).await
+    }
+
+    /// Initialize a `wgpu` state from a raw window handle with window size and scale factor.\
+    /// Use `StateBuilder` to set specific adapter parameters.
+    pub async fn new_raw(handle: raw_window_handle::RawWindowHandle, win_size: (u32,u32), scalefactor: f64, preferred_mode: Option<wgpu::PresentMode>, backend: Backends) -> Result<Self, Error> {
+        let mut builder = StateBuilder::new().backend(backend);
+        if let Some(mode) = preferred_mode {
+            builder = builder.present_mode(mode);
+        }
+        builder.build_raw(handle, win_size, scalefactor).await
+    }
 
+    /// Initialize a `wgpu` state without a window, rendering into an offscreen texture of the given size and format.\
+    /// Falls back to a software adapter when no hardware adapter is available, so it can be used in CI and tests.
+    pub async fn new_headless(width: u32, height: u32, format: wgpu::TextureFormat, backend: Backends) -> Result<Self, Error> {
+        StateBuilder::new().backend(backend).build_headless(width, height, format).await
+    }
 
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: swapchain_capabilities.formats[0],
-            width: size.width,
-            height: size.height,
-            present_mode,
-            alpha_mode: swapchain_capabilities.alpha_modes[0],
-            view_formats: vec![],
-        };
+    /// The main window, or `None` if this state is headless or every window was removed.
+    pub fn main_window(&self) -> Option<&WindowSurface> {
+        match &self.target {
+            RenderTarget::Window(id) => self.windows.get(id),
+            RenderTarget::Headless(_) | RenderTarget::Closed(_) => None,
+        }
+    }
 
-        surface.configure(&device, &config);
+    /// The main window, or `None` if this state is headless or every window was removed.
+    pub fn main_window_mut(&mut self) -> Option<&mut WindowSurface> {
+        match &self.target {
+            RenderTarget::Window(id) => self.windows.get_mut(id),
+            RenderTarget::Headless(_) | RenderTarget::Closed(_) => None,
+        }
+    }
 
+    /// The configuration of the main target.\
+    /// Every window shares its format, so pipelines can use `config().format` for their color targets.\
+    /// After the last window is removed it is that window's last configuration.
+    pub fn config(&self) -> &wgpu::SurfaceConfiguration {
+        match &self.target {
+            RenderTarget::Window(id) => &self.windows[id].config,
+            RenderTarget::Headless(headless) => &headless.config,
+            RenderTarget::Closed(config) => config,
+        }
+    }
 
-        Self {
-            scalefactor,
-            instance,
-            config,
-            surface,
-            adapter,
-            device,
-            queue
+    /// Resize the main target to a new physical size.\
+    /// Zero-sized resizes (e.g. a minimized window) are recorded but don't reconfigure the surface.
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        let device = &self.context.device;
+        let attachments = &self.attachments;
+        match &mut self.target {
+            RenderTarget::Window(id) => {
+                if let Some(window) = self.windows.get_mut(id) {
+                    window.resize(device, attachments, size);
+                }
+            }
+            RenderTarget::Headless(headless) => {
+                if size.width == 0 || size.height == 0 {
+                    return;
+                }
+                let mut config = headless.config.clone();
+                config.width = size.width;
+                config.height = size.height;
+                **headless = HeadlessTarget::new(device, config, attachments);
+            }
+            RenderTarget::Closed(_) => {}
         }
     }
 
-    /// Initialize a `wgpu` state from a winit window.\
-    /// TODO: allow setting specific adapter parameters.
-    pub async fn new_winit(window: &winit::window::Window, preferred_mode: Option<wgpu::PresentMode>, backend: Backends) -> Result<Self, wgpu::CreateSurfaceError> {
-        
-        let size = window.inner_size();
-        let scalefactor = window.scale_factor();
-        let instance = wgpu::Instance::new(backend.gen_instance_desc());
-        let surface = unsafe { instance.create_surface(window) }?;
+    /// Update the main window's size and scale factor from a window event.\
+    /// Handles `Resized` and `ScaleFactorChanged`, returns `true` if the event was one of them.
+    pub fn handle_window_event(&mut self, event: &WindowEvent) -> bool {
+        let device = &self.context.device;
+        let attachments = &self.attachments;
+        match &self.target {
+            RenderTarget::Window(id) => match self.windows.get_mut(id) {
+                Some(window) => window.handle_window_event(device, attachments, event),
+                None => false,
+            },
+            RenderTarget::Headless(_) | RenderTarget::Closed(_) => false,
+        }
+    }
+
+    /// Route a `Resized` or `ScaleFactorChanged` event to the window it belongs to.\
+    /// Returns `true` if a window was updated.
+    pub fn handle_event<T>(&mut self, event: &Event<T>) -> bool {
+        match event {
+            Event::WindowEvent { window_id, event } => match self.windows.get_mut(window_id) {
+                Some(window) => window.handle_window_event(&self.context.device, &self.attachments, event),
+                None => false,
+            },
+            _ => false,
+        }
+    }
 
-        Ok(Self::_new(instance, surface, size, scalefactor, preferred_mode).await)
+    /// The current size of the main target in physical pixels.
+    pub fn physical_size(&self) -> PhysicalSize<u32> {
+        match self.main_window() {
+            Some(window) => window.size,
+            None => PhysicalSize::new(self.config().width, self.config().height),
+        }
+    }
 
+    /// The current size of the main target in logical pixels.
+    pub fn logical_size(&self) -> LogicalSize<f64> {
+        self.physical_size().to_logical(self.scalefactor())
     }
 
-    /// Initialize a `wgpu` state from a raw window handle with window size and scale factor.\
-    /// TODO: allow setting specific adapter parameters.
-    pub async fn new_raw(handle: raw_window_handle::RawWindowHandle, win_size: (u32,u32), scalefactor: f64, preferred_mode: Option<wgpu::PresentMode>, backend: Backends) -> Result<Self, wgpu::CreateSurfaceError> {
+    /// The scale factor of the main window, `1.0` if this state is headless.
+    pub fn scalefactor(&self) -> f64 {
+        match self.main_window() {
+            Some(window) => window.scalefactor,
+            None => 1.0,
+        }
+    }
 
-        let size = PhysicalSize::new(win_size.0,win_size.1);
-        let instance = wgpu::Instance::new(backend.gen_instance_desc());
-        let surface = unsafe { instance.create_surface(&RWH {handle}) }?;
-        
-        Ok(Self::_new(instance, surface, size, scalefactor, preferred_mode).await)
+    /// The main window's surface, or `None` if this state is headless.
+    pub fn surface(&self) -> Option<&wgpu::Surface> {
+        self.main_window().map(|window| &window.surface)
+    }
 
+    /// The capabilities of the main window's surface, or `None` if this state is headless.
+    pub fn get_capabilities(&self) -> Option<wgpu::SurfaceCapabilities> {
+        self.surface().map(|surface| surface.get_capabilities(&self.adapter))
     }
 
-    pub fn resize(&mut self, size: PhysicalSize<u32>) {
-        // self.config.width = (size.width as f64 * self.scalefactor) as u32;
-        // self.config.height = (size.height as f64 * self.scalefactor) as u32;
-        self.config.width = size.width;
-        self.config.height = size.height;
-        self.surface.configure(&self.device, &self.config);
-    }    
-
-    pub fn get_capabilities(&self) -> wgpu::SurfaceCapabilities {
-        self.surface.get_capabilities(&self.adapter)
+    /// Add another winit window that renders with this state's device.\
+    /// Its surface uses the main target's format, so existing pipelines can draw to it.
+    pub fn add_window(&mut self, window: &winit::window::Window) -> Result<(), Error> {
+        let surface = unsafe { self.context.instance.create_surface(window) }?;
+        let surface = WindowSurface::matching(&self.context, surface, self.config(), window.inner_size(), window.scale_factor(), &self.attachments)?;
+        self.insert_window(window.id(), surface);
+        Ok(())
+    }
+
+    /// Add another window from a raw window handle, registered under `id`.
+    pub fn add_window_raw(&mut self, id: WindowId, handle: raw_window_handle::RawWindowHandle, win_size: (u32,u32), scalefactor: f64) -> Result<(), Error> {
+        let surface = unsafe { self.context.instance.create_surface(&RWH {handle}) }?;
+        let surface = WindowSurface::matching(&self.context, surface, self.config(), PhysicalSize::new(win_size.0, win_size.1), scalefactor, &self.attachments)?;
+        self.insert_window(id, surface);
+        Ok(())
+    }
+
+    fn insert_window(&mut self, id: WindowId, surface: WindowSurface) {
+        self.windows.insert(id, surface);
+        // Every window was removed before, this one takes the main window's place.
+        if let RenderTarget::Closed(_) = &self.target {
+            self.target = RenderTarget::Window(id);
+        }
+    }
+
+    /// Remove a window's surface, e.g. when it is closed.\
+    /// If it was the main window, another remaining window becomes the main one.
+    /// Removing the last window leaves the target `RenderTarget::Closed` until a window is added.
+    pub fn remove_window(&mut self, id: WindowId) -> Option<WindowSurface> {
+        let removed = self.windows.remove(&id)?;
+        if let RenderTarget::Window(main) = &self.target {
+            if *main == id {
+                self.target = match self.windows.keys().next() {
+                    Some(next) => RenderTarget::Window(*next),
+                    None => RenderTarget::Closed(removed.config.clone()),
+                };
+            }
+        }
+        Some(removed)
+    }
+
+    /// A `DepthStencilState` matching the state's depth buffer, `None` if it has no depth buffer.
+    pub fn depth_stencil_state(&self) -> Option<wgpu::DepthStencilState> {
+        self.attachments.depth_format.map(DepthTexture::depth_stencil_state)
+    }
+
+    pub fn window(&self, id: WindowId) -> Option<&WindowSurface> {
+        self.windows.get(&id)
+    }
+
+    pub fn window_mut(&mut self, id: WindowId) -> Option<&mut WindowSurface> {
+        self.windows.get_mut(&id)
     }
 
 }
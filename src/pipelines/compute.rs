@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use crate::State;
+use super::ShaderReflection;
+
+/// The compute counterpart of `Pipeline`, an "organizer" for the descriptors of a compute pipeline.\
+/// Like with `Pipeline`, make a struct with this trait for every compute shader.
+pub trait ComputePipeline {
+
+    fn shader_src<'a>(state: &State) -> wgpu::ShaderSource<'a>;
+
+    /// The name of the `@compute` function in the shader.
+    fn entry_point<'a>(state: &State) -> &'a str;
+
+    fn pipeline_desc<'a>(_state: &State, layout: Option<&'a wgpu::PipelineLayout>, module: &'a wgpu::ShaderModule, entry_point: &'a str) -> wgpu::ComputePipelineDescriptor<'a> {
+        wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout,
+            module,
+            entry_point,
+        }
+    }
+}
+
+pub struct ComputeShaderPipeline {
+    pub module: Arc<wgpu::ShaderModule>,
+    pub pipeline: Arc<wgpu::ComputePipeline>,
+    pub layout: Arc<wgpu::PipelineLayout>,
+    /// The `@workgroup_size` of the entry point, read from the WGSL. `[1, 1, 1]` for other shader languages.
+    pub workgroup_size: [u32; 3],
+}
+
+impl ComputeShaderPipeline {
+
+    /// The workgroups to dispatch to cover `elements` invocations in each dimension, see `dispatch_size`.
+    pub fn workgroups_for(&self, elements: [u32; 3]) -> [u32; 3] {
+        [
+            dispatch_size(elements[0], self.workgroup_size[0]),
+            dispatch_size(elements[1], self.workgroup_size[1]),
+            dispatch_size(elements[2], self.workgroup_size[2]),
+        ]
+    }
+}
+
+/// The number of workgroups of `workgroup_size` invocations needed to cover `elements` invocations.\
+/// The last workgroup may run past the end, the shader should check its `global_invocation_id` against the count.\
+/// Panics if `workgroup_size` is 0, which no shader can declare.
+pub fn dispatch_size(elements: u32, workgroup_size: u32) -> u32 {
+    assert!(workgroup_size > 0, "[wgpu_quick] A workgroup size of 0 can't cover {} elements", elements);
+    elements.div_ceil(workgroup_size)
+}
+
+/// Instantiate a compute pipeline from a defined `ComputePipeline` trait.\
+/// WGSL is reflected for the workgroup size, errors if it doesn't parse or lacks the entry point.
+pub fn make_compute_pipeline<'a, T: ComputePipeline>(state: &State, bind_group_layouts: &[&'a wgpu::BindGroupLayout], push_constant_ranges: &'a [wgpu::PushConstantRange]) -> anyhow::Result<ComputeShaderPipeline> {
+
+    let entry_point = T::entry_point(state);
+    let source = T::shader_src(state);
+    let workgroup_size = match &source {
+        wgpu::ShaderSource::Wgsl(_) => ShaderReflection::from_source(&source, &[entry_point])?.workgroup_size.unwrap_or([1, 1, 1]),
+        _ => [1, 1, 1],
+    };
+
+    let module = Arc::new(state.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source
+    }));
+
+    let layout = Arc::new(state.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts,
+        push_constant_ranges
+    }));
+
+    let pipeline = Arc::new(state.device.create_compute_pipeline(&T::pipeline_desc(state, Some(&layout), &module, entry_point)));
+
+    Ok(ComputeShaderPipeline {
+        module,
+        pipeline,
+        layout,
+        workgroup_size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dispatch_size_rounds_up() {
+        assert_eq!(dispatch_size(0, 64), 0);
+        assert_eq!(dispatch_size(1, 64), 1);
+        assert_eq!(dispatch_size(64, 64), 1);
+        assert_eq!(dispatch_size(65, 64), 2);
+        assert_eq!(dispatch_size(1000, 1), 1000);
+    }
+
+    #[test]
+    #[should_panic]
+    fn dispatch_size_rejects_empty_workgroups() {
+        dispatch_size(16, 0);
+    }
+}
@@ -0,0 +1,196 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::SystemTime;
+
+use anyhow::*;
+
+use crate::State;
+use super::{ShaderPipeline, ShaderPreprocessor};
+
+/// The newest pipeline of a hot reloaded `ShaderPipeline`, shared with the `RenderObject`s that render with it.\
+/// Render passes borrow their pipeline for as long as they record, so a replaced pipeline is kept until the cell
+/// is dropped instead of being freed while a pass may still use it. Reloads are rare, so the pipelines add up slowly.
+pub struct PipelineCell {
+    first: PipelineNode,
+    revision: AtomicUsize,
+}
+
+/// A pipeline of a `PipelineCell` and the one that replaced it, set once.
+struct PipelineNode {
+    pipeline: Arc<wgpu::RenderPipeline>,
+    next: OnceLock<Box<PipelineNode>>,
+}
+
+impl PipelineNode {
+    fn new(pipeline: Arc<wgpu::RenderPipeline>) -> Self {
+        Self {
+            pipeline,
+            next: OnceLock::new(),
+        }
+    }
+}
+
+impl PipelineCell {
+
+    pub fn new(pipeline: Arc<wgpu::RenderPipeline>) -> Self {
+        Self {
+            first: PipelineNode::new(pipeline),
+            revision: AtomicUsize::new(0),
+        }
+    }
+
+    fn last(&self) -> &PipelineNode {
+        let mut node = &self.first;
+        while let Some(next) = node.next.get() {
+            node = next;
+        }
+        node
+    }
+
+    /// The newest pipeline, borrowed for as long as the cell, e.g. for `RenderPass::set_pipeline`.
+    pub fn get(&self) -> &wgpu::RenderPipeline {
+        &self.last().pipeline
+    }
+
+    /// The newest pipeline.
+    pub fn current(&self) -> Arc<wgpu::RenderPipeline> {
+        Arc::clone(&self.last().pipeline)
+    }
+
+    /// Make `pipeline` the one `get` and `current` hand out.
+    pub fn replace(&self, pipeline: Arc<wgpu::RenderPipeline>) {
+        let mut node = Box::new(PipelineNode::new(pipeline));
+        // Another thread may have replaced it first, then append after its pipeline.
+        while let Err(rejected) = self.last().next.set(node) {
+            node = rejected;
+        }
+        self.revision.fetch_add(1, Ordering::Release);
+    }
+
+    /// Swap the newest pipeline into `held`, for holders of an `Arc<RenderPipeline>`, returns whether it was outdated.
+    pub fn update(&self, held: &mut Arc<wgpu::RenderPipeline>) -> bool {
+        let current = &self.last().pipeline;
+        if Arc::ptr_eq(held, current) {
+            return false;
+        }
+        *held = Arc::clone(current);
+        true
+    }
+
+    /// How many times the pipeline was replaced.
+    pub fn revision(&self) -> usize {
+        self.revision.load(Ordering::Acquire)
+    }
+}
+
+/// A WGSL file a `ShaderPipeline` is reloaded from when it, or a file it includes, changes.
+pub(crate) struct ShaderWatch {
+    path: PathBuf,
+    preprocessor: Option<ShaderPreprocessor>,
+    /// Every file the last reload read, with its modification time then.
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+fn modified(path: &PathBuf) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Parse and validate WGSL with naga, with errors formatted against the source.
+fn validate_wgsl(source: &str, path: &str) -> Result<()> {
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|e| anyhow!("{}", e.emit_to_string_with_path(source, path)))?;
+    naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all())
+        .validate(&module)
+        .map_err(|e| anyhow!("{}", e.emit_to_string_with_path(source, path)))?;
+    Ok(())
+}
+
+impl ShaderPipeline {
+
+    /// Opt into hot reloading: `reload_if_changed` re-reads the WGSL at `path` whenever the file is modified.\
+    /// Point it at the file `Pipeline::shader_src` includes, e.g. `concat!(env!("CARGO_MANIFEST_DIR"), "/src/shader.wgsl")`.
+    pub fn watch(&mut self, path: impl Into<PathBuf>) {
+        let path = path.into();
+        self.watch = Some(ShaderWatch {
+            files: vec![(path.clone(), modified(&path))],
+            path,
+            preprocessor: None,
+        });
+    }
+
+    /// Hot reload a file that is run through a `ShaderPreprocessor`, rebuilding when it or any file it includes changes.\
+    /// Errors point at the original file and line.
+    pub fn watch_preprocessed(&mut self, path: impl Into<PathBuf>, preprocessor: ShaderPreprocessor) {
+        let path = path.into();
+        // If it doesn't preprocess yet, watch the file itself until it does.
+        let files = preprocessor.process(&path.display().to_string())
+            .map(|processed| processed.paths.iter().map(|path| (path.clone(), modified(path))).collect())
+            .unwrap_or_else(|_| vec![(path.clone(), modified(&path))]);
+        self.watch = Some(ShaderWatch {
+            path,
+            preprocessor: Some(preprocessor),
+            files,
+        });
+    }
+
+    /// Rebuild the pipeline if the watched file changed since the last check, returns whether it was rebuilt.\
+    /// On errors the old pipeline is kept, and the error describes what failed, with naga's source locations.
+    pub fn reload_if_changed(&mut self, state: &State) -> Result<bool> {
+        let watch = match &mut self.watch {
+            Some(watch) => watch,
+            None => return Ok(false),
+        };
+        if watch.files.iter().all(|(path, time)| modified(path) == *time) {
+            return Ok(false);
+        }
+        // Only retry once a file changes again, not every frame.
+        for (path, time) in &mut watch.files {
+            *time = modified(path);
+        }
+        let name = watch.path.display().to_string();
+
+        let processed = match &watch.preprocessor {
+            Some(preprocessor) => preprocessor.process(&name)?,
+            None => {
+                let source = std::fs::read_to_string(&watch.path).with_context(|| format!("Could not read shader {}", name))?;
+                return self.reload_from_source(state, &source, &name).map(|_| true);
+            }
+        };
+        // Includes may have been added or removed, watch what this version reads.
+        let mut files: Vec<_> = processed.paths.iter().map(|path| (path.clone(), modified(path))).collect();
+        if !files.iter().any(|(path, _)| *path == watch.path) {
+            files.push((watch.path.clone(), modified(&watch.path)));
+        }
+        watch.files = files;
+        processed.validate()?;
+        self.rebuild_from(state, &processed.source, &name)?;
+        Ok(true)
+    }
+
+    /// Validate WGSL and rebuild the pipeline with it, keeping the layout.\
+    /// Shader and pipeline creation errors leave the old pipeline in place.
+    pub fn reload_from_source(&mut self, state: &State, source: &str, name: &str) -> Result<()> {
+        validate_wgsl(source, name)?;
+        self.rebuild_from(state, source, name)
+    }
+
+    /// Rebuild the pipeline with WGSL that naga accepted.
+    fn rebuild_from(&mut self, state: &State, source: &str, name: &str) -> Result<()> {
+        // The pipeline may still not match, e.g. a renamed entry point, so catch wgpu's validation errors too.
+        state.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = state.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(name),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let pipeline = (self.rebuild)(state, &module, &self.layout);
+        if let Some(error) = pollster::block_on(state.device.pop_error_scope()) {
+            bail!("Could not rebuild the pipeline for {}: {}", name, error);
+        }
+
+        self.module = Arc::new(module);
+        self.pipeline = Arc::new(pipeline);
+        self.cell.replace(Arc::clone(&self.pipeline));
+        Ok(())
+    }
+}
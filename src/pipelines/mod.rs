@@ -0,0 +1,216 @@
+mod compute;
+mod hot;
+mod preprocess;
+mod reflect;
+
+use crate::State;
+use crate::bindings::Bindings;
+use std::sync::Arc;
+
+pub use compute::{dispatch_size, make_compute_pipeline, ComputePipeline, ComputeShaderPipeline};
+pub use hot::PipelineCell;
+pub use preprocess::{ProcessedShader, ShaderPreprocessor, SourceMap};
+pub use reflect::{LayoutMismatch, ShaderReflection, VertexInput};
+use hot::ShaderWatch;
+
+pub struct ShaderPipeline {
+    /// The shader module, replaced when the shader is reloaded.
+    pub module: Arc<wgpu::ShaderModule>,
+    /// The pipeline, replaced when the shader is reloaded.
+    pub pipeline: Arc<wgpu::RenderPipeline>,
+    /// The newest pipeline, for `RenderObject::pipeline` so reloads are rendered without updating the objects.
+    pub cell: Arc<PipelineCell>,
+    pub layout: Arc<wgpu::PipelineLayout>,
+    /// The bind group layouts `make_pipline_reflected` created from the shader, empty for other pipelines.
+    pub bind_group_layouts: Vec<Arc<wgpu::BindGroupLayout>>,
+    rebuild: fn(&State, &wgpu::ShaderModule, &wgpu::PipelineLayout) -> wgpu::RenderPipeline,
+    watch: Option<ShaderWatch>,
+}
+
+/// Description for the Vertex Shader
+pub struct VertexDesc<'a> {
+    pub module: &'a wgpu::ShaderModule,
+    pub entry_point: &'a str,
+    pub buffer_layouts: Vec<wgpu::VertexBufferLayout<'a>>,
+}
+
+/// Description for the Fragment Shader
+pub enum FragmentDesc<'a> {
+    None,
+    Some {
+        module: &'a wgpu::ShaderModule,
+        entry_point: &'a str,
+        targets: Vec<Option<wgpu::ColorTargetState>>
+    }
+}
+
+impl<'a> FragmentDesc<'a> {
+
+    fn unpack(self) -> Option<(&'a wgpu::ShaderModule, &'a str, Option<Vec<Option<wgpu::ColorTargetState>>>)> {
+        match self {
+            FragmentDesc::None => None,
+            FragmentDesc::Some { module, entry_point, targets } => {
+                Some((module, entry_point, Some(targets)))
+            }
+        }
+    }
+}
+
+/// While `wgpu::RenderPipelineDescriptor` is already very simple\
+/// it tends to be very verbose in code. The goal is create an "organizer" for\
+/// containing the descriptors of a pipeline. The way I use this is I create\
+/// a .rs for every .wgsl/.glsl/.spirv file and just make a struct with this trait in it.
+pub trait Pipeline {
+
+    /// The shader `make_pipline` creates the module from.\
+    /// Preprocessed shaders can return `ProcessedShader::shader_source`, or skip this with `make_pipline_preprocessed`.
+    fn shader_src<'a>(state: &State) -> wgpu::ShaderSource<'a>;
+
+    fn vertex_state<'a>(state: &State, module: &'a wgpu::ShaderModule) -> VertexDesc<'a>;
+
+    fn fragment_desc<'a>(state: &State, module: &'a wgpu::ShaderModule) -> FragmentDesc<'a>;
+
+    fn pipeline_desc<'a>(state: &State, layout: Option<&'a wgpu::PipelineLayout>, fragment: Option<wgpu::FragmentState<'a>>, vertex: wgpu::VertexState<'a>) -> wgpu::RenderPipelineDescriptor<'a>;
+
+    /// A `DepthStencilState` matching the state's depth buffer, for use in `pipeline_desc`.\
+    /// `None` if the state was built without a depth format.
+    fn depth_stencil(state: &State) -> Option<wgpu::DepthStencilState> {
+        state.depth_stencil_state()
+    }
+
+    /// A `MultisampleState` matching the state's sample count, for use in `pipeline_desc`.\
+    /// Pipelines that draw to single sample textures instead should keep `MultisampleState::default()`.
+    fn multisample(state: &State) -> wgpu::MultisampleState {
+        state.attachments.multisample_state()
+    }
+}
+
+/// Instantiate a rendering pipeline from a defined `Pipeline` trait.\
+/// Use `Pipeline::multisample` in `pipeline_desc` for pipelines that draw to the state's targets, see `StateBuilder::sample_count`.
+pub fn make_pipline<'a, T: Pipeline>(state: &State, bind_group_layouts: &[&'a wgpu::BindGroupLayout], push_constant_ranges: &'a [wgpu::PushConstantRange]) -> ShaderPipeline {
+
+    let module = state.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: T::shader_src(state)
+    });
+
+    make_pipline_with_module::<T>(state, module, bind_group_layouts, push_constant_ranges)
+}
+
+/// Like `make_pipline`, but the module is created from WGSL run through a `ShaderPreprocessor` instead of `Pipeline::shader_src`.\
+/// The shader is validated first, so errors point at the original file and line rather than the preprocessed output.
+pub fn make_pipline_preprocessed<'a, T: Pipeline>(state: &State, shader: &ProcessedShader, bind_group_layouts: &[&'a wgpu::BindGroupLayout], push_constant_ranges: &'a [wgpu::PushConstantRange]) -> anyhow::Result<ShaderPipeline> {
+
+    shader.validate()?;
+    let module = state.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: shader.shader_source()
+    });
+
+    Ok(make_pipline_with_module::<T>(state, module, bind_group_layouts, push_constant_ranges))
+}
+
+/// Create the layout and pipeline of a `Pipeline` trait around an already created shader module.
+fn make_pipline_with_module<T: Pipeline>(state: &State, module: wgpu::ShaderModule, bind_group_layouts: &[&wgpu::BindGroupLayout], push_constant_ranges: &[wgpu::PushConstantRange]) -> ShaderPipeline {
+
+    let module = Arc::new(module);
+
+    let layout = Arc::new(state.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor{
+        label: None,
+        bind_group_layouts,
+        push_constant_ranges
+    }));
+
+    let pipeline = Arc::new(build_pipeline::<T>(state, &module, &layout));
+
+    ShaderPipeline {
+        module,
+        cell: Arc::new(PipelineCell::new(Arc::clone(&pipeline))),
+        pipeline,
+        layout,
+        bind_group_layouts: vec![],
+        rebuild: build_pipeline::<T>,
+        watch: None,
+    }
+
+}
+
+/// Create the shader module of a `Pipeline` trait and reflect its source for the entry points
+/// its vertex and fragment descriptions use.
+fn reflect_pipeline<T: Pipeline>(state: &State) -> anyhow::Result<(wgpu::ShaderModule, ShaderReflection)> {
+    let source = T::shader_src(state);
+    let module = state.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: source.clone()
+    });
+    let vertex = T::vertex_state(state, &module).entry_point;
+    let mut entry_points = vec![vertex];
+    if let Some((_, fragment, _)) = T::fragment_desc(state, &module).unpack() {
+        entry_points.push(fragment);
+    }
+    let reflection = ShaderReflection::from_source(&source, &entry_points)?;
+    Ok((module, reflection))
+}
+
+/// Check the bind group layouts, vertex buffers and color targets of a `Pipeline` trait against its shader.
+fn check_pipeline<T: Pipeline>(state: &State, module: &wgpu::ShaderModule, reflection: &ShaderReflection, bind_groups: &[&[wgpu::BindGroupLayoutEntry]]) -> Result<(), LayoutMismatch> {
+    let buffers = T::vertex_state(state, module).buffer_layouts;
+    let targets = T::fragment_desc(state, module).unpack().and_then(|(_, _, targets)| targets).unwrap_or_default();
+    reflection.check(bind_groups, &buffers, &targets)
+}
+
+/// Like `make_pipline`, but the bind group layouts are created from the shader with naga reflection.\
+/// The vertex buffers and color targets are checked against the shader, a `LayoutMismatch` lists the differences.
+/// The created layouts are in `ShaderPipeline::bind_group_layouts`.
+pub fn make_pipline_reflected<T: Pipeline>(state: &State, push_constant_ranges: &[wgpu::PushConstantRange]) -> anyhow::Result<ShaderPipeline> {
+    let (module, reflection) = reflect_pipeline::<T>(state)?;
+    let entries: Vec<&[wgpu::BindGroupLayoutEntry]> = reflection.bind_groups.iter().map(Vec::as_slice).collect();
+    check_pipeline::<T>(state, &module, &reflection, &entries)?;
+
+    let bind_group_layouts: Vec<Arc<wgpu::BindGroupLayout>> = reflection.create_bind_group_layouts(&state.device).into_iter().map(Arc::new).collect();
+    let layouts: Vec<&wgpu::BindGroupLayout> = bind_group_layouts.iter().map(|layout| layout.as_ref()).collect();
+    let mut pipeline = make_pipline_with_module::<T>(state, module, &layouts, push_constant_ranges);
+    pipeline.bind_group_layouts = bind_group_layouts;
+    Ok(pipeline)
+}
+
+/// Like `make_pipline`, but the `Bindings` of each group, the vertex buffers and the color targets are checked
+/// against the shader first. A `LayoutMismatch` lists every difference instead of wgpu panicking on the first.
+pub fn make_pipline_checked<T: Pipeline>(state: &State, bindings: &[&Bindings], push_constant_ranges: &[wgpu::PushConstantRange]) -> anyhow::Result<ShaderPipeline> {
+    let (module, reflection) = reflect_pipeline::<T>(state)?;
+    let entries: Vec<&[wgpu::BindGroupLayoutEntry]> = bindings.iter().map(|bindings| bindings.entries.as_slice()).collect();
+    check_pipeline::<T>(state, &module, &reflection, &entries)?;
+
+    let layouts: Vec<&wgpu::BindGroupLayout> = bindings.iter().map(|bindings| &bindings.bind_layout).collect();
+    Ok(make_pipline_with_module::<T>(state, module, &layouts, push_constant_ranges))
+}
+
+/// Create the render pipeline of a `Pipeline` trait with a shader module and layout.
+fn build_pipeline<T: Pipeline>(state: &State, module: &wgpu::ShaderModule, layout: &wgpu::PipelineLayout) -> wgpu::RenderPipeline {
+
+    let vstate = T::vertex_state(state, module);
+    let fstate = T::fragment_desc(state, module).unpack();
+
+    let mut fstate_targets: Option<Vec<Option<wgpu::ColorTargetState>>> = None;
+    let targets_unwraped: Vec<Option<wgpu::ColorTargetState>>;
+
+    let pipeline_desc = T::pipeline_desc(state, Some(layout), match fstate {
+        None => None,
+        Some (( module, entry_point, mut targets)) => {
+
+            std::mem::swap(&mut targets, &mut fstate_targets);
+            targets_unwraped = fstate_targets.expect("[wgpu_quick] Error swapping Color Target reference!");
+            Some(wgpu::FragmentState {
+                module,
+                entry_point,
+                targets: targets_unwraped.as_slice(),
+            })
+        }
+    }, wgpu::VertexState {
+        module: vstate.module,
+        entry_point: vstate.entry_point,
+        buffers: vstate.buffer_layouts.as_slice()
+    });
+
+    state.device.create_render_pipeline(&pipeline_desc)
+}
@@ -0,0 +1,336 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::*;
+
+/// A preprocessor for WGSL with `#include`, `#define`, `#undef`, `#ifdef`, `#ifndef`, `#else` and `#endif`.\
+/// Includes are looked up in the registered virtual files first, then next to the including file,
+/// then in the include directories. Every file is included once, later includes of it are skipped.\
+/// Defines with a value replace the identifier wherever it appears in code, they aren't expanded recursively.
+#[derive(Clone, Debug, Default)]
+pub struct ShaderPreprocessor {
+    files: HashMap<String, String>,
+    include_dirs: Vec<PathBuf>,
+    defines: HashMap<String, String>,
+}
+
+/// Maps the lines of preprocessed WGSL back to the files and lines they came from.
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    files: Vec<String>,
+    /// The file index and 1-based line of every output line.
+    lines: Vec<(usize, u32)>,
+}
+
+impl SourceMap {
+
+    /// The original file and 1-based line of a 1-based line of the output.
+    pub fn original(&self, line: u32) -> Option<(&str, u32)> {
+        let (file, line) = *self.lines.get((line as usize).checked_sub(1)?)?;
+        Some((&self.files[file], line))
+    }
+}
+
+/// Preprocessed WGSL and where its lines came from.
+#[derive(Clone, Debug)]
+pub struct ProcessedShader {
+    pub source: String,
+    pub source_map: SourceMap,
+    /// The files read from disk, for hot reloading.
+    pub(crate) paths: Vec<PathBuf>,
+}
+
+impl ProcessedShader {
+
+    /// The source for `Pipeline::shader_src`.
+    pub fn shader_source(&self) -> wgpu::ShaderSource<'static> {
+        wgpu::ShaderSource::Wgsl(self.source.clone().into())
+    }
+
+    /// Parse and validate the WGSL with naga. Errors point at the original file, line and column.
+    pub fn validate(&self) -> Result<naga::Module> {
+        let module = naga::front::wgsl::parse_str(&self.source).map_err(|e| {
+            let label = e.labels().next().map(|(_, label)| label.to_string());
+            self.error(e.location(&self.source), e.message().to_string(), label)
+        })?;
+        naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all())
+            .validate(&module)
+            .map_err(|e| {
+                let mut message = e.as_inner().to_string();
+                let mut source = std::error::Error::source(e.as_inner());
+                while let Some(error) = source {
+                    message += &format!(": {}", error);
+                    source = error.source();
+                }
+                let label = e.spans().next().map(|(_, label)| label.clone());
+                self.error(e.location(&self.source), message, label)
+            })?;
+        Ok(module)
+    }
+
+    fn error(&self, location: Option<naga::SourceLocation>, message: String, label: Option<String>) -> Error {
+        let label = label.filter(|label| !label.is_empty()).map(|label| format!(" ({})", label)).unwrap_or_default();
+        match location.and_then(|location| Some((self.source_map.original(location.line_number)?, location.line_position))) {
+            Some(((file, line), column)) => anyhow!("{}:{}:{}: {}{}", file, line, column, message, label),
+            None => anyhow!("{}{}", message, label),
+        }
+    }
+}
+
+/// A file being preprocessed, and where it is on disk if it was read from there.
+struct Source {
+    name: String,
+    text: String,
+    path: Option<PathBuf>,
+}
+
+/// The output and state shared by a file and everything it includes.
+struct Output {
+    source: String,
+    map: SourceMap,
+    included: HashSet<String>,
+    paths: Vec<PathBuf>,
+    defines: HashMap<String, String>,
+}
+
+/// One `#ifdef` or `#ifndef` block.
+struct Condition {
+    /// Whether the enclosing block is active.
+    parent: bool,
+    /// Whether the condition held.
+    taken: bool,
+    else_seen: bool,
+    active: bool,
+}
+
+impl ShaderPreprocessor {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a virtual file that `#include "name"` resolves to before looking on disk.
+    pub fn file(mut self, name: &str, source: &str) -> Self {
+        self.files.insert(name.to_string(), source.to_string());
+        self
+    }
+
+    /// A directory to look for included files in.
+    pub fn include_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.include_dirs.push(dir.into());
+        self
+    }
+
+    /// Define a name for `#ifdef`, and with a non-empty value, replace it in code.
+    pub fn define(mut self, name: &str, value: &str) -> Self {
+        self.defines.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Preprocess a virtual file, or a file on disk relative to the include directories or the working directory.
+    pub fn process(&self, name: &str) -> Result<ProcessedShader> {
+        let source = self.resolve(name, None)?.with_context(|| format!("Could not find shader {}", name))?;
+        self.run(source)
+    }
+
+    /// Preprocess WGSL given as a string, `name` is used in errors and the source map.
+    pub fn process_source(&self, name: &str, source: &str) -> Result<ProcessedShader> {
+        self.run(Source {
+            name: name.to_string(),
+            text: source.to_string(),
+            path: None,
+        })
+    }
+
+    fn run(&self, source: Source) -> Result<ProcessedShader> {
+        let mut output = Output {
+            source: String::new(),
+            map: SourceMap::default(),
+            included: HashSet::new(),
+            paths: vec![],
+            defines: self.defines.clone(),
+        };
+        output.included.insert(include_key(&source));
+        self.process_file(&mut output, source)?;
+        Ok(ProcessedShader {
+            source: output.source,
+            source_map: output.map,
+            paths: output.paths,
+        })
+    }
+
+    /// Find an included file: a virtual file, next to the including file, in an include directory, or as given.
+    fn resolve(&self, name: &str, including: Option<&Path>) -> Result<Option<Source>> {
+        if let Some(text) = self.files.get(name) {
+            return Ok(Some(Source {
+                name: name.to_string(),
+                text: text.clone(),
+                path: None,
+            }));
+        }
+        let candidates = including.and_then(Path::parent).map(|dir| dir.join(name)).into_iter()
+            .chain(self.include_dirs.iter().map(|dir| dir.join(name)))
+            .chain(std::iter::once(PathBuf::from(name)));
+        for path in candidates {
+            if path.is_file() {
+                let text = std::fs::read_to_string(&path).with_context(|| format!("Could not read shader {}", path.display()))?;
+                return Ok(Some(Source {
+                    name: path.display().to_string(),
+                    text,
+                    path: Some(path),
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    fn process_file(&self, output: &mut Output, source: Source) -> Result<()> {
+        let file = output.map.files.len();
+        output.map.files.push(source.name.clone());
+        if let Some(path) = &source.path {
+            output.paths.push(path.clone());
+        }
+
+        let mut conditions: Vec<Condition> = vec![];
+        for (index, line) in source.text.lines().enumerate() {
+            let line_number = index as u32 + 1;
+            let error = |message: String| anyhow!("{}:{}: {}", source.name, line_number, message);
+            let active = conditions.last().is_none_or(|condition| condition.active);
+
+            let directive = match line.trim_start().strip_prefix('#') {
+                Some(directive) => directive.trim(),
+                None => {
+                    if active {
+                        output.source += &substitute(line, &output.defines);
+                        output.source.push('\n');
+                        output.map.lines.push((file, line_number));
+                    }
+                    continue;
+                }
+            };
+            let (keyword, argument) = match directive.split_once(char::is_whitespace) {
+                Some((keyword, argument)) => (keyword, argument.trim()),
+                None => (directive, ""),
+            };
+
+            match keyword {
+                "ifdef" | "ifndef" => {
+                    if argument.is_empty() {
+                        return Err(error(format!("#{} needs a name", keyword)));
+                    }
+                    let taken = output.defines.contains_key(argument) == (keyword == "ifdef");
+                    conditions.push(Condition {
+                        parent: active,
+                        taken,
+                        else_seen: false,
+                        active: active && taken,
+                    });
+                }
+                "else" => {
+                    let condition = conditions.last_mut().ok_or_else(|| error("#else without #ifdef".to_string()))?;
+                    if condition.else_seen {
+                        return Err(error("A second #else for the same #ifdef".to_string()));
+                    }
+                    condition.else_seen = true;
+                    condition.active = condition.parent && !condition.taken;
+                }
+                "endif" => {
+                    conditions.pop().ok_or_else(|| error("#endif without #ifdef".to_string()))?;
+                }
+                // Other directives in inactive blocks are skipped, even unknown ones.
+                _ if !active => {}
+                "define" => {
+                    let (name, value) = argument.split_once(char::is_whitespace).unwrap_or((argument, ""));
+                    if name.is_empty() {
+                        return Err(error("#define needs a name".to_string()));
+                    }
+                    output.defines.insert(name.to_string(), value.trim().to_string());
+                }
+                "undef" => {
+                    output.defines.remove(argument);
+                }
+                "include" => {
+                    let name = argument.strip_prefix('"').and_then(|name| name.strip_suffix('"'))
+                        .ok_or_else(|| error(format!("#include expects a quoted file name, not {}", argument)))?;
+                    let included = self.resolve(name, source.path.as_deref())?
+                        .ok_or_else(|| error(format!("Could not find included shader {}", name)))?;
+                    if output.included.insert(include_key(&included)) {
+                        self.process_file(output, included)?;
+                    }
+                }
+                _ => return Err(error(format!("Unknown directive #{}", keyword))),
+            }
+        }
+
+        if !conditions.is_empty() {
+            bail!("{}: {} #ifdef without #endif", source.name, conditions.len());
+        }
+        Ok(())
+    }
+}
+
+/// Identifies a file for include-once, by its canonical path if it is on disk.
+fn include_key(source: &Source) -> String {
+    match &source.path {
+        Some(path) => path.canonicalize().unwrap_or_else(|_| path.clone()).display().to_string(),
+        None => source.name.clone(),
+    }
+}
+
+/// Replace identifiers that have a defined value.
+fn substitute(line: &str, defines: &HashMap<String, String>) -> String {
+    let mut output = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find(|c: char| c.is_alphabetic() || c == '_') {
+        output += &rest[..start];
+        rest = &rest[start..];
+        let end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+        let identifier = &rest[..end];
+        match defines.get(identifier) {
+            Some(value) if !value.is_empty() => output += value,
+            _ => output += identifier,
+        }
+        rest = &rest[end..];
+    }
+    output += rest;
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn includes_defines_and_conditions() {
+        let processed = ShaderPreprocessor::new()
+            .file("common.wgsl", "#define SCALE 2.0\nfn scale(x: f32) -> f32 { return x * SCALE; }")
+            .define("RED", "")
+            .process_source("main.wgsl", "#include \"common.wgsl\"\n#include \"common.wgsl\"\n#ifdef RED\nlet c = 1.0;\n#else\nlet c = 0.0;\n#endif\nlet d = SCALE_2;")
+            .unwrap();
+        assert_eq!(processed.source, "fn scale(x: f32) -> f32 { return x * 2.0; }\nlet c = 1.0;\nlet d = SCALE_2;\n");
+        assert_eq!(processed.source_map.original(1), Some(("common.wgsl", 2)));
+        assert_eq!(processed.source_map.original(2), Some(("main.wgsl", 4)));
+        assert_eq!(processed.source_map.original(4), None);
+    }
+
+    #[test]
+    fn errors_name_the_file_and_line() {
+        let preprocessor = ShaderPreprocessor::new().file("broken.wgsl", "\n#ifdef A\n");
+        let error = preprocessor.process_source("main.wgsl", "#include \"missing.wgsl\"").unwrap_err();
+        assert_eq!(error.to_string(), "main.wgsl:1: Could not find included shader missing.wgsl");
+        let error = preprocessor.process("broken.wgsl").unwrap_err();
+        assert_eq!(error.to_string(), "broken.wgsl: 1 #ifdef without #endif");
+        let error = preprocessor.process_source("main.wgsl", "#pragma once").unwrap_err();
+        assert_eq!(error.to_string(), "main.wgsl:1: Unknown directive #pragma");
+    }
+
+    #[test]
+    fn naga_errors_point_at_the_original_line() {
+        let processed = ShaderPreprocessor::new()
+            .file("lib.wgsl", "fn one() -> f32 {\n    return 1.0;\n}\nfn broken() -> f32 {\n    return one() + ;\n}")
+            .process_source("main.wgsl", "#include \"lib.wgsl\"\n@fragment\nfn fs_main() -> @location(0) vec4<f32> { return vec4<f32>(one()); }")
+            .unwrap();
+        let error = processed.validate().unwrap_err().to_string();
+        assert!(error.starts_with("lib.wgsl:5:"), "{}", error);
+    }
+}
@@ -0,0 +1,529 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::num::{NonZeroU32, NonZeroU64};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+/// A vertex shader input and the vertex format that matches its type exactly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VertexInput {
+    pub location: u32,
+    pub format: wgpu::VertexFormat,
+}
+
+/// What a shader expects from the pipeline around it, read from its WGSL with naga.\
+/// Only the entry points it was reflected for are considered, bindings they don't use are left out.
+#[derive(Clone, Debug)]
+pub struct ShaderReflection {
+    /// The layout entries of every bind group by group index, sorted by binding. Groups in between that
+    /// the shader doesn't use are empty.\
+    /// Sampled float textures are reflected as filterable and samplers as filtering, the shader can't tell.
+    pub bind_groups: Vec<Vec<wgpu::BindGroupLayoutEntry>>,
+    /// The inputs of the vertex entry point, sorted by location.
+    pub vertex_inputs: Vec<VertexInput>,
+    /// How many color targets the fragment entry point writes, one past its highest output location.
+    pub fragment_outputs: u32,
+    /// The `@workgroup_size` of the compute entry point, if one was reflected.
+    pub workgroup_size: Option<[u32; 3]>,
+    /// The scalar kind of each fragment output by location.
+    output_kinds: Vec<Option<naga::ScalarKind>>,
+}
+
+/// The ways a pipeline doesn't match its shader, one readable line per difference.
+#[derive(Clone, Debug)]
+pub struct LayoutMismatch {
+    pub differences: Vec<String>,
+}
+
+impl fmt::Display for LayoutMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the pipeline doesn't match its shader:")?;
+        for difference in &self.differences {
+            write!(f, "\n  {}", difference)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for LayoutMismatch {}
+
+impl ShaderReflection {
+
+    /// Reflect a `ShaderSource`, only WGSL is supported.
+    pub fn from_source(source: &wgpu::ShaderSource, entry_points: &[&str]) -> Result<Self> {
+        match source {
+            wgpu::ShaderSource::Wgsl(source) => Self::from_wgsl(source, entry_points),
+            _ => bail!("Only WGSL shaders can be reflected"),
+        }
+    }
+
+    /// Parse and validate WGSL, then reflect the named entry points.
+    pub fn from_wgsl(source: &str, entry_points: &[&str]) -> Result<Self> {
+        let module = naga::front::wgsl::parse_str(source)
+            .map_err(|e| anyhow!("{}", e.emit_to_string(source)))?;
+        let info = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all())
+            .validate(&module)
+            .map_err(|e| anyhow!("{}", e.emit_to_string(source)))?;
+        Self::from_module(&module, &info, entry_points)
+    }
+
+    /// Reflect the named entry points of a validated naga module.
+    pub fn from_module(module: &naga::Module, info: &naga::valid::ModuleInfo, entry_points: &[&str]) -> Result<Self> {
+        let mut used = vec![];
+        for name in entry_points {
+            let index = module.entry_points.iter().position(|entry| entry.name == *name)
+                .with_context(|| format!("The shader has no entry point named {}", name))?;
+            used.push((index, &module.entry_points[index]));
+        }
+
+        let mut groups: BTreeMap<u32, Vec<wgpu::BindGroupLayoutEntry>> = BTreeMap::new();
+        for (handle, global) in module.global_variables.iter() {
+            let binding = match &global.binding {
+                Some(binding) => binding,
+                None => continue,
+            };
+            let visibility = used.iter()
+                .filter(|(index, _)| !info.get_entry_point(*index)[handle].is_empty())
+                .fold(wgpu::ShaderStages::NONE, |stages, (_, entry)| stages | shader_stage(entry.stage));
+            if visibility.is_empty() {
+                continue;
+            }
+            let (ty, count) = binding_type(module, global)
+                .with_context(|| format!("Could not reflect group {} binding {}", binding.group, binding.binding))?;
+            groups.entry(binding.group).or_default().push(wgpu::BindGroupLayoutEntry {
+                binding: binding.binding,
+                visibility,
+                ty,
+                count,
+            });
+        }
+        let mut bind_groups = vec![vec![]; groups.keys().last().map_or(0, |group| *group as usize + 1)];
+        for (group, mut entries) in groups {
+            entries.sort_by_key(|entry| entry.binding);
+            bind_groups[group as usize] = entries;
+        }
+
+        let mut vertex_inputs = vec![];
+        let mut output_kinds = vec![];
+        let mut workgroup_size = None;
+        for (_, entry) in &used {
+            match entry.stage {
+                naga::ShaderStage::Vertex => {
+                    for argument in &entry.function.arguments {
+                        locations(module, argument.ty, argument.binding.as_ref(), &mut |location, ty| {
+                            let format = vertex_format(ty).with_context(|| format!("Vertex input {} has no vertex format", location))?;
+                            vertex_inputs.push(VertexInput { location, format });
+                            Ok(())
+                        })?;
+                    }
+                }
+                naga::ShaderStage::Fragment => {
+                    if let Some(result) = &entry.function.result {
+                        locations(module, result.ty, result.binding.as_ref(), &mut |location, ty| {
+                            let location = location as usize;
+                            if output_kinds.len() <= location {
+                                output_kinds.resize(location + 1, None);
+                            }
+                            output_kinds[location] = ty.scalar_kind();
+                            Ok(())
+                        })?;
+                    }
+                }
+                naga::ShaderStage::Compute => workgroup_size = Some(entry.workgroup_size),
+            }
+        }
+        vertex_inputs.sort_by_key(|input| input.location);
+
+        Ok(Self {
+            bind_groups,
+            vertex_inputs,
+            fragment_outputs: output_kinds.len() as u32,
+            workgroup_size,
+            output_kinds,
+        })
+    }
+
+    /// Create a bind group layout for every group, including empty ones for unused groups in between.
+    pub fn create_bind_group_layouts(&self, device: &wgpu::Device) -> Vec<wgpu::BindGroupLayout> {
+        self.bind_groups.iter().map(|entries| {
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries,
+            })
+        }).collect()
+    }
+
+    /// Check the layout entries of each bind group, the vertex buffers and the color targets against the shader.\
+    /// Bindings the layout has but the shader doesn't use are fine, like in wgpu.
+    pub fn check(&self, bind_groups: &[&[wgpu::BindGroupLayoutEntry]], vertex_buffers: &[wgpu::VertexBufferLayout], targets: &[Option<wgpu::ColorTargetState>]) -> Result<(), LayoutMismatch> {
+        let mut differences = vec![];
+
+        for (group, entries) in self.bind_groups.iter().enumerate() {
+            let layout = match bind_groups.get(group) {
+                Some(layout) => layout,
+                None => {
+                    if !entries.is_empty() {
+                        let bindings: Vec<u32> = entries.iter().map(|entry| entry.binding).collect();
+                        differences.push(format!("group {}: missing from the pipeline layout, the shader uses bindings {:?}", group, bindings));
+                    }
+                    continue;
+                }
+            };
+            for expected in entries {
+                let prefix = format!("group {} binding {}", group, expected.binding);
+                let entry = match layout.iter().find(|entry| entry.binding == expected.binding) {
+                    Some(entry) => entry,
+                    None => {
+                        differences.push(format!("{}: missing from the layout, the shader uses {:?}", prefix, expected.ty));
+                        continue;
+                    }
+                };
+                if !binding_compatible(&expected.ty, &entry.ty) {
+                    differences.push(format!("{}: the layout has {:?}, the shader uses {:?}", prefix, entry.ty, expected.ty));
+                }
+                if !entry.visibility.contains(expected.visibility) {
+                    differences.push(format!("{}: visible to {:?}, the shader uses it in {:?}", prefix, entry.visibility, expected.visibility));
+                }
+                if entry.count != expected.count {
+                    differences.push(format!("{}: the layout has a count of {:?}, the shader {:?}", prefix, entry.count, expected.count));
+                }
+            }
+        }
+
+        for input in &self.vertex_inputs {
+            let provided = vertex_buffers.iter().enumerate().find_map(|(buffer, layout)| {
+                layout.attributes.iter().find(|attribute| attribute.shader_location == input.location).map(|attribute| (buffer, attribute.format))
+            });
+            match provided {
+                None => differences.push(format!("vertex location {}: missing from the vertex buffers, the shader expects {:?}", input.location, input.format)),
+                Some((buffer, format)) if !vertex_compatible(input.format, format) => differences.push(format!(
+                    "vertex location {}: buffer {} provides {:?}, the shader expects {:?}", input.location, buffer, format, input.format
+                )),
+                Some(_) => {}
+            }
+        }
+
+        for (location, target) in targets.iter().enumerate() {
+            let target = match target {
+                Some(target) if !target.write_mask.is_empty() => target,
+                _ => continue,
+            };
+            match self.output_kinds.get(location).copied().flatten() {
+                None => differences.push(format!("color target {}: the fragment shader writes no output at location {}", location, location)),
+                Some(kind) => {
+                    let holds = target_kind(target.format);
+                    if holds != Some(kind) {
+                        differences.push(format!("color target {}: {:?} holds {:?} values, the fragment shader writes {:?}", location, target.format, holds, kind));
+                    }
+                }
+            }
+        }
+
+        if differences.is_empty() {
+            Ok(())
+        } else {
+            Err(LayoutMismatch { differences })
+        }
+    }
+}
+
+fn shader_stage(stage: naga::ShaderStage) -> wgpu::ShaderStages {
+    match stage {
+        naga::ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
+        naga::ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+        naga::ShaderStage::Compute => wgpu::ShaderStages::COMPUTE,
+    }
+}
+
+/// The binding type of a global and its count if it is a binding array.
+fn binding_type(module: &naga::Module, global: &naga::GlobalVariable) -> Result<(wgpu::BindingType, Option<NonZeroU32>)> {
+    let (ty, count) = match module.types[global.ty].inner {
+        naga::TypeInner::BindingArray { base, size } => {
+            let count = match size {
+                naga::ArraySize::Constant(constant) => match module.constants[constant].inner {
+                    naga::ConstantInner::Scalar { value: naga::ScalarValue::Uint(count), .. } => NonZeroU32::new(count as u32),
+                    naga::ConstantInner::Scalar { value: naga::ScalarValue::Sint(count), .. } => NonZeroU32::new(count as u32),
+                    _ => bail!("The binding array's length isn't an integer"),
+                },
+                naga::ArraySize::Dynamic => None,
+            };
+            (base, count)
+        }
+        _ => (global.ty, None),
+    };
+    let inner = &module.types[ty].inner;
+
+    let binding = match global.space {
+        naga::AddressSpace::Uniform => wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: NonZeroU64::new(inner.size(&module.constants) as u64),
+        },
+        naga::AddressSpace::Storage { access } => wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: !access.contains(naga::StorageAccess::STORE) },
+            has_dynamic_offset: false,
+            min_binding_size: NonZeroU64::new(inner.size(&module.constants) as u64),
+        },
+        naga::AddressSpace::Handle => match *inner {
+            naga::TypeInner::Sampler { comparison: true } => wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+            naga::TypeInner::Sampler { comparison: false } => wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            naga::TypeInner::Image { dim, arrayed, class } => {
+                let view_dimension = match (dim, arrayed) {
+                    (naga::ImageDimension::D1, _) => wgpu::TextureViewDimension::D1,
+                    (naga::ImageDimension::D2, false) => wgpu::TextureViewDimension::D2,
+                    (naga::ImageDimension::D2, true) => wgpu::TextureViewDimension::D2Array,
+                    (naga::ImageDimension::D3, _) => wgpu::TextureViewDimension::D3,
+                    (naga::ImageDimension::Cube, false) => wgpu::TextureViewDimension::Cube,
+                    (naga::ImageDimension::Cube, true) => wgpu::TextureViewDimension::CubeArray,
+                };
+                match class {
+                    naga::ImageClass::Sampled { kind, multi } => wgpu::BindingType::Texture {
+                        sample_type: match kind {
+                            naga::ScalarKind::Sint => wgpu::TextureSampleType::Sint,
+                            naga::ScalarKind::Uint => wgpu::TextureSampleType::Uint,
+                            _ => wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        view_dimension,
+                        multisampled: multi,
+                    },
+                    naga::ImageClass::Depth { multi } => wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension,
+                        multisampled: multi,
+                    },
+                    naga::ImageClass::Storage { format, access } => wgpu::BindingType::StorageTexture {
+                        access: match (access.contains(naga::StorageAccess::LOAD), access.contains(naga::StorageAccess::STORE)) {
+                            (true, true) => wgpu::StorageTextureAccess::ReadWrite,
+                            (true, false) => wgpu::StorageTextureAccess::ReadOnly,
+                            _ => wgpu::StorageTextureAccess::WriteOnly,
+                        },
+                        format: storage_format(format),
+                        view_dimension,
+                    },
+                }
+            }
+            _ => bail!("A handle that is neither a texture nor a sampler"),
+        },
+        space => bail!("Bindings in the {:?} address space aren't supported", space),
+    };
+    Ok((binding, count))
+}
+
+fn storage_format(format: naga::StorageFormat) -> wgpu::TextureFormat {
+    use naga::StorageFormat as S;
+    use wgpu::TextureFormat as T;
+    match format {
+        S::R8Unorm => T::R8Unorm,
+        S::R8Snorm => T::R8Snorm,
+        S::R8Uint => T::R8Uint,
+        S::R8Sint => T::R8Sint,
+        S::R16Uint => T::R16Uint,
+        S::R16Sint => T::R16Sint,
+        S::R16Float => T::R16Float,
+        S::Rg8Unorm => T::Rg8Unorm,
+        S::Rg8Snorm => T::Rg8Snorm,
+        S::Rg8Uint => T::Rg8Uint,
+        S::Rg8Sint => T::Rg8Sint,
+        S::R32Uint => T::R32Uint,
+        S::R32Sint => T::R32Sint,
+        S::R32Float => T::R32Float,
+        S::Rg16Uint => T::Rg16Uint,
+        S::Rg16Sint => T::Rg16Sint,
+        S::Rg16Float => T::Rg16Float,
+        S::Rgba8Unorm => T::Rgba8Unorm,
+        S::Rgba8Snorm => T::Rgba8Snorm,
+        S::Rgba8Uint => T::Rgba8Uint,
+        S::Rgba8Sint => T::Rgba8Sint,
+        S::Rgb10a2Unorm => T::Rgb10a2Unorm,
+        S::Rg11b10Float => T::Rg11b10Float,
+        S::Rg32Uint => T::Rg32Uint,
+        S::Rg32Sint => T::Rg32Sint,
+        S::Rg32Float => T::Rg32Float,
+        S::Rgba16Uint => T::Rgba16Uint,
+        S::Rgba16Sint => T::Rgba16Sint,
+        S::Rgba16Float => T::Rgba16Float,
+        S::Rgba32Uint => T::Rgba32Uint,
+        S::Rgba32Sint => T::Rgba32Sint,
+        S::Rgba32Float => T::Rgba32Float,
+        S::R16Unorm => T::R16Unorm,
+        S::R16Snorm => T::R16Snorm,
+        S::Rg16Unorm => T::Rg16Unorm,
+        S::Rg16Snorm => T::Rg16Snorm,
+        S::Rgba16Unorm => T::Rgba16Unorm,
+        S::Rgba16Snorm => T::Rgba16Snorm,
+    }
+}
+
+/// Call `found` with every location of an entry point argument or result, looking into structs.
+fn locations(module: &naga::Module, ty: naga::Handle<naga::Type>, binding: Option<&naga::Binding>, found: &mut dyn FnMut(u32, &naga::TypeInner) -> Result<()>) -> Result<()> {
+    match binding {
+        Some(naga::Binding::Location { location, .. }) => found(*location, &module.types[ty].inner),
+        Some(naga::Binding::BuiltIn(_)) => Ok(()),
+        None => match &module.types[ty].inner {
+            naga::TypeInner::Struct { members, .. } => {
+                for member in members {
+                    locations(module, member.ty, member.binding.as_ref(), found)?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        },
+    }
+}
+
+/// The vertex format that matches a shader input's type exactly.
+fn vertex_format(ty: &naga::TypeInner) -> Option<wgpu::VertexFormat> {
+    use naga::ScalarKind as K;
+    use wgpu::VertexFormat as F;
+    let (kind, width, components) = match *ty {
+        naga::TypeInner::Scalar { kind, width } => (kind, width, 1),
+        naga::TypeInner::Vector { size, kind, width } => (kind, width, size as u32),
+        _ => return None,
+    };
+    Some(match (kind, width, components) {
+        (K::Float, 4, 1) => F::Float32,
+        (K::Float, 4, 2) => F::Float32x2,
+        (K::Float, 4, 3) => F::Float32x3,
+        (K::Float, 4, 4) => F::Float32x4,
+        (K::Float, 8, 1) => F::Float64,
+        (K::Float, 8, 2) => F::Float64x2,
+        (K::Float, 8, 3) => F::Float64x3,
+        (K::Float, 8, 4) => F::Float64x4,
+        (K::Uint, 4, 1) => F::Uint32,
+        (K::Uint, 4, 2) => F::Uint32x2,
+        (K::Uint, 4, 3) => F::Uint32x3,
+        (K::Uint, 4, 4) => F::Uint32x4,
+        (K::Sint, 4, 1) => F::Sint32,
+        (K::Sint, 4, 2) => F::Sint32x2,
+        (K::Sint, 4, 3) => F::Sint32x3,
+        (K::Sint, 4, 4) => F::Sint32x4,
+        _ => return None,
+    })
+}
+
+/// The scalar kind a vertex format reads as in a shader, and whether it is a vector.
+fn vertex_kind(format: wgpu::VertexFormat) -> (naga::ScalarKind, bool) {
+    use wgpu::VertexFormat as F;
+    match format {
+        F::Uint32 => (naga::ScalarKind::Uint, false),
+        F::Sint32 => (naga::ScalarKind::Sint, false),
+        F::Float32 | F::Float64 => (naga::ScalarKind::Float, false),
+        F::Uint8x2 | F::Uint8x4 | F::Uint16x2 | F::Uint16x4 | F::Uint32x2 | F::Uint32x3 | F::Uint32x4 => (naga::ScalarKind::Uint, true),
+        F::Sint8x2 | F::Sint8x4 | F::Sint16x2 | F::Sint16x4 | F::Sint32x2 | F::Sint32x3 | F::Sint32x4 => (naga::ScalarKind::Sint, true),
+        _ => (naga::ScalarKind::Float, true),
+    }
+}
+
+/// Whether a buffer format can feed a shader input, by wgpu's rules: the same scalar kind,
+/// and a vector input needs a vector format.
+fn vertex_compatible(expected: wgpu::VertexFormat, provided: wgpu::VertexFormat) -> bool {
+    let (expected_kind, expected_vector) = vertex_kind(expected);
+    let (provided_kind, provided_vector) = vertex_kind(provided);
+    expected_kind == provided_kind && (provided_vector || !expected_vector)
+}
+
+/// Whether a layout's binding type fits the one the shader declares.
+fn binding_compatible(shader: &wgpu::BindingType, layout: &wgpu::BindingType) -> bool {
+    use wgpu::BindingType as B;
+    match (shader, layout) {
+        (B::Buffer { ty: shader_ty, min_binding_size: shader_size, .. }, B::Buffer { ty: layout_ty, min_binding_size: layout_size, .. }) => {
+            shader_ty == layout_ty && match (shader_size, layout_size) {
+                (Some(shader_size), Some(layout_size)) => layout_size >= shader_size,
+                _ => true,
+            }
+        }
+        (B::Sampler(shader), B::Sampler(layout)) => {
+            (*shader == wgpu::SamplerBindingType::Comparison) == (*layout == wgpu::SamplerBindingType::Comparison)
+        }
+        (
+            B::Texture { sample_type: shader_type, view_dimension: shader_dimension, multisampled: shader_multisampled },
+            B::Texture { sample_type: layout_type, view_dimension: layout_dimension, multisampled: layout_multisampled },
+        ) => {
+            shader_dimension == layout_dimension && shader_multisampled == layout_multisampled && match (shader_type, layout_type) {
+                // Whether a float texture is filterable depends on its format and sampler, not the shader.
+                (wgpu::TextureSampleType::Float { .. }, wgpu::TextureSampleType::Float { .. }) => true,
+                (wgpu::TextureSampleType::Float { .. }, wgpu::TextureSampleType::Depth) => true,
+                (shader_type, layout_type) => shader_type == layout_type,
+            }
+        }
+        (shader, layout) => shader == layout,
+    }
+}
+
+/// The scalar kind a color target format stores.
+fn target_kind(format: wgpu::TextureFormat) -> Option<naga::ScalarKind> {
+    match format.describe().sample_type {
+        wgpu::TextureSampleType::Float { .. } | wgpu::TextureSampleType::Depth => Some(naga::ScalarKind::Float),
+        wgpu::TextureSampleType::Sint => Some(naga::ScalarKind::Sint),
+        wgpu::TextureSampleType::Uint => Some(naga::ScalarKind::Uint),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = "
+struct Camera { view: mat4x4<f32> }
+@group(0) @binding(0) var<uniform> camera: Camera;
+@group(1) @binding(0) var tex: texture_2d<f32>;
+@group(1) @binding(1) var samp: sampler;
+@group(1) @binding(2) var<storage, read> unused: array<f32>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(2) id: u32,
+}
+
+@vertex
+fn vs_main(in: VertexInput) -> @builtin(position) vec4<f32> {
+    return camera.view * vec4<f32>(in.position, f32(in.id));
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureSample(tex, samp, position.xy);
+}";
+
+    #[test]
+    fn reflects_bindings_and_interfaces() {
+        let reflection = ShaderReflection::from_wgsl(SHADER, &["vs_main", "fs_main"]).unwrap();
+        assert_eq!(reflection.bind_groups.len(), 2);
+        assert_eq!(reflection.bind_groups[0][0].visibility, wgpu::ShaderStages::VERTEX);
+        assert_eq!(reflection.bind_groups[0][0].ty, wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: NonZeroU64::new(64),
+        });
+        // The storage buffer isn't used by either entry point.
+        let bindings: Vec<u32> = reflection.bind_groups[1].iter().map(|entry| entry.binding).collect();
+        assert_eq!(bindings, vec![0, 1]);
+        assert_eq!(reflection.vertex_inputs, vec![
+            VertexInput { location: 0, format: wgpu::VertexFormat::Float32x3 },
+            VertexInput { location: 2, format: wgpu::VertexFormat::Uint32 },
+        ]);
+        assert_eq!(reflection.fragment_outputs, 1);
+    }
+
+    #[test]
+    fn check_lists_every_difference() {
+        let reflection = ShaderReflection::from_wgsl(SHADER, &["vs_main", "fs_main"]).unwrap();
+        let camera = [wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None },
+            count: None,
+        }];
+        let attributes = wgpu::vertex_attr_array![0 => Float32x3, 2 => Float32];
+        let buffers = [wgpu::VertexBufferLayout { array_stride: 16, step_mode: wgpu::VertexStepMode::Vertex, attributes: &attributes }];
+        let targets = [Some(wgpu::TextureFormat::Rgba8Unorm.into()), Some(wgpu::TextureFormat::R32Uint.into())];
+
+        let mismatch = reflection.check(&[&camera], &buffers, &targets).unwrap_err();
+        assert_eq!(mismatch.differences, vec![
+            "group 0 binding 0: visible to FRAGMENT, the shader uses it in VERTEX".to_string(),
+            "group 1: missing from the pipeline layout, the shader uses bindings [0, 1]".to_string(),
+            "vertex location 2: buffer 0 provides Float32, the shader expects Uint32".to_string(),
+            "color target 1: the fragment shader writes no output at location 1".to_string(),
+        ]);
+    }
+}
@@ -5,9 +5,9 @@ use winit::dpi::{PhysicalSize, PhysicalPosition};
 #[repr(C)]
 #[derive(Debug,Copy,Clone)]
 pub struct WorldPoint {
-    x: f32,
-    y: f32,
-    z: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
 }
 
 unsafe impl bytemuck::Zeroable for WorldPoint {}
@@ -82,8 +82,8 @@ impl WorldRectangle {
 #[repr(C)]
 #[derive(Debug,Copy,Clone)]
 pub struct ScreenPoint {
-    x: u32,
-    y: u32,
+    pub x: u32,
+    pub y: u32,
 }
 
 unsafe impl bytemuck::Zeroable for ScreenPoint {}
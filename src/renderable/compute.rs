@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+/// The compute counterpart of `RenderObject`: a pipeline, its bind groups and how many workgroups to dispatch.
+pub struct ComputeObject {
+    pub pipeline: Arc<wgpu::ComputePipeline>,
+    /// The bind groups in order of compatible `BindGroupLayouts` in the `PipelineLayout`.
+    pub bind_groups: Vec<Arc<wgpu::BindGroup>>,
+    /// The number of workgroups in x, y and z, see `ComputeShaderPipeline::workgroups_for`.
+    pub workgroups: [u32; 3],
+}
+
+impl ComputeObject {
+
+    pub fn new(pipeline: &Arc<wgpu::ComputePipeline>, bind_groups: Vec<Arc<wgpu::BindGroup>>, workgroups: [u32; 3]) -> Self {
+        Self {
+            pipeline: Arc::clone(pipeline),
+            bind_groups,
+            workgroups,
+        }
+    }
+
+    pub fn compute_this<'a>(&'a self, compute_pass: &mut wgpu::ComputePass<'a>) {
+        // Dispatching no workgroups is valid but pointless.
+        if self.workgroups.contains(&0) {
+            return;
+        }
+        compute_pass.set_pipeline(&self.pipeline);
+        for i in 0..self.bind_groups.len() {
+            compute_pass.set_bind_group(i as u32, self.bind_groups[i].as_ref(), &[]);
+        }
+        compute_pass.dispatch_workgroups(self.workgroups[0], self.workgroups[1], self.workgroups[2]);
+    }
+
+    /// Record the dispatch in a compute pass of its own.
+    pub fn dispatch(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+        self.compute_this(&mut compute_pass);
+    }
+}
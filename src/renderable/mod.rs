@@ -1,14 +1,16 @@
 use std::ops::Range;
 use std::sync::Arc;
 
-use wgpu::RenderPipeline;
 use wgpu::util::DeviceExt;
-use crate::pipelines::Pipeline;
+use crate::pipelines::{Pipeline, PipelineCell};
 
 use super::State;
 
 /// Data for a renderable object.
 pub mod model;
+mod compute;
+
+pub use compute::ComputeObject;
 
 /// Data for indexing vertices
 pub enum Indices {
@@ -40,8 +42,9 @@ pub trait Renderable {
     fn indices(&self) -> &Indices;
 }
 pub struct RenderObject {
-    /// The pipeline to render with.
-    pub pipeline: Arc<wgpu::RenderPipeline>,
+    /// The pipeline to render with, usually a `ShaderPipeline::cell`. The newest pipeline is used at every draw,
+    /// so a hot reloaded shader shows up without touching the object.
+    pub pipeline: Arc<PipelineCell>,
     /// The bind groups in order of compatible `BindGroupLayouts` in the `PipelineLayout`.
     pub bind_groups: Vec<Arc<wgpu::BindGroup>>,
     /// The model buffers 
@@ -50,7 +53,7 @@ pub struct RenderObject {
 
 impl RenderObject {
 
-    pub fn new(pipeline: &Arc<RenderPipeline>, bind_groups: Vec<Arc<wgpu::BindGroup>>, model: &Arc<dyn Renderable>) -> Self {
+    pub fn new(pipeline: &Arc<PipelineCell>, bind_groups: Vec<Arc<wgpu::BindGroup>>, model: &Arc<dyn Renderable>) -> Self {
         Self {
             pipeline: Arc::clone(pipeline),
             bind_groups,
@@ -59,7 +62,7 @@ impl RenderObject {
     }
 
     pub fn render_this<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
-        render_pass.set_pipeline(self.pipeline.as_ref());
+        render_pass.set_pipeline(self.pipeline.get());
         for i in 0..self.bind_groups.len() {
             render_pass.set_bind_group(i as u32, self.bind_groups[i].as_ref(), &[]);
         }
@@ -89,7 +92,7 @@ impl RenderObject {
     pub fn render_with_draw_args<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, vertices: Range<u32>, instances: Range<u32>) {
         // !! attempting to draw no instances results in (exit code: 0xc000041d)
         if instances.start != instances.end {
-            render_pass.set_pipeline(self.pipeline.as_ref());
+            render_pass.set_pipeline(self.pipeline.get());
             for i in 0..self.bind_groups.len() {
                 render_pass.set_bind_group(i as u32, self.bind_groups[i].as_ref(), &[]);
             }
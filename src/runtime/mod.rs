@@ -37,12 +37,10 @@ pub fn start<Shared: 'static,Proxy>(window: &Window, event_loop: EventLoop<Proxy
                     label: None
                 });
 
-                let surface_tex = state.surface.get_current_texture().unwrap();
-                let surface_view = surface_tex.texture.create_view(&wgpu::TextureViewDescriptor{
-                    label: None,
-                    format: Some(state.config.format.clone()),
-                    .. Default::default()
-                });
+                let frame = match state.acquire_frame().expect("Out of memory acquiring a frame") {
+                    Some(frame) => frame,
+                    None => return,
+                };
 
                 for (prog, rend) in &mut progs_rends {
                     {
@@ -52,19 +50,38 @@ pub fn start<Shared: 'static,Proxy>(window: &Window, event_loop: EventLoop<Proxy
                             color_attachments: &[Some(
                                 wgpu::RenderPassColorAttachment{
                                     view: match &rend.texture{
-                                        RendererTexture::Surface => &surface_view,
+                                        RendererTexture::Surface => &frame.view,
                                         RendererTexture::Texture(tex) => {
                                             &tex.view
                                         }
                                     },
-                                    resolve_target: None,
+                                    resolve_target: match &rend.texture {
+                                        RendererTexture::Surface => frame.resolve_target.as_ref(),
+                                        RendererTexture::Texture(_) => None
+                                    },
                                     ops: wgpu::Operations{
                                         load: rend.loadop.clone(),
                                         store: true
                                     }
                                 }
                         )],
-                            depth_stencil_attachment: None
+                            depth_stencil_attachment: match (&rend.texture, &frame.depth_view) {
+                                (RendererTexture::Surface, Some(depth_view)) if rend.depth_test => {
+                                    Some(wgpu::RenderPassDepthStencilAttachment {
+                                        view: depth_view,
+                                        depth_ops: Some(wgpu::Operations {
+                                            // Clear depth along with the color, otherwise keep what earlier programs drew.
+                                            load: match rend.loadop {
+                                                wgpu::LoadOp::Clear(_) => wgpu::LoadOp::Clear(1.0),
+                                                wgpu::LoadOp::Load => wgpu::LoadOp::Load,
+                                            },
+                                            store: true
+                                        }),
+                                        stencil_ops: None
+                                    })
+                                }
+                                _ => None
+                            }
                         });
 
                         match rend.render_timing.check() {
@@ -80,9 +97,12 @@ pub fn start<Shared: 'static,Proxy>(window: &Window, event_loop: EventLoop<Proxy
                 }
 
                 state.queue.submit(std::iter::once(encoder.finish()));
-                surface_tex.present();
+                frame.present();
             }
             e => {
+                if let Event::WindowEvent { event, .. } = &e {
+                    state.handle_window_event(event);
+                }
                 for (prog, rend) in &mut progs_rends {
                     prog.on_event(&mut global, &mut state, rend, &e);
                 }
@@ -19,6 +19,8 @@ pub struct ProgRenderer {
     pub loadop: wgpu::LoadOp<wgpu::Color>,
     pub render_timing: Timing,
     pub update_timing: Timing,
+    /// Whether to depth test against the state's depth buffer when rendering to the surface.
+    pub depth_test: bool,
 }
 
 impl ProgRenderer {
@@ -33,7 +35,8 @@ impl ProgRenderer {
             texture,
             loadop,
             render_timing,
-            update_timing
+            update_timing,
+            depth_test: false
         }
     }
 
@@ -53,6 +56,11 @@ impl ProgRenderer {
         self.texture = texture
     }
 
+    /// Opt into depth testing, the state needs a depth format for this to have any effect.
+    pub fn set_depth_test(&mut self, depth_test: bool) {
+        self.depth_test = depth_test
+    }
+
     pub(crate) fn get_wait_time(&self) -> Duration {
         unimplemented!()
     }
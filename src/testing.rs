@@ -0,0 +1,269 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::*;
+
+use crate::bindings::Bindings;
+use crate::frame::Frame;
+use crate::pipelines::PipelineCell;
+use crate::renderable::{model::Model, Indices, RenderObject};
+use crate::runtime::program::Program;
+use crate::{State, StateBuilder};
+
+/// Set this environment variable to overwrite stored golden images with the rendered ones.
+pub const BLESS_ENV: &str = "WGPU_QUICK_BLESS";
+
+/// Set this environment variable to skip GPU tests on machines without a fallback adapter instead of failing them.
+pub const SKIP_GPU_TESTS_ENV: &str = "WGPU_QUICK_SKIP_GPU_TESTS";
+
+/// Create a headless state on a software (fallback) adapter, for reproducible golden images.\
+/// Panics if the machine has no fallback adapter, so a missing one can't pass tests unnoticed.
+/// With `WGPU_QUICK_SKIP_GPU_TESTS` set it returns `None` instead, for tests to return early.
+pub fn headless_state(width: u32, height: u32) -> Option<State> {
+    headless_state_with(StateBuilder::new(), width, height)
+}
+
+/// Like `headless_state`, with `wgpu::Limits::downlevel_defaults()` so compute shaders and storage buffers are available.\
+/// Panics or returns `None` like `headless_state` if the fallback adapter can't provide them.
+pub fn headless_compute_state(width: u32, height: u32) -> Option<State> {
+    headless_state_with(StateBuilder::new().limits(wgpu::Limits::downlevel_defaults()), width, height)
+}
+
+/// Like `headless_state`, with the device options of `builder`. The fallback adapter and target are set here.
+pub fn headless_state_with(builder: StateBuilder, width: u32, height: u32) -> Option<State> {
+    let state = pollster::block_on(
+        builder
+            .force_fallback_adapter(true)
+            .build_headless(width, height, wgpu::TextureFormat::Rgba8UnormSrgb)
+    );
+    match state {
+        std::result::Result::Ok(state) => Some(state),
+        Err(e) if std::env::var_os(SKIP_GPU_TESTS_ENV).is_some() => {
+            eprintln!("[wgpu_quick] Skipping GPU test, no headless state: {}", e);
+            None
+        }
+        Err(e) => panic!("[wgpu_quick] Could not create a headless state, set {}=1 to skip GPU tests: {}", SKIP_GPU_TESTS_ENV, e),
+    }
+}
+
+/// Begin a render pass that clears the frame, including its depth buffer if it has one.
+fn begin_pass<'p>(encoder: &'p mut wgpu::CommandEncoder, frame: &'p Frame, clear: wgpu::Color) -> wgpu::RenderPass<'p> {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: &frame.view,
+            resolve_target: frame.resolve_target.as_ref(),
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(clear),
+                store: true,
+            },
+        })],
+        depth_stencil_attachment: frame.depth_view.as_ref().map(|view| wgpu::RenderPassDepthStencilAttachment {
+            view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(1.0),
+                store: true,
+            }),
+            stencil_ops: None,
+        }),
+    })
+}
+
+fn acquire(state: &State) -> Result<(Frame, wgpu::CommandEncoder)> {
+    let frame = state.acquire_frame()?.context("The state has no frame to render to")?;
+    let encoder = state.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    Ok((frame, encoder))
+}
+
+fn submit_and_capture(state: &State, frame: Frame, encoder: wgpu::CommandEncoder) -> Result<image::RgbaImage> {
+    state.queue.submit(Some(encoder.finish()));
+    frame.capture(state)
+}
+
+/// Render a `RenderObject` into a headless state and read the pixels back.
+pub fn render_object(state: &State, object: &RenderObject, clear: wgpu::Color) -> Result<image::RgbaImage> {
+    let (frame, mut encoder) = acquire(state)?;
+    {
+        let mut rpass = begin_pass(&mut encoder, &frame, clear);
+        object.render_this(&mut rpass);
+    }
+    submit_and_capture(state, frame, encoder)
+}
+
+/// The vertex stage `render_fullscreen` adds, a triangle covering the target.
+const FULLSCREEN_VERTEX: &str = "
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((i << 1u) & 2u), f32(i & 2u));
+    return vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+}
+";
+
+/// Render a triangle covering a headless state's target with a fragment shader and read the pixels back.\
+/// `wgsl` declares `fs_main` and the bindings of group 0, which `bindings` provides. The vertex stage is added to it.
+pub fn render_fullscreen(state: &State, wgsl: &str, bindings: &Bindings) -> Result<image::RgbaImage> {
+    let shader = state.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("wgpu_quick fullscreen test"),
+        source: wgpu::ShaderSource::Wgsl(format!("{}{}", FULLSCREEN_VERTEX, wgsl).into()),
+    });
+    let layout = state.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&bindings.bind_layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = state.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("wgpu_quick fullscreen test"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: state.attachments.multisample_state(),
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(state.config().format.into())],
+        }),
+        multiview: None,
+    });
+    let object = RenderObject {
+        pipeline: std::sync::Arc::new(PipelineCell::new(std::sync::Arc::new(pipeline))),
+        bind_groups: bindings.bind_groups.iter().take(1).cloned().collect(),
+        model: Model::<()>::empty(Indices::Ranged { vertices: 0..3, instances: 0..1 }),
+    };
+    render_object(state, &object, wgpu::Color::BLACK)
+}
+
+/// An image of one color, e.g. a sprite or texture layer in tests.
+pub fn solid(width: u32, height: u32, color: [u8; 4]) -> image::DynamicImage {
+    image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(width, height, image::Rgba(color)))
+}
+
+/// Render one frame of a `Program` into a headless state and read the pixels back.
+pub fn render_program<S, P>(state: &State, program: &mut dyn Program<Shared = S, Proxy = P>, global: &mut S, clear: wgpu::Color) -> Result<image::RgbaImage> {
+    let (frame, mut encoder) = acquire(state)?;
+    {
+        let mut rpass = begin_pass(&mut encoder, &frame, clear);
+        program.render(global, state, &mut rpass);
+    }
+    submit_and_capture(state, frame, encoder)
+}
+
+/// How far a rendered image may stray from its golden image.
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerance {
+    /// The largest difference allowed in any channel of a pixel before it counts as mismatched.
+    pub per_channel: u8,
+    /// The percentage of pixels (0 to 100) that may mismatch.
+    pub max_mismatched_percent: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            per_channel: 2,
+            max_mismatched_percent: 0.5,
+        }
+    }
+}
+
+/// The result of comparing two images of the same size.
+pub struct Comparison {
+    pub mismatched: usize,
+    pub total: usize,
+    /// Mismatched pixels in red over a dimmed copy of the expected image.
+    pub diff: image::RgbaImage,
+}
+
+impl Comparison {
+    pub fn mismatched_percent(&self) -> f32 {
+        self.mismatched as f32 / self.total.max(1) as f32 * 100.0
+    }
+
+    pub fn passes(&self, tolerance: &Tolerance) -> bool {
+        self.mismatched_percent() <= tolerance.max_mismatched_percent
+    }
+}
+
+/// Compare two images pixel by pixel, `None` if their sizes differ.
+pub fn compare(actual: &image::RgbaImage, expected: &image::RgbaImage, tolerance: &Tolerance) -> Option<Comparison> {
+    if actual.dimensions() != expected.dimensions() {
+        return None;
+    }
+    let mut mismatched = 0;
+    let mut diff = image::RgbaImage::new(expected.width(), expected.height());
+    for ((a, e), d) in actual.pixels().zip(expected.pixels()).zip(diff.pixels_mut()) {
+        let differs = a.0.iter().zip(e.0.iter()).any(|(a, e)| a.abs_diff(*e) > tolerance.per_channel);
+        *d = if differs {
+            mismatched += 1;
+            image::Rgba([255, 0, 0, 255])
+        } else {
+            image::Rgba([e[0] / 4, e[1] / 4, e[2] / 4, 255])
+        };
+    }
+    Some(Comparison {
+        mismatched,
+        total: (expected.width() * expected.height()) as usize,
+        diff,
+    })
+}
+
+fn failure_path(expected: &Path, suffix: &str) -> PathBuf {
+    let stem = expected.file_stem().and_then(|s| s.to_str()).unwrap_or("golden");
+    expected.with_file_name("failures").join(format!("{}-{}.png", stem, suffix))
+}
+
+/// Check a rendered image against the PNG stored at `expected`.\
+/// With `WGPU_QUICK_BLESS` set the rendered image is stored instead, a missing PNG is an error otherwise.\
+/// On mismatch the actual, expected and diff images are written to a `failures` directory next to it.
+pub fn assert_golden(actual: &image::RgbaImage, expected: impl AsRef<Path>, tolerance: &Tolerance) -> Result<()> {
+    let expected_path = expected.as_ref();
+
+    if std::env::var_os(BLESS_ENV).is_some() {
+        if let Some(dir) = expected_path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        actual.save(expected_path)?;
+        return Ok(());
+    }
+    if !expected_path.exists() {
+        bail!("golden image {} missing, rerun with {}=1 to create it", expected_path.display(), BLESS_ENV);
+    }
+
+    let expected = image::open(expected_path)
+        .with_context(|| format!("Could not open golden image {}", expected_path.display()))?
+        .to_rgba8();
+
+    let comparison = compare(actual, &expected, tolerance);
+    if let Some(comparison) = &comparison {
+        if comparison.passes(tolerance) {
+            return Ok(());
+        }
+    }
+
+    let actual_path = failure_path(expected_path, "actual");
+    std::fs::create_dir_all(actual_path.parent().unwrap())?;
+    actual.save(&actual_path)?;
+    expected.save(failure_path(expected_path, "expected"))?;
+
+    match comparison {
+        Some(comparison) => {
+            comparison.diff.save(failure_path(expected_path, "diff"))?;
+            bail!(
+                "{} mismatches its golden image: {:.2}% of pixels differ (allowed {:.2}%), see {}",
+                expected_path.display(),
+                comparison.mismatched_percent(),
+                tolerance.max_mismatched_percent,
+                actual_path.parent().unwrap().display()
+            )
+        }
+        None => bail!(
+            "{} mismatches its golden image: rendered {:?} but expected {:?}",
+            expected_path.display(),
+            actual.dimensions(),
+            expected.dimensions()
+        ),
+    }
+}
@@ -0,0 +1,348 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use image::{DynamicImage, RgbaImage};
+use anyhow::*;
+use winit::dpi::PhysicalSize;
+
+use crate::rect::{ScreenPoint, ScreenRectangle, WorldRectangle};
+use crate::State;
+use super::{Texture, TextureBuilder};
+
+/// Identifies a sprite in an `Atlas`, stays valid when the atlas is repacked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SpriteHandle {
+    atlas: u64,
+    index: usize,
+}
+
+/// The id of the next atlas, so handles can't be looked up in an atlas they don't belong to.
+static NEXT_ATLAS_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Where a sprite is stored in an `Atlas`.
+#[derive(Clone, Copy, Debug)]
+pub struct AtlasSprite {
+    /// The index of the page texture in `Atlas::pages`.
+    pub page: usize,
+    /// The sprite's texture coordinates on its page, from 0 to 1.
+    pub uv: WorldRectangle,
+    /// The sprite's texels on its page, without padding.
+    pub rect: ScreenRectangle,
+}
+
+/// A skyline (bottom-left) rectangle packer.\
+/// The skyline is a list of segments `(x, y, width)` covering the page's width, everything below them is taken.
+#[derive(Clone, Debug)]
+struct Skyline {
+    width: u32,
+    height: u32,
+    segments: Vec<(u32, u32, u32)>,
+}
+
+impl Skyline {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            segments: vec![(0, 0, width)],
+        }
+    }
+
+    /// The lowest `y` a rectangle can be placed at starting at segment `index`.
+    fn fit(&self, index: usize, width: u32, height: u32) -> Option<u32> {
+        let x = self.segments[index].0;
+        if x + width > self.width {
+            return None;
+        }
+        let mut y = 0;
+        let mut remaining = width as i64;
+        for &(_, segment_y, segment_width) in &self.segments[index..] {
+            if remaining <= 0 {
+                break;
+            }
+            y = y.max(segment_y);
+            remaining -= segment_width as i64;
+        }
+        (y + height <= self.height).then_some(y)
+    }
+
+    /// Place a rectangle as low as possible, then as far left as possible, and return its position.
+    fn insert(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let mut best: Option<(usize, u32, u32)> = None;
+        for index in 0..self.segments.len() {
+            if let Some(y) = self.fit(index, width, height) {
+                if best.is_none_or(|(_, _, best_y)| y < best_y) {
+                    best = Some((index, self.segments[index].0, y));
+                }
+            }
+        }
+        let (index, x, y) = best?;
+
+        // The new segment covers the rectangle's top, segments under it shrink or disappear.
+        self.segments.insert(index, (x, y + height, width));
+        let right = x + width;
+        let next = index + 1;
+        while next < self.segments.len() {
+            let (segment_x, segment_y, segment_width) = self.segments[next];
+            if segment_x >= right {
+                break;
+            }
+            let segment_right = segment_x + segment_width;
+            if segment_right <= right {
+                self.segments.remove(next);
+            } else {
+                self.segments[next] = (right, segment_y, segment_right - right);
+                break;
+            }
+        }
+        // Merge neighbours at the same height.
+        let mut i = 0;
+        while i + 1 < self.segments.len() {
+            if self.segments[i].1 == self.segments[i + 1].1 {
+                self.segments[i].2 += self.segments[i + 1].2;
+                self.segments.remove(i + 1);
+            } else {
+                i += 1;
+            }
+        }
+        Some((x, y))
+    }
+}
+
+struct Entry {
+    image: RgbaImage,
+    sprite: AtlasSprite,
+}
+
+/// Packs many images into a few page textures, so sprites can share a bind group.\
+/// Sprites are placed with a skyline packer. When a new sprite doesn't fit in any page, every sprite is
+/// repacked from the largest down, and pages are added if they still don't fit.
+pub struct Atlas {
+    id: u64,
+    page_size: PhysicalSize<u32>,
+    padding: u32,
+    extrude: bool,
+    builder: TextureBuilder,
+    pages: Vec<Texture>,
+    skylines: Vec<Skyline>,
+    entries: Vec<Entry>,
+    revision: u64,
+}
+
+impl Atlas {
+
+    /// An empty atlas with pages of `width` by `height` texels, 1 texel of extruded padding around each sprite
+    /// and `Rgba8UnormSrgb` pages with a linear sampler.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            id: NEXT_ATLAS_ID.fetch_add(1, Ordering::Relaxed),
+            page_size: PhysicalSize::new(width, height),
+            padding: 1,
+            extrude: true,
+            builder: TextureBuilder::new().filter(wgpu::FilterMode::Linear),
+            pages: vec![],
+            skylines: vec![],
+            entries: vec![],
+            revision: 0,
+        }
+    }
+
+    /// The texels left around each sprite, so filtering doesn't sample its neighbours.
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Fill the padding with copies of the sprite's edge texels (the default), instead of leaving it transparent.
+    pub fn extrude(mut self, extrude: bool) -> Self {
+        self.extrude = extrude;
+        self
+    }
+
+    /// The sampler and sRGB options of the page textures. The format has to store 8-bit RGBA.
+    pub fn texture_builder(mut self, builder: TextureBuilder) -> Self {
+        self.builder = builder;
+        self
+    }
+
+    /// The page textures, bind one of them per sprite's `page`.
+    pub fn pages(&self) -> &[Texture] {
+        &self.pages
+    }
+
+    /// Where a sprite currently is, this changes when the atlas is repacked.\
+    /// `None` if the handle came from another atlas.
+    pub fn sprite(&self, handle: SpriteHandle) -> Option<AtlasSprite> {
+        if handle.atlas != self.id {
+            return None;
+        }
+        self.entries.get(handle.index).map(|entry| entry.sprite)
+    }
+
+    /// The number of sprites in the atlas.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Increases every time the atlas is repacked, so cached `AtlasSprite`s can be refreshed.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Add an image to the atlas, repacking it if no page has room left.\
+    /// Look sprites up again with `sprite` after an insertion changes the `revision`.
+    pub fn insert(&mut self, state: &State, img: &DynamicImage) -> Result<SpriteHandle> {
+        let image = img.to_rgba8();
+        let (slot_width, slot_height) = self.slot_size(&image);
+        if slot_width > self.page_size.width || slot_height > self.page_size.height {
+            bail!(
+                "A {}x{} sprite with {} texels of padding doesn't fit on a {}x{} atlas page",
+                image.width(), image.height(), self.padding, self.page_size.width, self.page_size.height
+            );
+        }
+
+        let handle = SpriteHandle {
+            atlas: self.id,
+            index: self.entries.len(),
+        };
+        // Packed into a copy, which only replaces the page's skyline once the upload succeeded.
+        let placed = self.skylines.iter().enumerate().find_map(|(page, skyline)| {
+            let mut skyline = skyline.clone();
+            skyline.insert(slot_width, slot_height).map(|position| (page, position, skyline))
+        });
+        match placed {
+            Some((page, position, skyline)) => {
+                let sprite = self.place(page, position, &image);
+                self.pages[page].write_region(&state.queue, self.slot(&sprite), &self.padded(&image))?;
+                self.skylines[page] = skyline;
+                self.entries.push(Entry { image, sprite });
+            }
+            None => {
+                // A placeholder until the repack places it.
+                let sprite = self.place(0, (0, 0), &image);
+                self.entries.push(Entry { image, sprite });
+                if let Err(e) = self.repack(state) {
+                    self.entries.pop();
+                    return Err(e);
+                }
+            }
+        }
+        Ok(handle)
+    }
+
+    /// Pack every sprite again from the largest down, adding pages as needed, and upload the pages.
+    pub fn repack(&mut self, state: &State) -> Result<()> {
+        let mut order: Vec<usize> = (0..self.entries.len()).collect();
+        order.sort_by_key(|&i| {
+            let (width, height) = self.slot_size(&self.entries[i].image);
+            std::cmp::Reverse((height, width))
+        });
+
+        // The new places are only given to the entries once the pages exist and were uploaded,
+        // a failed repack leaves the atlas as it was.
+        let mut skylines: Vec<Skyline> = vec![];
+        let mut sprites: Vec<AtlasSprite> = self.entries.iter().map(|entry| entry.sprite).collect();
+        for i in order {
+            let (slot_width, slot_height) = self.slot_size(&self.entries[i].image);
+            let mut placed = skylines.iter_mut().enumerate().find_map(|(page, skyline)| {
+                skyline.insert(slot_width, slot_height).map(|position| (page, position))
+            });
+            if placed.is_none() {
+                let mut skyline = Skyline::new(self.page_size.width, self.page_size.height);
+                let position = skyline.insert(slot_width, slot_height).context("A sprite doesn't fit on an empty atlas page")?;
+                skylines.push(skyline);
+                placed = Some((skylines.len() - 1, position));
+            }
+            let (page, position) = placed.unwrap();
+            sprites[i] = self.place(page, position, &self.entries[i].image);
+        }
+
+        // Extra pages are only used once the entries are, so they can stay if a later step fails.
+        while self.pages.len() < skylines.len() {
+            let page = self.builder.build(state, self.page_size.width, self.page_size.height)?;
+            self.pages.push(page);
+        }
+
+        let mut images: Vec<RgbaImage> = self.pages.iter().map(|_| RgbaImage::new(self.page_size.width, self.page_size.height)).collect();
+        for (entry, sprite) in self.entries.iter().zip(&sprites) {
+            let slot = self.slot(sprite);
+            image::imageops::replace(&mut images[sprite.page], &self.padded(&entry.image), slot.pos.x as i64, slot.pos.y as i64);
+        }
+        // Every page has the same size and format, so uploads fail on the first page before any is overwritten.
+        for (page, image) in self.pages.iter().zip(images) {
+            page.write_region(&state.queue, ScreenRectangle::from_size(self.page_size.width, self.page_size.height), image.as_raw())?;
+        }
+
+        for (entry, sprite) in self.entries.iter_mut().zip(sprites) {
+            entry.sprite = sprite;
+        }
+        self.skylines = skylines;
+        self.revision += 1;
+        Ok(())
+    }
+
+    fn slot_size(&self, image: &RgbaImage) -> (u32, u32) {
+        (image.width() + 2 * self.padding, image.height() + 2 * self.padding)
+    }
+
+    /// The sprite in a slot placed at `position`.
+    fn place(&self, page: usize, position: (u32, u32), image: &RgbaImage) -> AtlasSprite {
+        let rect = ScreenRectangle {
+            pos: ScreenPoint::new(position.0 + self.padding, position.1 + self.padding),
+            width: image.width(),
+            height: image.height(),
+        };
+        AtlasSprite {
+            page,
+            uv: WorldRectangle::from_screen_rect(&self.page_size, &rect),
+            rect,
+        }
+    }
+
+    /// The sprite's slot including its padding.
+    fn slot(&self, sprite: &AtlasSprite) -> ScreenRectangle {
+        ScreenRectangle {
+            pos: ScreenPoint::new(sprite.rect.pos.x - self.padding, sprite.rect.pos.y - self.padding),
+            width: sprite.rect.width + 2 * self.padding,
+            height: sprite.rect.height + 2 * self.padding,
+        }
+    }
+
+    /// The image surrounded by its padding, extruded or transparent.
+    fn padded(&self, image: &RgbaImage) -> RgbaImage {
+        let (width, height) = self.slot_size(image);
+        let padding = self.padding as i64;
+        RgbaImage::from_fn(width, height, |x, y| {
+            let (x, y) = (x as i64 - padding, y as i64 - padding);
+            let inside = x >= 0 && y >= 0 && x < image.width() as i64 && y < image.height() as i64;
+            if inside || self.extrude {
+                let x = x.clamp(0, image.width() as i64 - 1) as u32;
+                let y = y.clamp(0, image.height() as i64 - 1) as u32;
+                *image.get_pixel(x, y)
+            } else {
+                image::Rgba([0; 4])
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skyline_packs_bottom_left_without_overlap() {
+        let mut skyline = Skyline::new(8, 8);
+        assert_eq!(skyline.insert(4, 2), Some((0, 0)));
+        assert_eq!(skyline.insert(4, 3), Some((4, 0)));
+        assert_eq!(skyline.insert(2, 2), Some((0, 2)));
+        // Lower to the right of the 2x2 rectangle than on top of it.
+        assert_eq!(skyline.insert(6, 1), Some((2, 3)));
+        assert_eq!(skyline.insert(8, 5), None);
+        assert_eq!(skyline.insert(8, 4), Some((0, 4)));
+        assert_eq!(skyline.segments, vec![(0, 8, 8)]);
+    }
+}
@@ -0,0 +1,457 @@
+use std::borrow::Cow;
+use std::num::NonZeroU8;
+
+use image::{DynamicImage, GenericImageView};
+use anyhow::*;
+
+use crate::State;
+use super::{format_features, half_floats, image_data, write_level, Texture};
+use super::compressed::{supported_image, write_container, ContainerImage, DDS_MAGIC, KTX2_MAGIC};
+use super::cube::render_equirectangular;
+use super::mipmap::{blit_mipmaps, can_blit, can_render, mip_level_count, upload_cpu_mipmaps};
+
+/// Collects the format, usage and sampler options used to create a `Texture`.\
+/// `Texture::new` and `Texture::from_image` are shorthands for a default builder.
+pub struct TextureBuilder {
+    label: Option<String>,
+    format: Option<wgpu::TextureFormat>,
+    srgb: bool,
+    usage: wgpu::TextureUsages,
+    view_formats: Vec<wgpu::TextureFormat>,
+    mipmaps: bool,
+    address_modes: [wgpu::AddressMode; 3],
+    mag_filter: wgpu::FilterMode,
+    min_filter: wgpu::FilterMode,
+    mipmap_filter: wgpu::FilterMode,
+    lod_min_clamp: f32,
+    lod_max_clamp: f32,
+    compare: Option<wgpu::CompareFunction>,
+    anisotropy_clamp: Option<NonZeroU8>,
+    border_color: Option<wgpu::SamplerBorderColor>,
+}
+
+impl Default for TextureBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextureBuilder {
+
+    pub fn new() -> Self {
+        Self {
+            label: None,
+            format: None,
+            srgb: true,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: vec![],
+            mipmaps: false,
+            address_modes: [wgpu::AddressMode::ClampToEdge; 3],
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: f32::MAX,
+            compare: None,
+            anisotropy_clamp: None,
+            border_color: None,
+        }
+    }
+
+    /// A debug label for the texture and its view and sampler.
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    /// The texture format, instead of `Rgba8UnormSrgb` or the one picked for an image's color type.\
+    /// An image must have the same channels and precision as the format, only its sRGB suffix may differ.
+    pub fn format(mut self, format: wgpu::TextureFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Whether 8-bit colors are stored as sRGB (the default) or linear values.\
+    /// Data that isn't a color, like normal maps or roughness, should use `srgb(false)`.
+    pub fn srgb(mut self, srgb: bool) -> Self {
+        self.srgb = srgb;
+        self
+    }
+
+    /// Usages in addition to `TEXTURE_BINDING | COPY_DST`, e.g. `RENDER_ATTACHMENT`, `STORAGE_BINDING` or `COPY_SRC`.\
+    /// Storage textures need a format that allows it, which excludes the sRGB ones.
+    pub fn usage(mut self, usage: wgpu::TextureUsages) -> Self {
+        self.usage |= usage;
+        self
+    }
+
+    /// Other formats the texture's views may have, e.g. its sRGB counterpart.
+    pub fn view_formats(mut self, view_formats: &[wgpu::TextureFormat]) -> Self {
+        self.view_formats = view_formats.to_vec();
+        self
+    }
+
+    /// Give the texture a full mip chain.\
+    /// Images fill it on the GPU if the format is renderable and filterable, and with `image` downscaling otherwise.\
+    /// An empty texture's chain is left empty, see `Texture::generate_mipmaps`.
+    pub fn mipmaps(mut self, mipmaps: bool) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+
+    /// How the sampler handles coordinates outside of the texture, in all directions.
+    pub fn address_mode(mut self, mode: wgpu::AddressMode) -> Self {
+        self.address_modes = [mode; 3];
+        self
+    }
+
+    /// How the sampler handles coordinates outside of the texture, in the u, v and w directions.
+    pub fn address_modes(mut self, u: wgpu::AddressMode, v: wgpu::AddressMode, w: wgpu::AddressMode) -> Self {
+        self.address_modes = [u, v, w];
+        self
+    }
+
+    /// The color `AddressMode::ClampToBorder` clamps to, needs `Features::ADDRESS_MODE_CLAMP_TO_BORDER`.
+    pub fn border_color(mut self, color: wgpu::SamplerBorderColor) -> Self {
+        self.border_color = Some(color);
+        self
+    }
+
+    /// The filter for both magnified and minified texels.
+    pub fn filter(mut self, filter: wgpu::FilterMode) -> Self {
+        self.mag_filter = filter;
+        self.min_filter = filter;
+        self
+    }
+
+    pub fn mag_filter(mut self, filter: wgpu::FilterMode) -> Self {
+        self.mag_filter = filter;
+        self
+    }
+
+    pub fn min_filter(mut self, filter: wgpu::FilterMode) -> Self {
+        self.min_filter = filter;
+        self
+    }
+
+    pub fn mipmap_filter(mut self, filter: wgpu::FilterMode) -> Self {
+        self.mipmap_filter = filter;
+        self
+    }
+
+    /// The maximum anisotropy of the sampler, 1, 2, 4, 8 or 16. Takes effect only with linear filters.
+    pub fn anisotropy(mut self, clamp: u8) -> Self {
+        self.anisotropy_clamp = NonZeroU8::new(clamp);
+        self
+    }
+
+    /// The range of mip levels the sampler may use.
+    pub fn lod_clamp(mut self, min: f32, max: f32) -> Self {
+        self.lod_min_clamp = min;
+        self.lod_max_clamp = max;
+        self
+    }
+
+    /// Make the sampler a comparison sampler, e.g. for shadow maps.\
+    /// It has to be bound as `SamplerBindingType::Comparison`.
+    pub fn compare(mut self, compare: wgpu::CompareFunction) -> Self {
+        self.compare = Some(compare);
+        self
+    }
+
+    /// Create an empty texture.
+    pub fn build(&self, state: &State, width: u32, height: u32) -> Result<Texture> {
+        self.build_shape(state, Shape::D2, width, height, 1)
+    }
+
+    /// Create an empty array of 2D textures, viewed as `D2Array`.
+    pub fn build_array(&self, state: &State, width: u32, height: u32, layers: u32) -> Result<Texture> {
+        self.build_shape(state, Shape::Array, width, height, layers)
+    }
+
+    /// Create an empty cube map with square faces, viewed as `Cube`.
+    pub fn build_cube(&self, state: &State, face_size: u32) -> Result<Texture> {
+        self.build_shape(state, Shape::Cube, face_size, face_size, 6)
+    }
+
+    /// Create an empty 3D texture, viewed as `D3`. Volumes don't have mip chains.
+    pub fn build_volume(&self, state: &State, width: u32, height: u32, depth: u32) -> Result<Texture> {
+        self.build_shape(state, Shape::Volume, width, height, depth)
+    }
+
+    /// Upload an encoded image, or a KTX2 or DDS file which are recognized by their magic bytes.
+    pub fn from_bytes(&self, state: &State, bytes: &[u8]) -> Result<Texture> {
+        if bytes.starts_with(&KTX2_MAGIC) {
+            return self.from_ktx2(state, bytes);
+        }
+        if bytes.starts_with(&DDS_MAGIC) {
+            return self.from_dds(state, bytes);
+        }
+        let img = image::load_from_memory(bytes)?;
+        self.from_image(state, &img)
+    }
+
+    /// Upload a KTX2 file, see `from_container`.
+    pub fn from_ktx2(&self, state: &State, bytes: &[u8]) -> Result<Texture> {
+        self.from_container(state, &ContainerImage::from_ktx2(bytes)?)
+    }
+
+    /// Upload a DDS file, see `from_container`.
+    pub fn from_dds(&self, state: &State, bytes: &[u8]) -> Result<Texture> {
+        self.from_container(state, &ContainerImage::from_dds(bytes)?)
+    }
+
+    /// Upload the layers, cube faces and mip levels stored in a KTX2 or DDS file, in the file's format.\
+    /// The default `StateBuilder` enables the compression features the adapter has, so those formats upload as they are.
+    /// If the device lacks the feature of a compressed format, BC1 to BC5, BC7, ETC2, EAC and ASTC LDR are decompressed on the CPU.\
+    /// The builder's format, sRGB and mipmap options don't apply.
+    pub fn from_container(&self, state: &State, image: &ContainerImage) -> Result<Texture> {
+        let image = supported_image(state, image)?;
+        let (shape, layers) = match (image.cube, image.layers, image.depth) {
+            (_, _, depth) if depth > 1 => (Shape::Volume, depth),
+            (true, 6, _) => (Shape::Cube, 6),
+            (true, layers, _) => (Shape::CubeArray, layers),
+            (false, 1, _) => (Shape::D2, 1),
+            (false, layers, _) => (Shape::Array, layers),
+        };
+        let size = wgpu::Extent3d {
+            width: image.width,
+            height: image.height,
+            depth_or_array_layers: layers
+        };
+        let texture = self.create(state, shape, size, image.format, image.mip_levels, wgpu::TextureUsages::empty())?;
+        write_container(&state.queue, &texture.texture, &image);
+        Ok(texture)
+    }
+
+    /// Upload an image of any color type, see `Texture::from_image` for the formats it is stored in.
+    pub fn from_image(&self, state: &State, img: &DynamicImage) -> Result<Texture> {
+        self.upload_layers(state, Shape::D2, std::slice::from_ref(img))
+    }
+
+    /// Upload images of the same size and precision as the layers of a `D2Array` texture.
+    pub fn array_from_images(&self, state: &State, images: &[DynamicImage]) -> Result<Texture> {
+        self.upload_layers(state, Shape::Array, images)
+    }
+
+    /// Upload six square images as the faces of a cube map, in the order +X, -X, +Y, -Y, +Z, -Z.
+    pub fn cubemap_from_faces(&self, state: &State, faces: &[DynamicImage; 6]) -> Result<Texture> {
+        let (width, height) = faces[0].dimensions();
+        if width != height {
+            bail!("Cube map faces must be square, not {}x{}", width, height);
+        }
+        self.upload_layers(state, Shape::Cube, faces)
+    }
+
+    /// Upload images of the same size and precision as the depth slices of a 3D texture, viewed as `D3`.
+    pub fn volume_from_slices(&self, state: &State, slices: &[DynamicImage]) -> Result<Texture> {
+        self.upload_layers(state, Shape::Volume, slices)
+    }
+
+    /// Render a cube map with `face_size` texels per side from an equirectangular (latitude/longitude) panorama.\
+    /// Float panoramas are stored as `Rgba16Float` so they can be filtered while converting.\
+    /// Mipmaps are rendered from the faces, so they are an error for formats that can't be filtered.
+    pub fn cubemap_from_equirectangular(&self, state: &State, img: &DynamicImage, face_size: u32) -> Result<Texture> {
+        let (image_format, bytes_per_pixel, pixels) = match image_data(img) {
+            (wgpu::TextureFormat::Rgba32Float, _, _) => (wgpu::TextureFormat::Rgba16Float, 8, Cow::Owned(half_floats(&img.to_rgba32f()))),
+            data => data,
+        };
+        let format = self.image_format(img, image_format)?;
+        if !can_render(state, format) {
+            bail!("Can't render a cube map of format {:?}", format);
+        }
+        if self.mipmaps && !can_blit(state, format) {
+            bail!("Can't generate mipmaps for a cube map of format {:?}, build it without `mipmaps`", format);
+        }
+
+        let (width, height) = img.dimensions();
+        let source = TextureBuilder::new()
+            .format(format)
+            .filter(wgpu::FilterMode::Linear)
+            .address_modes(wgpu::AddressMode::Repeat, wgpu::AddressMode::ClampToEdge, wgpu::AddressMode::ClampToEdge)
+            .create(state, Shape::D2, wgpu::Extent3d { width, height, depth_or_array_layers: 1 }, format, 1, wgpu::TextureUsages::empty())?;
+        write_level(&state.queue, &source.texture, 0, 0, bytes_per_pixel, &pixels);
+
+        let mip_levels = self.mip_levels(face_size, face_size);
+        let cube = self.create(state, Shape::Cube, wgpu::Extent3d { width: face_size, height: face_size, depth_or_array_layers: 6 }, format, mip_levels, wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC)?;
+        render_equirectangular(&state.device, &state.queue, &source, &cube.texture);
+        if mip_levels > 1 {
+            blit_mipmaps(&state.device, &state.queue, &cube.texture);
+        }
+
+        Ok(cube)
+    }
+
+    fn build_shape(&self, state: &State, shape: Shape, width: u32, height: u32, layers: u32) -> Result<Texture> {
+        let format = self.format.unwrap_or_else(|| self.color_space(wgpu::TextureFormat::Rgba8UnormSrgb));
+        let mip_levels = if shape == Shape::Volume { 1 } else { self.mip_levels(width, height) };
+        self.create(state, shape, wgpu::Extent3d { width, height, depth_or_array_layers: layers }, format, mip_levels, wgpu::TextureUsages::empty())
+    }
+
+    /// Upload one image per layer, or per depth slice of a volume, and fill the mip chains.
+    fn upload_layers(&self, state: &State, shape: Shape, images: &[DynamicImage]) -> Result<Texture> {
+        let first = images.first().context("Can't create a texture from no images")?;
+        let (width, height) = first.dimensions();
+        let layers: Vec<_> = images.iter().map(image_data).collect();
+        let image_format = layers[0].0;
+        for (img, (layer_format, _, _)) in images.iter().zip(&layers) {
+            if img.dimensions() != (width, height) {
+                bail!("All images of a texture must have the same size, found {}x{} and {:?}", width, height, img.dimensions());
+            }
+            if *layer_format != image_format {
+                bail!("All images of a texture must have the same precision, found {:?} and {:?}", first.color(), img.color());
+            }
+        }
+
+        let format = self.image_format(first, image_format)?;
+        let mip_levels = if shape == Shape::Volume { 1 } else { self.mip_levels(width, height) };
+        let blit = mip_levels > 1 && can_blit(state, format);
+        let extra_usage = if blit { wgpu::TextureUsages::COPY_SRC } else { wgpu::TextureUsages::empty() };
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: images.len() as u32
+        };
+        let texture = self.create(state, shape, size, format, mip_levels, extra_usage)?;
+
+        for (layer, (_, bytes_per_pixel, pixels)) in layers.iter().enumerate() {
+            write_level(&state.queue, &texture.texture, 0, layer as u32, *bytes_per_pixel, pixels);
+        }
+        if blit {
+            blit_mipmaps(&state.device, &state.queue, &texture.texture);
+        } else if mip_levels > 1 {
+            for (layer, img) in images.iter().enumerate() {
+                upload_cpu_mipmaps(&state.queue, &texture.texture, layer as u32, img);
+            }
+        }
+
+        Ok(texture)
+    }
+
+    /// The format to store an image in, the builder's own if it fits the image's data.
+    fn image_format(&self, img: &DynamicImage, image_format: wgpu::TextureFormat) -> Result<wgpu::TextureFormat> {
+        match self.format {
+            Some(format) if format.remove_srgb_suffix() != image_format.remove_srgb_suffix() => {
+                bail!("A {:?} image is uploaded as {:?}, it can't be stored in a {:?} texture", img.color(), image_format, format)
+            }
+            Some(format) => Ok(format),
+            None => Ok(self.color_space(image_format)),
+        }
+    }
+
+    fn mip_levels(&self, width: u32, height: u32) -> u32 {
+        if self.mipmaps {
+            mip_level_count(width, height)
+        } else {
+            1
+        }
+    }
+
+    fn color_space(&self, format: wgpu::TextureFormat) -> wgpu::TextureFormat {
+        if self.srgb {
+            format.add_srgb_suffix()
+        } else {
+            format.remove_srgb_suffix()
+        }
+    }
+
+    /// Check the size, format and usage against the device, then create the texture, its view and sampler.
+    fn create(&self, state: &State, shape: Shape, size: wgpu::Extent3d, format: wgpu::TextureFormat, mip_level_count: u32, extra_usage: wgpu::TextureUsages) -> Result<Texture> {
+        if size.width == 0 || size.height == 0 || size.depth_or_array_layers == 0 {
+            bail!("Can't create an empty {}x{}x{} texture", size.width, size.height, size.depth_or_array_layers);
+        }
+        let limits = state.device.limits();
+        let (max_dimension, max_layers) = match shape {
+            Shape::Volume => (limits.max_texture_dimension_3d, limits.max_texture_dimension_3d),
+            _ => (limits.max_texture_dimension_2d, limits.max_texture_array_layers),
+        };
+        if size.width > max_dimension || size.height > max_dimension || size.depth_or_array_layers > max_layers {
+            bail!(
+                "A {}x{}x{} texture is larger than the device's maximum of {} texels per side and {} layers",
+                size.width, size.height, size.depth_or_array_layers, max_dimension, max_layers
+            );
+        }
+
+        let info = format.describe();
+        let missing_features = info.required_features - state.device.features();
+        if !missing_features.is_empty() {
+            bail!("{:?} textures need the device features {:?}", format, missing_features);
+        }
+        let usage = self.usage | extra_usage;
+        let missing_usages = usage - format_features(state, format).allowed_usages;
+        if !missing_usages.is_empty() {
+            bail!("{:?} textures can't have {:?} usage", format, missing_usages);
+        }
+
+        let label = self.label.as_deref();
+        let texture = state.device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count,
+            sample_count: 1,
+            dimension: shape.dimension(),
+            format,
+            usage,
+            view_formats: &self.view_formats,
+        });
+
+        let view_dimension = shape.view_dimension();
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label,
+            dimension: Some(view_dimension),
+            ..Default::default()
+        });
+        let sampler = state.device.create_sampler(&wgpu::SamplerDescriptor {
+            label,
+            address_mode_u: self.address_modes[0],
+            address_mode_v: self.address_modes[1],
+            address_mode_w: self.address_modes[2],
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            lod_min_clamp: self.lod_min_clamp,
+            lod_max_clamp: self.lod_max_clamp,
+            compare: self.compare,
+            anisotropy_clamp: self.anisotropy_clamp,
+            border_color: self.border_color,
+        });
+
+        Ok(Texture {
+            texture,
+            view,
+            sampler,
+            view_dimension,
+            view_formats: self.view_formats.clone(),
+            label: self.label.clone(),
+        })
+    }
+}
+
+/// How the layers of a texture are laid out and viewed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Shape {
+    D2,
+    Array,
+    Cube,
+    CubeArray,
+    Volume,
+}
+
+impl Shape {
+    fn dimension(self) -> wgpu::TextureDimension {
+        match self {
+            Shape::Volume => wgpu::TextureDimension::D3,
+            _ => wgpu::TextureDimension::D2,
+        }
+    }
+
+    fn view_dimension(self) -> wgpu::TextureViewDimension {
+        match self {
+            Shape::D2 => wgpu::TextureViewDimension::D2,
+            Shape::Array => wgpu::TextureViewDimension::D2Array,
+            Shape::Cube => wgpu::TextureViewDimension::Cube,
+            Shape::CubeArray => wgpu::TextureViewDimension::CubeArray,
+            Shape::Volume => wgpu::TextureViewDimension::D3,
+        }
+    }
+}
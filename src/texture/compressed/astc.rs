@@ -0,0 +1,467 @@
+//! A decoder for ASTC LDR blocks of every 2D block size.
+
+/// The number of texels in the largest block, 12x12.
+pub(super) const MAX_TEXELS: usize = 144;
+
+/// What blocks with reserved or HDR encodings decode to.
+const ERROR_COLOR: [u8; 4] = [255, 0, 255, 255];
+
+/// The number of values of each quantization level.
+const RANGES: [u32; 21] = [2, 3, 4, 5, 6, 8, 10, 12, 16, 20, 24, 32, 40, 48, 64, 80, 96, 128, 160, 192, 256];
+
+/// The lowest quantization level color endpoints may use, 6 values.
+const MIN_COLOR_QUANT: usize = 4;
+
+/// How values of a quantization level are stored: as a trit (3) or quint (5) and low bits, or only bits (1).
+fn encoding(quant: usize) -> (u32, u32) {
+    let range = RANGES[quant];
+    let base = if range.is_multiple_of(3) { 3 } else if range.is_multiple_of(5) { 5 } else { 1 };
+    (base, (range / base).trailing_zeros())
+}
+
+/// The number of bits `count` values of a quantization level take.
+fn ise_bits(count: u32, quant: usize) -> u32 {
+    let (base, bits) = encoding(quant);
+    bits * count + match base {
+        3 => (8 * count).div_ceil(5),
+        5 => (7 * count).div_ceil(3),
+        _ => 0,
+    }
+}
+
+/// Reads bits from the bottom up, bits past `end` read as zero.
+struct BitReader {
+    data: u128,
+    position: u32,
+    end: u32,
+}
+
+impl BitReader {
+    fn take(&mut self, count: u32) -> u32 {
+        let mut value = 0;
+        for i in 0..count {
+            let position = self.position + i;
+            if position < self.end {
+                value |= (((self.data >> position) & 1) as u32) << i;
+            }
+        }
+        self.position += count;
+        value
+    }
+}
+
+fn decode_trits(t: u32) -> [u32; 5] {
+    let bit = |value: u32, n: u32| (value >> n) & 1;
+    let (c, t3, t4) = if (t >> 2) & 7 == 7 {
+        (((t >> 5) & 7) << 2 | (t & 3), 2, 2)
+    } else if (t >> 5) & 3 == 3 {
+        (t & 0x1F, bit(t, 7), 2)
+    } else {
+        (t & 0x1F, (t >> 5) & 3, bit(t, 7))
+    };
+    let (t0, t1, t2) = if c & 3 == 3 {
+        (bit(c, 3) << 1 | (bit(c, 2) & !bit(c, 3) & 1), bit(c, 4), 2)
+    } else if (c >> 2) & 3 == 3 {
+        (c & 3, 2, 2)
+    } else {
+        (bit(c, 1) << 1 | (bit(c, 0) & !bit(c, 1) & 1), (c >> 2) & 3, bit(c, 4))
+    };
+    [t0, t1, t2, t3, t4]
+}
+
+fn decode_quints(q: u32) -> [u32; 3] {
+    let bit = |n: u32| (q >> n) & 1;
+    if (q >> 1) & 3 == 3 && (q >> 5) & 3 == 0 {
+        let q2 = bit(0) << 2 | (bit(4) & !bit(0) & 1) << 1 | (bit(3) & !bit(0) & 1);
+        return [4, 4, q2];
+    }
+    let (c, q2) = if (q >> 1) & 3 == 3 {
+        (((q >> 3) & 3) << 3 | (!(q >> 5) & 3) << 1 | bit(0), 4)
+    } else {
+        (q & 0x1F, (q >> 5) & 3)
+    };
+    if c & 7 == 5 {
+        [(c >> 3) & 3, 4, q2]
+    } else {
+        [c & 7, (c >> 3) & 3, q2]
+    }
+}
+
+/// Decode the integer sequence of `values.len()` values starting at `start`, as pairs of the trit or quint and the low bits.
+fn decode_ise(data: u128, start: u32, quant: usize, values: &mut [(u32, u32)]) {
+    let (base, bits) = encoding(quant);
+    let mut reader = BitReader {
+        data,
+        position: start,
+        end: start + ise_bits(values.len() as u32, quant),
+    };
+    match base {
+        3 => for group in values.chunks_mut(5) {
+            // The trit bits of a group of five are spread between the values' low bits.
+            let mut low = [0; 5];
+            let mut t = 0;
+            for (i, (count, shift)) in [(2, 0), (2, 2), (1, 4), (2, 5), (1, 7)].into_iter().enumerate() {
+                low[i] = reader.take(bits);
+                t |= reader.take(count) << shift;
+            }
+            for ((value, trit), low) in group.iter_mut().zip(decode_trits(t)).zip(low) {
+                *value = (trit, low);
+            }
+        },
+        5 => for group in values.chunks_mut(3) {
+            let mut low = [0; 3];
+            let mut q = 0;
+            for (i, (count, shift)) in [(3, 0), (2, 3), (2, 5)].into_iter().enumerate() {
+                low[i] = reader.take(bits);
+                q |= reader.take(count) << shift;
+            }
+            for ((value, quint), low) in group.iter_mut().zip(decode_quints(q)).zip(low) {
+                *value = (quint, low);
+            }
+        },
+        _ => for value in values {
+            *value = (0, reader.take(bits));
+        },
+    }
+}
+
+/// Repeat the `bits` low bits of `value` until they fill `to` bits.
+fn replicate(value: u32, bits: u32, to: u32) -> u32 {
+    let mut result = 0;
+    let mut filled = 0;
+    while filled < to {
+        result = (result << bits) | value;
+        filled += bits;
+    }
+    result >> (filled - to)
+}
+
+/// Unquantize a weight to 0..=64.
+fn unquantize_weight(quant: usize, (d, m): (u32, u32)) -> u32 {
+    let (base, bits) = encoding(quant);
+    let bit = |n: u32| (m >> n) & 1;
+    let weight = match (base, bits) {
+        (1, _) => replicate(m, bits, 6),
+        (3, 0) => return d * 32,
+        (5, 0) => return d * 16,
+        _ => {
+            let a = if bit(0) == 1 { 0x7F } else { 0 };
+            let (b, c) = match (base, bits) {
+                (3, 1) => (0, 50),
+                (5, 1) => (0, 28),
+                (3, 2) => (bit(1) * 0x45, 23),
+                (5, 2) => (bit(1) * 0x42, 13),
+                (3, 3) => (bit(2) * 0x42 + bit(1) * 0x21, 11),
+                _ => unreachable!("weights are at most 32 levels"),
+            };
+            (a & 0x20) | (((d * c + b) ^ a) >> 2)
+        }
+    };
+    if weight > 32 { weight + 1 } else { weight }
+}
+
+/// Unquantize a color endpoint value to 0..=255.
+fn unquantize_color(quant: usize, (d, m): (u32, u32)) -> u32 {
+    let (base, bits) = encoding(quant);
+    if base == 1 {
+        return replicate(m, bits, 8);
+    }
+    let bit = |n: u32| (m >> n) & 1;
+    let a = if bit(0) == 1 { 0x1FF } else { 0 };
+    let (b, c) = match (base, bits) {
+        (3, 1) => (0, 204),
+        (5, 1) => (0, 113),
+        (3, 2) => (bit(1) * 0x116, 93),
+        (5, 2) => (bit(1) * 0x10C, 54),
+        (3, 3) => (bit(2) * 0x10A + bit(1) * 0x85, 44),
+        (5, 3) => (bit(2) * 0x105 + bit(1) * 0x82, 26),
+        (3, 4) => (bit(3) * 0x104 + bit(2) * 0x82 + bit(1) * 0x41, 22),
+        (5, 4) => (bit(3) * 0x102 + bit(2) * 0x81 + bit(1) * 0x40, 13),
+        (3, 5) => (bit(4) * 0x102 + bit(3) * 0x81 + bit(2) * 0x40 + bit(1) * 0x20, 11),
+        (5, 5) => (bit(4) * 0x101 + bit(3) * 0x80 + bit(2) * 0x40 + bit(1) * 0x20, 6),
+        (3, 6) => (bit(5) * 0x101 + bit(4) * 0x80 + bit(3) * 0x40 + bit(2) * 0x20 + bit(1) * 0x10, 5),
+        _ => unreachable!("color endpoints use at least 6 levels"),
+    };
+    (a & 0x80) | (((d * c + b) ^ a) >> 2)
+}
+
+/// The weight grid size, whether there are two weight planes and the weights' quantization level of a block mode.
+fn block_mode(mode: u32) -> Option<(usize, usize, bool, usize)> {
+    let a = ((mode >> 5) & 3) as usize;
+    let mut high_precision = (mode >> 9) & 1;
+    let mut dual_plane = (mode >> 10) & 1;
+    let (width, height, range) = if mode & 3 != 0 {
+        let b = ((mode >> 7) & 3) as usize;
+        let size = match (mode >> 2) & 3 {
+            0 => (b + 4, a + 2),
+            1 => (b + 8, a + 2),
+            2 => (a + 2, b + 8),
+            _ if mode & 0x100 != 0 => ((b & 1) + 2, a + 2),
+            _ => (a + 2, (b & 1) + 6),
+        };
+        (size.0, size.1, (mode & 3) << 1 | (mode >> 4) & 1)
+    } else {
+        if (mode >> 2) & 3 == 0 {
+            return None;
+        }
+        let b = ((mode >> 9) & 3) as usize;
+        let size = match (mode >> 7) & 3 {
+            0 => (12, a + 2),
+            1 => (a + 2, 12),
+            2 => {
+                high_precision = 0;
+                dual_plane = 0;
+                (a + 6, b + 6)
+            }
+            _ => match a {
+                0 => (6, 10),
+                1 => (10, 6),
+                _ => return None,
+            },
+        };
+        (size.0, size.1, (mode >> 1) & 6 | (mode >> 4) & 1)
+    };
+    Some((width, height, dual_plane == 1, (range - 2 + 6 * high_precision) as usize))
+}
+
+fn hash52(mut p: u32) -> u32 {
+    p ^= p >> 15;
+    p = p.wrapping_sub(p << 17);
+    p = p.wrapping_add(p << 7);
+    p = p.wrapping_add(p << 4);
+    p ^= p >> 5;
+    p = p.wrapping_add(p << 16);
+    p ^= p >> 7;
+    p ^= p >> 3;
+    p ^= p << 6;
+    p ^= p >> 17;
+    p
+}
+
+/// The partition of a texel, from the block's 10 bit partition seed.
+fn select_partition(seed: u32, x: u32, y: u32, partitions: u32, small_block: bool) -> usize {
+    let (x, y) = if small_block { (x << 1, y << 1) } else { (x, y) };
+    let seed = seed + (partitions - 1) * 1024;
+    let rnum = hash52(seed);
+    let mut seeds = [0u32; 8];
+    for (i, s) in seeds.iter_mut().enumerate() {
+        *s = (rnum >> (i * 4)) & 0xF;
+        *s *= *s;
+    }
+    let (sh1, sh2) = if seed & 1 != 0 {
+        (if seed & 2 != 0 { 4 } else { 5 }, if partitions == 3 { 6 } else { 5 })
+    } else {
+        (if partitions == 3 { 6 } else { 5 }, if seed & 2 != 0 { 4 } else { 5 })
+    };
+    for (i, s) in seeds.iter_mut().enumerate() {
+        *s >>= if i % 2 == 0 { sh1 } else { sh2 };
+    }
+
+    let a = (seeds[0] * x + seeds[1] * y + (rnum >> 14)) & 0x3F;
+    let b = (seeds[2] * x + seeds[3] * y + (rnum >> 10)) & 0x3F;
+    let c = if partitions < 3 { 0 } else { (seeds[4] * x + seeds[5] * y + (rnum >> 6)) & 0x3F };
+    let d = if partitions < 4 { 0 } else { (seeds[6] * x + seeds[7] * y + (rnum >> 2)) & 0x3F };
+
+    if a >= b && a >= c && a >= d {
+        0
+    } else if b >= c && b >= d {
+        1
+    } else if c >= d {
+        2
+    } else {
+        3
+    }
+}
+
+fn bit_transfer_signed(a: &mut i32, b: &mut i32) {
+    *b = (*b >> 1) | (*a & 0x80);
+    *a = (*a >> 1) & 0x3F;
+    if *a & 0x20 != 0 {
+        *a -= 0x40;
+    }
+}
+
+fn blue_contract(color: [i32; 4]) -> [i32; 4] {
+    [(color[0] + color[2]) >> 1, (color[1] + color[2]) >> 1, color[2], color[3]]
+}
+
+/// The two endpoints of an LDR color endpoint mode, `None` for HDR modes.
+fn endpoints(cem: u32, v: &[i32]) -> Option<[[i32; 4]; 2]> {
+    let mut v = [v[0], v[1], v.get(2).copied().unwrap_or(0), v.get(3).copied().unwrap_or(0),
+        v.get(4).copied().unwrap_or(0), v.get(5).copied().unwrap_or(0), v.get(6).copied().unwrap_or(0), v.get(7).copied().unwrap_or(0)];
+    let endpoints = match cem {
+        0 => [[v[0], v[0], v[0], 255], [v[1], v[1], v[1], 255]],
+        1 => {
+            let l0 = (v[0] >> 2) | (v[1] & 0xC0);
+            let l1 = (l0 + (v[1] & 0x3F)).min(255);
+            [[l0, l0, l0, 255], [l1, l1, l1, 255]]
+        }
+        4 => [[v[0], v[0], v[0], v[2]], [v[1], v[1], v[1], v[3]]],
+        5 => {
+            let [v0, v1, v2, v3, ..] = &mut v;
+            bit_transfer_signed(v1, v0);
+            bit_transfer_signed(v3, v2);
+            [[v[0], v[0], v[0], v[2]], [v[0] + v[1], v[0] + v[1], v[0] + v[1], v[2] + v[3]]]
+        }
+        6 => [[(v[0] * v[3]) >> 8, (v[1] * v[3]) >> 8, (v[2] * v[3]) >> 8, 255], [v[0], v[1], v[2], 255]],
+        8 | 12 => {
+            let (a0, a1) = if cem == 12 { (v[6], v[7]) } else { (255, 255) };
+            if v[1] + v[3] + v[5] >= v[0] + v[2] + v[4] {
+                [[v[0], v[2], v[4], a0], [v[1], v[3], v[5], a1]]
+            } else {
+                [blue_contract([v[1], v[3], v[5], a1]), blue_contract([v[0], v[2], v[4], a0])]
+            }
+        }
+        9 | 13 => {
+            let [v0, v1, v2, v3, v4, v5, v6, v7] = &mut v;
+            bit_transfer_signed(v1, v0);
+            bit_transfer_signed(v3, v2);
+            bit_transfer_signed(v5, v4);
+            bit_transfer_signed(v7, v6);
+            let (a0, a1) = if cem == 13 { (v[6], v[6] + v[7]) } else { (255, 255) };
+            if v[1] + v[3] + v[5] >= 0 {
+                [[v[0], v[2], v[4], a0], [v[0] + v[1], v[2] + v[3], v[4] + v[5], a1]]
+            } else {
+                [blue_contract([v[0] + v[1], v[2] + v[3], v[4] + v[5], a1]), blue_contract([v[0], v[2], v[4], a0])]
+            }
+        }
+        10 => [[(v[0] * v[3]) >> 8, (v[1] * v[3]) >> 8, (v[2] * v[3]) >> 8, v[4]], [v[0], v[1], v[2], v[5]]],
+        _ => return None,
+    };
+    Some(endpoints.map(|endpoint| endpoint.map(|channel| channel.clamp(0, 255))))
+}
+
+/// Decode a constant color block.
+fn decode_void_extent(data: u128, out: &mut [[u8; 4]]) -> Option<()> {
+    let bits = |start: u32, count: u32| ((data >> start) & ((1 << count) - 1)) as u32;
+    // HDR colors aren't part of the LDR profile.
+    if bits(9, 1) == 1 {
+        return None;
+    }
+    let (min_s, max_s, min_t, max_t) = (bits(12, 13), bits(25, 13), bits(38, 13), bits(51, 13));
+    let no_extent = [min_s, max_s, min_t, max_t].iter().all(|&coordinate| coordinate == 0x1FFF);
+    if !no_extent && (min_s >= max_s || min_t >= max_t) {
+        return None;
+    }
+    let color = [0, 1, 2, 3].map(|channel| (bits(64 + channel * 16, 16) >> 8) as u8);
+    out.fill(color);
+    Some(())
+}
+
+/// Decode a block, `None` if it uses a reserved encoding.
+fn decode(data: u128, width: usize, height: usize, srgb: bool, out: &mut [[u8; 4]]) -> Option<()> {
+    let bits = |start: u32, count: u32| ((data >> start) & ((1 << count) - 1)) as u32;
+    let mode = bits(0, 11);
+    if mode & 0x1FF == 0x1FC {
+        return decode_void_extent(data, out);
+    }
+
+    let (grid_width, grid_height, dual_plane, weight_quant) = block_mode(mode)?;
+    let planes = if dual_plane { 2 } else { 1 };
+    let weight_count = grid_width * grid_height * planes;
+    let weight_bits = ise_bits(weight_count as u32, weight_quant);
+    if grid_width > width || grid_height > height || weight_count > 64 || !(24..=96).contains(&weight_bits) {
+        return None;
+    }
+    let partitions = bits(11, 2) + 1;
+    if dual_plane && partitions == 4 {
+        return None;
+    }
+
+    // The endpoint modes, with bits of the extended encoding stored below the weights.
+    let mut cems = [0; 4];
+    let mut below_weights = 128 - weight_bits;
+    let color_start = if partitions == 1 {
+        cems[0] = bits(13, 4);
+        17
+    } else {
+        let field = bits(23, 6);
+        if field & 3 == 0 {
+            cems.fill(field >> 2);
+        } else {
+            let extra = 3 * partitions - 4;
+            below_weights -= extra;
+            let encoded = (field | bits(below_weights, extra) << 6) >> 2;
+            let base_class = (field & 3) - 1;
+            for (i, cem) in cems.iter_mut().enumerate().take(partitions as usize) {
+                let class = base_class + ((encoded >> i) & 1);
+                *cem = class << 2 | (encoded >> (partitions as usize + 2 * i)) & 3;
+            }
+        }
+        29
+    };
+    let cems = &cems[..partitions as usize];
+    let (color_end, plane2_component) = if dual_plane {
+        (below_weights - 2, bits(below_weights - 2, 2) as usize)
+    } else {
+        (below_weights, 4)
+    };
+
+    let color_count: usize = cems.iter().map(|cem| 2 * (*cem as usize / 4 + 1)).sum();
+    if color_count > 18 || color_end < color_start {
+        return None;
+    }
+    let color_quant = (0..RANGES.len()).rev().find(|&quant| ise_bits(color_count as u32, quant) <= color_end - color_start)?;
+    if color_quant < MIN_COLOR_QUANT {
+        return None;
+    }
+    let mut values = [(0, 0); 18];
+    decode_ise(data, color_start, color_quant, &mut values[..color_count]);
+    let colors = values.map(|value| unquantize_color(color_quant, value) as i32);
+    let mut partition_endpoints = [[[0; 4]; 2]; 4];
+    let mut offset = 0;
+    for (endpoints_of, &cem) in partition_endpoints.iter_mut().zip(cems) {
+        *endpoints_of = endpoints(cem, &colors[offset..])?;
+        offset += 2 * (cem as usize / 4 + 1);
+    }
+
+    // Weights are stored bit reversed from the top of the block.
+    let mut values = [(0, 0); 64];
+    decode_ise(data.reverse_bits(), 0, weight_quant, &mut values[..weight_count]);
+    // Padded, so the infill can read one past the grid's edges with a weight of 0.
+    let mut weights = [[0; 64 + 13]; 2];
+    for (i, value) in values[..weight_count].iter().enumerate() {
+        weights[i % planes][i / planes] = unquantize_weight(weight_quant, *value);
+    }
+
+    let seed = bits(13, 10);
+    let small_block = width * height < 31;
+    let ds = (1024 + width / 2) / (width - 1);
+    let dt = (1024 + height / 2) / (height - 1);
+    for (i, texel) in out.iter_mut().enumerate() {
+        let (x, y) = (i % width, i / width);
+        let partition = if partitions > 1 { select_partition(seed, x as u32, y as u32, partitions, small_block) } else { 0 };
+
+        // Bilinear infill of the weight grid.
+        let gs = (ds * x * (grid_width - 1) + 32) >> 6;
+        let gt = (dt * y * (grid_height - 1) + 32) >> 6;
+        let (js, fs, jt, ft) = (gs >> 4, gs & 0xF, gt >> 4, gt & 0xF);
+        let v0 = js + jt * grid_width;
+        let w11 = (fs * ft + 8) >> 4;
+        let (w10, w01) = (ft - w11, fs - w11);
+        let w00 = 16 + w11 - fs - ft;
+        let infill = |plane: &[u32; 77]| {
+            (plane[v0] * w00 as u32 + plane[v0 + 1] * w01 as u32 + plane[v0 + grid_width] * w10 as u32 + plane[v0 + grid_width + 1] * w11 as u32 + 8) >> 4
+        };
+        let weight = [infill(&weights[0]), if dual_plane { infill(&weights[1]) } else { 0 }];
+
+        let [e0, e1] = partition_endpoints[partition];
+        for (channel, value) in texel.iter_mut().enumerate() {
+            let w = weight[(channel == plane2_component) as usize];
+            let expand = |e: i32| if srgb { (e as u32) << 8 | 0x80 } else { e as u32 * 257 };
+            let c = (expand(e0[channel]) * (64 - w) + expand(e1[channel]) * w + 32) >> 6;
+            *value = (c >> 8) as u8;
+        }
+    }
+    Some(())
+}
+
+/// Decode an ASTC block of `width` by `height` texels into `out`, row by row.\
+/// Reserved and HDR encodings decode to magenta, like the LDR profile asks for.
+pub(super) fn decode_astc(block: &[u8], width: usize, height: usize, srgb: bool, out: &mut [[u8; 4]]) {
+    let out = &mut out[..width * height];
+    let data = u128::from_le_bytes(block[..16].try_into().unwrap());
+    if decode(data, width, height, srgb, out).is_none() {
+        out.fill(ERROR_COLOR);
+    }
+}
@@ -0,0 +1,290 @@
+//! Decoders for the BC1 to BC5 and BC7 block formats.
+
+/// Expand a 5:6:5 color to 8 bits per channel.
+fn rgb565(color: u16) -> [u8; 4] {
+    let r = ((color >> 11) & 31) as u8;
+    let g = ((color >> 5) & 63) as u8;
+    let b = (color & 31) as u8;
+    [(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2), 255]
+}
+
+fn mix(a: [u8; 4], b: [u8; 4], wa: u32, wb: u32) -> [u8; 4] {
+    let mut out = [0; 4];
+    for c in 0..4 {
+        out[c] = ((a[c] as u32 * wa + b[c] as u32 * wb) / (wa + wb)) as u8;
+    }
+    out
+}
+
+/// The color half of a BC1, BC2 or BC3 block. BC2 and BC3 always use four colors.
+fn color_block(block: &[u8], four_colors: bool, out: &mut [[u8; 4]; 16]) {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let e0 = rgb565(c0);
+    let e1 = rgb565(c1);
+    let palette = if four_colors || c0 > c1 {
+        [e0, e1, mix(e0, e1, 2, 1), mix(e0, e1, 1, 2)]
+    } else {
+        [e0, e1, mix(e0, e1, 1, 1), [0, 0, 0, 0]]
+    };
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    for (i, texel) in out.iter_mut().enumerate() {
+        *texel = palette[(indices >> (2 * i) & 3) as usize];
+    }
+}
+
+/// An 8 value ramp between two endpoints, or 6 values plus both extremes if the first endpoint isn't the larger one.
+fn ramp(e0: i32, e1: i32, min: i32, max: i32) -> [i32; 8] {
+    if e0 > e1 {
+        [e0, e1, (6 * e0 + e1) / 7, (5 * e0 + 2 * e1) / 7, (4 * e0 + 3 * e1) / 7, (3 * e0 + 4 * e1) / 7, (2 * e0 + 5 * e1) / 7, (e0 + 6 * e1) / 7]
+    } else {
+        [e0, e1, (4 * e0 + e1) / 5, (3 * e0 + 2 * e1) / 5, (2 * e0 + 3 * e1) / 5, (e0 + 4 * e1) / 5, min, max]
+    }
+}
+
+/// A BC3 alpha or BC4 channel block, writing channel `channel` of each texel.\
+/// Signed values are stored as the bits of an `i8`.
+fn channel_block(block: &[u8], signed: bool, channel: usize, out: &mut [[u8; 4]; 16]) {
+    let values = if signed {
+        let e0 = (block[0] as i8).max(-127) as i32;
+        let e1 = (block[1] as i8).max(-127) as i32;
+        ramp(e0, e1, -127, 127)
+    } else {
+        ramp(block[0] as i32, block[1] as i32, 0, 255)
+    };
+    let mut bits = [0u8; 8];
+    bits[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(bits);
+    for (i, texel) in out.iter_mut().enumerate() {
+        texel[channel] = values[(indices >> (3 * i) & 7) as usize] as u8;
+    }
+}
+
+pub(crate) fn decode_bc1(block: &[u8], out: &mut [[u8; 4]; 16]) {
+    color_block(block, false, out);
+}
+
+pub(crate) fn decode_bc2(block: &[u8], out: &mut [[u8; 4]; 16]) {
+    color_block(&block[8..], true, out);
+    let alpha = u64::from_le_bytes(block[..8].try_into().unwrap());
+    for (i, texel) in out.iter_mut().enumerate() {
+        texel[3] = (alpha >> (4 * i) & 15) as u8 * 17;
+    }
+}
+
+pub(crate) fn decode_bc3(block: &[u8], out: &mut [[u8; 4]; 16]) {
+    color_block(&block[8..], true, out);
+    channel_block(&block[..8], false, 3, out);
+}
+
+pub(crate) fn decode_bc4(block: &[u8], signed: bool, out: &mut [[u8; 4]; 16]) {
+    *out = [[0, 0, 0, 255]; 16];
+    channel_block(block, signed, 0, out);
+}
+
+pub(crate) fn decode_bc5(block: &[u8], signed: bool, out: &mut [[u8; 4]; 16]) {
+    *out = [[0, 0, 0, 255]; 16];
+    channel_block(&block[..8], signed, 0, out);
+    channel_block(&block[8..], signed, 1, out);
+}
+
+/// Reads bits from the least significant end of a 128-bit block.
+struct Bits(u128);
+
+impl Bits {
+    fn take(&mut self, count: u32) -> u8 {
+        let value = (self.0 & ((1 << count) - 1)) as u8;
+        self.0 >>= count;
+        value
+    }
+}
+
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    endpoint_pbits: bool,
+    shared_pbits: bool,
+    index_bits: u32,
+    index2_bits: u32,
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    Bc7Mode { subsets: 3, partition_bits: 4, rotation_bits: 0, index_selection_bits: 0, color_bits: 4, alpha_bits: 0, endpoint_pbits: true, shared_pbits: false, index_bits: 3, index2_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 6, alpha_bits: 0, endpoint_pbits: false, shared_pbits: true, index_bits: 3, index2_bits: 0 },
+    Bc7Mode { subsets: 3, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 0, endpoint_pbits: false, shared_pbits: false, index_bits: 2, index2_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 0, endpoint_pbits: true, shared_pbits: false, index_bits: 2, index2_bits: 0 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 1, color_bits: 5, alpha_bits: 6, endpoint_pbits: false, shared_pbits: false, index_bits: 2, index2_bits: 3 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 0, color_bits: 7, alpha_bits: 8, endpoint_pbits: false, shared_pbits: false, index_bits: 2, index2_bits: 2 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 7, endpoint_pbits: true, shared_pbits: false, index_bits: 4, index2_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 5, endpoint_pbits: true, shared_pbits: false, index_bits: 2, index2_bits: 0 },
+];
+
+/// The subset of each texel for the 64 two-subset partitions, one bit per texel.
+const PARTITIONS_2: [u16; 64] = [
+    0xCCCC, 0x8888, 0xEEEE, 0xECC8, 0xC880, 0xFEEC, 0xFEC8, 0xEC80,
+    0xC800, 0xFFEC, 0xFE80, 0xE800, 0xFFE8, 0xFF00, 0xFFF0, 0xF000,
+    0xF710, 0x008E, 0x7100, 0x08CE, 0x008C, 0x7310, 0x3100, 0x8CCE,
+    0x088C, 0x3110, 0x6666, 0x366C, 0x17E8, 0x0FF0, 0x718E, 0x399C,
+    0xAAAA, 0xF0F0, 0x5A5A, 0x33CC, 0x3C3C, 0x55AA, 0x9696, 0xA55A,
+    0x73CE, 0x13C8, 0x324C, 0x3BDC, 0x6996, 0xC33C, 0x9966, 0x0660,
+    0x0272, 0x04E4, 0x4E40, 0x2720, 0xC936, 0x936C, 0x39C6, 0x639C,
+    0x9336, 0x9CC6, 0x817E, 0xE718, 0xCCF0, 0x0FCC, 0x7744, 0xEE22,
+];
+
+/// The subset of each texel for the 64 three-subset partitions, two bits per texel.
+const PARTITIONS_3: [u32; 64] = [
+    0xAA685050, 0x6A5A5040, 0x5A5A4200, 0x5450A0A8, 0xA5A50000, 0xA0A05050, 0x5555A0A0, 0x5A5A5050,
+    0xAA550000, 0xAA555500, 0xAAAA5500, 0x90909090, 0x94949494, 0xA4A4A4A4, 0xA9A59450, 0x2A0A4250,
+    0xA5945040, 0x0A425054, 0xA5A5A500, 0x55A0A0A0, 0xA8A85454, 0x6A6A4040, 0xA4A45000, 0x1A1A0500,
+    0x0050A4A4, 0xAAA59090, 0x14696914, 0x69691400, 0xA08585A0, 0xAA821414, 0x50A4A450, 0x6A5A0200,
+    0xA9A58000, 0x5090A0A8, 0xA8A09050, 0x24242424, 0x00AA5500, 0x24924924, 0x24499224, 0x50A50A50,
+    0x500AA550, 0xAAAA4444, 0x66660000, 0xA5A0A5A0, 0x50A050A0, 0x69286928, 0x44AAAA44, 0x66666600,
+    0xAA444444, 0x54A854A8, 0x95809580, 0x96969600, 0xA85454A8, 0x80959580, 0xAA141414, 0x96960000,
+    0xAAAA1414, 0xA05050A0, 0xA0A5A5A0, 0x96000000, 0x40804080, 0xA9A8A9A8, 0xAAAAAA44, 0x2A4A5254,
+];
+
+const ANCHORS_2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,
+    15, 2, 8, 2, 2, 8, 8, 15, 2, 8, 2, 2, 8, 8, 2, 2,
+    15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6,
+    6, 2, 6, 8, 15, 15, 2, 2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+const ANCHORS_3_SECOND: [u8; 64] = [
+    3, 3, 15, 15, 8, 3, 15, 15, 8, 8, 6, 6, 6, 5, 3, 3,
+    3, 3, 8, 15, 3, 3, 6, 10, 5, 8, 8, 6, 8, 5, 15, 15,
+    8, 15, 3, 5, 6, 10, 8, 15, 15, 3, 15, 5, 15, 15, 15, 15,
+    3, 15, 5, 5, 5, 8, 5, 10, 5, 10, 8, 13, 15, 12, 3, 3,
+];
+
+const ANCHORS_3_THIRD: [u8; 64] = [
+    15, 8, 8, 3, 15, 15, 3, 8, 15, 15, 15, 15, 15, 15, 15, 8,
+    15, 8, 15, 3, 15, 8, 15, 8, 3, 15, 6, 10, 15, 15, 10, 8,
+    15, 3, 15, 10, 10, 8, 9, 10, 6, 15, 8, 15, 3, 6, 6, 8,
+    15, 3, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 3, 15, 15, 8,
+];
+
+const WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+fn bc7_weight(bits: u32, index: u8) -> u32 {
+    match bits {
+        2 => WEIGHTS_2[index as usize],
+        3 => WEIGHTS_3[index as usize],
+        _ => WEIGHTS_4[index as usize],
+    }
+}
+
+fn bc7_subset(mode: &Bc7Mode, partition: usize, texel: usize) -> usize {
+    match mode.subsets {
+        2 => (PARTITIONS_2[partition] >> texel & 1) as usize,
+        3 => (PARTITIONS_3[partition] >> (2 * texel) & 3) as usize,
+        _ => 0,
+    }
+}
+
+fn bc7_is_anchor(mode: &Bc7Mode, partition: usize, texel: usize) -> bool {
+    texel == 0
+        || (mode.subsets == 2 && texel == ANCHORS_2[partition] as usize)
+        || (mode.subsets == 3 && (texel == ANCHORS_3_SECOND[partition] as usize || texel == ANCHORS_3_THIRD[partition] as usize))
+}
+
+pub(crate) fn decode_bc7(block: &[u8], out: &mut [[u8; 4]; 16]) {
+    let mut bits = Bits(u128::from_le_bytes(block[..16].try_into().unwrap()));
+    let mode_index = block[0].trailing_zeros() as usize;
+    if mode_index >= 8 {
+        // Reserved mode, decodes to transparent black.
+        *out = [[0; 4]; 16];
+        return;
+    }
+    let mode = &BC7_MODES[mode_index];
+    bits.take(mode_index as u32 + 1);
+
+    let partition = bits.take(mode.partition_bits) as usize;
+    let rotation = bits.take(mode.rotation_bits);
+    let index_selection = bits.take(mode.index_selection_bits);
+
+    // [subset * 2 + endpoint][channel]
+    let endpoint_count = mode.subsets * 2;
+    let mut endpoints = [[0u8; 4]; 6];
+    for channel in 0..3 {
+        for endpoint in endpoints.iter_mut().take(endpoint_count) {
+            endpoint[channel] = bits.take(mode.color_bits);
+        }
+    }
+    for endpoint in endpoints.iter_mut().take(endpoint_count) {
+        endpoint[3] = if mode.alpha_bits > 0 { bits.take(mode.alpha_bits) } else { 255 };
+    }
+
+    let mut pbits = [0u8; 6];
+    if mode.endpoint_pbits {
+        for pbit in pbits.iter_mut().take(endpoint_count) {
+            *pbit = bits.take(1);
+        }
+    } else if mode.shared_pbits {
+        for subset in 0..mode.subsets {
+            let pbit = bits.take(1);
+            pbits[subset * 2] = pbit;
+            pbits[subset * 2 + 1] = pbit;
+        }
+    }
+
+    let has_pbits = mode.endpoint_pbits || mode.shared_pbits;
+    for (endpoint, pbit) in endpoints.iter_mut().zip(pbits).take(endpoint_count) {
+        for (channel, component) in endpoint.iter_mut().enumerate() {
+            let channel_bits = if channel < 3 { mode.color_bits } else { mode.alpha_bits };
+            if channel_bits == 0 {
+                continue;
+            }
+            let (value, value_bits) = if has_pbits {
+                ((*component << 1) | pbit, channel_bits + 1)
+            } else {
+                (*component, channel_bits)
+            };
+            // Expand to 8 bits by repeating the high bits in the low ones.
+            let value = (value as u32) << (8 - value_bits);
+            *component = (value | value >> value_bits) as u8;
+        }
+    }
+
+    let mut indices = [0u8; 16];
+    for (texel, index) in indices.iter_mut().enumerate() {
+        let anchor = bc7_is_anchor(mode, partition, texel);
+        *index = bits.take(mode.index_bits - anchor as u32);
+    }
+    let mut indices2 = [0u8; 16];
+    if mode.index2_bits > 0 {
+        for (texel, index) in indices2.iter_mut().enumerate() {
+            *index = bits.take(mode.index2_bits - (texel == 0) as u32);
+        }
+    }
+
+    for (texel, color) in out.iter_mut().enumerate() {
+        let subset = bc7_subset(mode, partition, texel);
+        let e0 = endpoints[subset * 2];
+        let e1 = endpoints[subset * 2 + 1];
+        let (color_weight, alpha_weight) = if mode.index2_bits == 0 {
+            let weight = bc7_weight(mode.index_bits, indices[texel]);
+            (weight, weight)
+        } else if index_selection == 0 {
+            (bc7_weight(mode.index_bits, indices[texel]), bc7_weight(mode.index2_bits, indices2[texel]))
+        } else {
+            (bc7_weight(mode.index2_bits, indices2[texel]), bc7_weight(mode.index_bits, indices[texel]))
+        };
+        for channel in 0..4 {
+            let weight = if channel < 3 { color_weight } else { alpha_weight };
+            color[channel] = (((64 - weight) * e0[channel] as u32 + weight * e1[channel] as u32 + 32) >> 6) as u8;
+        }
+        match rotation {
+            1 => color.swap(0, 3),
+            2 => color.swap(1, 3),
+            3 => color.swap(2, 3),
+            _ => {}
+        }
+    }
+}
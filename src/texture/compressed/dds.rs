@@ -0,0 +1,87 @@
+use anyhow::*;
+use ddsfile::{Caps2, D3DFormat, Dds, DxgiFormat, MiscFlag};
+
+use super::ContainerImage;
+
+/// The wgpu format of a DXGI format, from files with a DX10 header.
+fn dxgi_format(format: DxgiFormat) -> Option<wgpu::TextureFormat> {
+    use wgpu::TextureFormat::*;
+    Some(match format {
+        DxgiFormat::R8_UNorm => R8Unorm,
+        DxgiFormat::R8_SNorm => R8Snorm,
+        DxgiFormat::R8G8_UNorm => Rg8Unorm,
+        DxgiFormat::R8G8_SNorm => Rg8Snorm,
+        DxgiFormat::R8G8B8A8_UNorm => Rgba8Unorm,
+        DxgiFormat::R8G8B8A8_UNorm_sRGB => Rgba8UnormSrgb,
+        DxgiFormat::R8G8B8A8_SNorm => Rgba8Snorm,
+        DxgiFormat::B8G8R8A8_UNorm => Bgra8Unorm,
+        DxgiFormat::B8G8R8A8_UNorm_sRGB => Bgra8UnormSrgb,
+        DxgiFormat::R16G16B16A16_Float => Rgba16Float,
+        DxgiFormat::R32G32B32A32_Float => Rgba32Float,
+        DxgiFormat::BC1_UNorm => Bc1RgbaUnorm,
+        DxgiFormat::BC1_UNorm_sRGB => Bc1RgbaUnormSrgb,
+        DxgiFormat::BC2_UNorm => Bc2RgbaUnorm,
+        DxgiFormat::BC2_UNorm_sRGB => Bc2RgbaUnormSrgb,
+        DxgiFormat::BC3_UNorm => Bc3RgbaUnorm,
+        DxgiFormat::BC3_UNorm_sRGB => Bc3RgbaUnormSrgb,
+        DxgiFormat::BC4_UNorm => Bc4RUnorm,
+        DxgiFormat::BC4_SNorm => Bc4RSnorm,
+        DxgiFormat::BC5_UNorm => Bc5RgUnorm,
+        DxgiFormat::BC5_SNorm => Bc5RgSnorm,
+        DxgiFormat::BC6H_UF16 => Bc6hRgbUfloat,
+        DxgiFormat::BC6H_SF16 => Bc6hRgbSfloat,
+        DxgiFormat::BC7_UNorm => Bc7RgbaUnorm,
+        DxgiFormat::BC7_UNorm_sRGB => Bc7RgbaUnormSrgb,
+        _ => return None,
+    })
+}
+
+/// The wgpu format of a legacy D3D format, from files without a DX10 header.
+fn d3d_format(format: D3DFormat) -> Option<wgpu::TextureFormat> {
+    use wgpu::TextureFormat::*;
+    Some(match format {
+        D3DFormat::A8B8G8R8 => Rgba8Unorm,
+        D3DFormat::A8R8G8B8 => Bgra8Unorm,
+        D3DFormat::DXT1 => Bc1RgbaUnorm,
+        D3DFormat::DXT2 | D3DFormat::DXT3 => Bc2RgbaUnorm,
+        D3DFormat::DXT4 | D3DFormat::DXT5 => Bc3RgbaUnorm,
+        _ => return None,
+    })
+}
+
+impl ContainerImage {
+
+    /// Read a DDS file, with or without a DX10 header.
+    pub fn from_dds(bytes: &[u8]) -> Result<Self> {
+        let dds = Dds::read(bytes).context("Invalid DDS file")?;
+
+        let format = match (dds.get_dxgi_format(), dds.get_d3d_format()) {
+            (Some(format), _) => dxgi_format(format).with_context(|| format!("The DDS format {:?} isn't supported", format))?,
+            (None, Some(format)) => d3d_format(format).with_context(|| format!("The DDS format {:?} isn't supported", format))?,
+            (None, None) => bail!("The DDS file's pixel format isn't supported"),
+        };
+
+        let (layers, cube) = match &dds.header10 {
+            // DX10 cube maps count whole cubes.
+            Some(header10) if header10.misc_flag.contains(MiscFlag::TEXTURECUBE) => (header10.array_size.max(1) * 6, true),
+            Some(header10) => (header10.array_size.max(1), false),
+            None if dds.header.caps2.contains(Caps2::CUBEMAP) => (6, true),
+            None => (1, false),
+        };
+        let depth = if dds.header.caps2.contains(Caps2::VOLUME) { dds.get_depth().max(1) } else { 1 };
+
+        // DDS already stores every level of a layer together.
+        let image = ContainerImage {
+            format,
+            width: dds.get_width(),
+            height: dds.get_height().max(1),
+            depth,
+            layers,
+            cube,
+            mip_levels: dds.get_num_mipmap_levels().max(1),
+            data: dds.data,
+        };
+        image.validate()?;
+        Ok(image)
+    }
+}
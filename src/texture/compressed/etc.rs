@@ -0,0 +1,230 @@
+//! Decoders for the ETC2 and EAC block formats.
+
+const MODIFIERS: [[i32; 2]; 8] = [[2, 8], [5, 17], [9, 29], [13, 42], [18, 60], [24, 80], [33, 106], [47, 183]];
+const DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+fn bits(block: u64, high: u32, count: u32) -> i32 {
+    ((block >> (high + 1 - count)) & ((1 << count) - 1)) as i32
+}
+
+fn extend4(value: i32) -> i32 {
+    (value << 4) | value
+}
+
+fn extend5(value: i32) -> i32 {
+    (value << 3) | (value >> 2)
+}
+
+fn clamp(value: i32) -> u8 {
+    value.clamp(0, 255) as u8
+}
+
+fn offset(color: [i32; 3], by: i32) -> [u8; 4] {
+    [clamp(color[0] + by), clamp(color[1] + by), clamp(color[2] + by), 255]
+}
+
+/// Texels are indexed column by column in ETC blocks, and row by row in the decoded output.
+fn texel(etc_index: usize) -> usize {
+    (etc_index % 4) * 4 + etc_index / 4
+}
+
+/// The 2-bit index of a texel, given in ETC order.
+fn pixel_index(block: u64, etc_index: usize) -> usize {
+    let msb = (block >> (16 + etc_index)) & 1;
+    let lsb = (block >> etc_index) & 1;
+    (msb * 2 + lsb) as usize
+}
+
+/// Decode an ETC2 RGB block. With `punchthrough` the differential bit is the opaque bit of RGB8A1.
+fn decode_rgb(block: &[u8], punchthrough: bool, out: &mut [[u8; 4]; 16]) {
+    let block = u64::from_be_bytes(block[..8].try_into().unwrap());
+    let differential = punchthrough || bits(block, 33, 1) == 1;
+    let opaque = !punchthrough || bits(block, 33, 1) == 1;
+    let transparent = |index: usize| !opaque && index == 2;
+
+    if differential {
+        let r = bits(block, 63, 5);
+        let g = bits(block, 55, 5);
+        let b = bits(block, 47, 5);
+        let signed3 = |value: i32| if value >= 4 { value - 8 } else { value };
+        let r2 = r + signed3(bits(block, 58, 3));
+        let g2 = g + signed3(bits(block, 50, 3));
+        let b2 = b + signed3(bits(block, 42, 3));
+
+        if !(0..32).contains(&r2) {
+            // T mode
+            let c1 = [
+                extend4((bits(block, 60, 2) << 2) | bits(block, 57, 2)),
+                extend4(bits(block, 55, 4)),
+                extend4(bits(block, 51, 4)),
+            ];
+            let c2 = [extend4(bits(block, 47, 4)), extend4(bits(block, 43, 4)), extend4(bits(block, 39, 4))];
+            let d = DISTANCES[((bits(block, 35, 2) << 1) | bits(block, 32, 1)) as usize];
+            let paint = [offset(c1, 0), offset(c2, d), offset(c2, 0), offset(c2, -d)];
+            return paint_block(block, paint, transparent, out);
+        }
+        if !(0..32).contains(&g2) {
+            // H mode
+            let c1 = [
+                bits(block, 62, 4),
+                (bits(block, 58, 3) << 1) | bits(block, 52, 1),
+                (bits(block, 51, 1) << 3) | bits(block, 49, 3),
+            ];
+            let c2 = [bits(block, 46, 4), bits(block, 42, 4), bits(block, 38, 4)];
+            let order = ((c1[0] << 8) | (c1[1] << 4) | c1[2]) >= ((c2[0] << 8) | (c2[1] << 4) | c2[2]);
+            let d = DISTANCES[((bits(block, 34, 1) << 2) | (bits(block, 32, 1) << 1) | order as i32) as usize];
+            let c1 = c1.map(extend4);
+            let c2 = c2.map(extend4);
+            let paint = [offset(c1, d), offset(c1, -d), offset(c2, d), offset(c2, -d)];
+            return paint_block(block, paint, transparent, out);
+        }
+        if !(0..32).contains(&b2) {
+            return decode_planar(block, out);
+        }
+
+        let base = [[extend5(r), extend5(g), extend5(b)], [extend5(r2), extend5(g2), extend5(b2)]];
+        decode_subblocks(block, base, opaque, out);
+    } else {
+        let base = [
+            [extend4(bits(block, 63, 4)), extend4(bits(block, 55, 4)), extend4(bits(block, 47, 4))],
+            [extend4(bits(block, 59, 4)), extend4(bits(block, 51, 4)), extend4(bits(block, 43, 4))],
+        ];
+        decode_subblocks(block, base, true, out);
+    }
+}
+
+/// The individual and differential modes, two sub-blocks with a base color and modifier table each.
+fn decode_subblocks(block: u64, base: [[i32; 3]; 2], opaque: bool, out: &mut [[u8; 4]; 16]) {
+    let tables = [bits(block, 39, 3) as usize, bits(block, 36, 3) as usize];
+    let flip = bits(block, 32, 1) == 1;
+    for etc_index in 0..16 {
+        let (x, y) = (etc_index / 4, etc_index % 4);
+        let subblock = if flip { (y >= 2) as usize } else { (x >= 2) as usize };
+        let index = pixel_index(block, etc_index);
+        let [small, large] = MODIFIERS[tables[subblock]];
+        out[texel(etc_index)] = match index {
+            0 if !opaque => offset(base[subblock], 0),
+            0 => offset(base[subblock], small),
+            1 => offset(base[subblock], large),
+            2 if !opaque => [0, 0, 0, 0],
+            2 => offset(base[subblock], -small),
+            _ => offset(base[subblock], -large),
+        };
+    }
+}
+
+fn paint_block(block: u64, paint: [[u8; 4]; 4], transparent: impl Fn(usize) -> bool, out: &mut [[u8; 4]; 16]) {
+    for etc_index in 0..16 {
+        let index = pixel_index(block, etc_index);
+        out[texel(etc_index)] = if transparent(index) { [0, 0, 0, 0] } else { paint[index] };
+    }
+}
+
+fn decode_planar(block: u64, out: &mut [[u8; 4]; 16]) {
+    let extend6 = |value: i32| (value << 2) | (value >> 4);
+    let extend7 = |value: i32| (value << 1) | (value >> 6);
+    let origin = [
+        extend6(bits(block, 62, 6)),
+        extend7((bits(block, 56, 1) << 6) | bits(block, 54, 6)),
+        extend6((bits(block, 48, 1) << 5) | (bits(block, 44, 2) << 3) | bits(block, 41, 3)),
+    ];
+    let horizontal = [
+        extend6((bits(block, 38, 5) << 1) | bits(block, 32, 1)),
+        extend7(bits(block, 31, 7)),
+        extend6(bits(block, 24, 6)),
+    ];
+    let vertical = [extend6(bits(block, 18, 6)), extend7(bits(block, 12, 7)), extend6(bits(block, 5, 6))];
+    for y in 0..4 {
+        for x in 0..4 {
+            let mut color = [0, 0, 0, 255];
+            for c in 0..3 {
+                color[c] = clamp((x * (horizontal[c] - origin[c]) + y * (vertical[c] - origin[c]) + 4 * origin[c] + 2) >> 2);
+            }
+            out[(y * 4 + x) as usize] = color;
+        }
+    }
+}
+
+/// Decode an EAC channel block to 11-bit values, signed ones in -1023..=1023.
+fn decode_eac11(block: &[u8], signed: bool) -> [i32; 16] {
+    let block = u64::from_be_bytes(block[..8].try_into().unwrap());
+    let base = if signed { (bits(block, 63, 8) as u8 as i8).max(-127) as i32 } else { bits(block, 63, 8) };
+    let multiplier = bits(block, 55, 4);
+    let modifiers = EAC_MODIFIERS[bits(block, 51, 4) as usize];
+    let mut values = [0; 16];
+    for etc_index in 0..16 {
+        let modifier = modifiers[bits(block, 47 - 3 * etc_index as u32, 3) as usize];
+        let scaled = if multiplier == 0 { modifier } else { modifier * multiplier * 8 };
+        values[texel(etc_index)] = if signed {
+            (base * 8 + scaled).clamp(-1023, 1023)
+        } else {
+            (base * 8 + 4 + scaled).clamp(0, 2047)
+        };
+    }
+    values
+}
+
+/// Round an 11-bit EAC value to the bits of an 8-bit unorm or snorm channel.
+fn eac11_to_8(value: i32, signed: bool) -> u8 {
+    if signed {
+        ((value * 127 + value.signum() * 511) / 1023) as i8 as u8
+    } else {
+        ((value * 255 + 1023) / 2047) as u8
+    }
+}
+
+fn decode_eac_channel(block: &[u8], signed: bool, channel: usize, out: &mut [[u8; 4]; 16]) {
+    for (texel, value) in out.iter_mut().zip(decode_eac11(block, signed)) {
+        texel[channel] = eac11_to_8(value, signed);
+    }
+}
+
+pub(crate) fn decode_etc2_rgb8(block: &[u8], out: &mut [[u8; 4]; 16]) {
+    decode_rgb(block, false, out);
+}
+
+pub(crate) fn decode_etc2_rgb8a1(block: &[u8], out: &mut [[u8; 4]; 16]) {
+    decode_rgb(block, true, out);
+}
+
+pub(crate) fn decode_etc2_rgba8(block: &[u8], out: &mut [[u8; 4]; 16]) {
+    decode_rgb(&block[8..], false, out);
+    let alpha = u64::from_be_bytes(block[..8].try_into().unwrap());
+    let base = bits(alpha, 63, 8);
+    let multiplier = bits(alpha, 55, 4);
+    let modifiers = EAC_MODIFIERS[bits(alpha, 51, 4) as usize];
+    for etc_index in 0..16 {
+        let modifier = modifiers[bits(alpha, 47 - 3 * etc_index as u32, 3) as usize];
+        out[texel(etc_index)][3] = clamp(base + modifier * multiplier);
+    }
+}
+
+pub(crate) fn decode_eac_r11(block: &[u8], signed: bool, out: &mut [[u8; 4]; 16]) {
+    *out = [[0, 0, 0, 255]; 16];
+    decode_eac_channel(block, signed, 0, out);
+}
+
+pub(crate) fn decode_eac_rg11(block: &[u8], signed: bool, out: &mut [[u8; 4]; 16]) {
+    *out = [[0, 0, 0, 255]; 16];
+    decode_eac_channel(&block[..8], signed, 0, out);
+    decode_eac_channel(&block[8..], signed, 1, out);
+}
@@ -0,0 +1,102 @@
+use anyhow::*;
+use ktx2::Format;
+
+use super::ContainerImage;
+
+/// The wgpu format of a KTX2 (Vulkan) format.
+fn texture_format(format: Format) -> Option<wgpu::TextureFormat> {
+    use wgpu::TextureFormat::*;
+    Some(match format {
+        Format::R8_UNORM => R8Unorm,
+        Format::R8_SNORM => R8Snorm,
+        Format::R8G8_UNORM => Rg8Unorm,
+        Format::R8G8_SNORM => Rg8Snorm,
+        Format::R8G8B8A8_UNORM => Rgba8Unorm,
+        Format::R8G8B8A8_SRGB => Rgba8UnormSrgb,
+        Format::R8G8B8A8_SNORM => Rgba8Snorm,
+        Format::B8G8R8A8_UNORM => Bgra8Unorm,
+        Format::B8G8R8A8_SRGB => Bgra8UnormSrgb,
+        Format::R16G16B16A16_SFLOAT => Rgba16Float,
+        Format::R32G32B32A32_SFLOAT => Rgba32Float,
+        Format::BC1_RGBA_UNORM_BLOCK | Format::BC1_RGB_UNORM_BLOCK => Bc1RgbaUnorm,
+        Format::BC1_RGBA_SRGB_BLOCK | Format::BC1_RGB_SRGB_BLOCK => Bc1RgbaUnormSrgb,
+        Format::BC2_UNORM_BLOCK => Bc2RgbaUnorm,
+        Format::BC2_SRGB_BLOCK => Bc2RgbaUnormSrgb,
+        Format::BC3_UNORM_BLOCK => Bc3RgbaUnorm,
+        Format::BC3_SRGB_BLOCK => Bc3RgbaUnormSrgb,
+        Format::BC4_UNORM_BLOCK => Bc4RUnorm,
+        Format::BC4_SNORM_BLOCK => Bc4RSnorm,
+        Format::BC5_UNORM_BLOCK => Bc5RgUnorm,
+        Format::BC5_SNORM_BLOCK => Bc5RgSnorm,
+        Format::BC6H_UFLOAT_BLOCK => Bc6hRgbUfloat,
+        Format::BC6H_SFLOAT_BLOCK => Bc6hRgbSfloat,
+        Format::BC7_UNORM_BLOCK => Bc7RgbaUnorm,
+        Format::BC7_SRGB_BLOCK => Bc7RgbaUnormSrgb,
+        Format::ETC2_R8G8B8_UNORM_BLOCK => Etc2Rgb8Unorm,
+        Format::ETC2_R8G8B8_SRGB_BLOCK => Etc2Rgb8UnormSrgb,
+        Format::ETC2_R8G8B8A1_UNORM_BLOCK => Etc2Rgb8A1Unorm,
+        Format::ETC2_R8G8B8A1_SRGB_BLOCK => Etc2Rgb8A1UnormSrgb,
+        Format::ETC2_R8G8B8A8_UNORM_BLOCK => Etc2Rgba8Unorm,
+        Format::ETC2_R8G8B8A8_SRGB_BLOCK => Etc2Rgba8UnormSrgb,
+        Format::EAC_R11_UNORM_BLOCK => EacR11Unorm,
+        Format::EAC_R11_SNORM_BLOCK => EacR11Snorm,
+        Format::EAC_R11G11_UNORM_BLOCK => EacRg11Unorm,
+        Format::EAC_R11G11_SNORM_BLOCK => EacRg11Snorm,
+        format if (Format::ASTC_4x4_UNORM_BLOCK..=Format::ASTC_12x12_SRGB_BLOCK).contains(&format) => {
+            use wgpu::AstcBlock::*;
+            // The ASTC formats come in unorm/srgb pairs, in the same block order as wgpu's.
+            let index = format.0.get() - Format::ASTC_4x4_UNORM_BLOCK.0.get();
+            let block = [B4x4, B5x4, B5x5, B6x5, B6x6, B8x5, B8x6, B8x8, B10x5, B10x6, B10x8, B10x10, B12x10, B12x12][index as usize / 2];
+            let channel = if index.is_multiple_of(2) { wgpu::AstcChannel::Unorm } else { wgpu::AstcChannel::UnormSrgb };
+            Astc { block, channel }
+        }
+        _ => return None,
+    })
+}
+
+impl ContainerImage {
+
+    /// Read a KTX2 file. Supercompressed (Basis Universal, Zstandard) files aren't supported.
+    pub fn from_ktx2(bytes: &[u8]) -> Result<Self> {
+        let reader = ktx2::Reader::new(bytes).map_err(|e| anyhow!("Invalid KTX2 file: {}", e))?;
+        let header = reader.header();
+        if let Some(scheme) = header.supercompression_scheme {
+            bail!("Supercompressed KTX2 files ({:?}) aren't supported", scheme);
+        }
+        let format = match header.format {
+            Some(format) => texture_format(format).with_context(|| format!("The KTX2 format {:?} isn't supported", format))?,
+            None => bail!("KTX2 files without a format (Basis Universal) aren't supported"),
+        };
+
+        let cube = header.face_count == 6;
+        let layers = header.layer_count.max(1) * header.face_count;
+        let depth = header.pixel_depth.max(1);
+        if depth > 1 && layers > 1 {
+            bail!("KTX2 arrays of volume textures aren't supported");
+        }
+
+        let mut image = ContainerImage {
+            format,
+            width: header.pixel_width,
+            height: header.pixel_height.max(1),
+            depth,
+            layers,
+            cube,
+            mip_levels: header.level_count.max(1),
+            data: Vec::new(),
+        };
+
+        // KTX2 stores every layer of a level together, reorder them to every level of a layer.
+        let levels: Vec<&[u8]> = reader.levels().collect();
+        for layer in 0..layers as usize {
+            for (level, level_data) in levels.iter().enumerate() {
+                let (_, size) = image.level_size(level as u32);
+                let layer_data = level_data.get(layer * size..(layer + 1) * size)
+                    .with_context(|| format!("KTX2 mip level {} is {} bytes, too short for {} layers", level, level_data.len(), layers))?;
+                image.data.extend_from_slice(layer_data);
+            }
+        }
+
+        Ok(image)
+    }
+}
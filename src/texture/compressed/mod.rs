@@ -0,0 +1,228 @@
+mod astc;
+mod bc;
+mod dds;
+mod etc;
+mod ktx;
+
+use std::borrow::Cow;
+
+use anyhow::*;
+
+use crate::State;
+
+/// The magic bytes at the start of a KTX2 file.
+pub(crate) const KTX2_MAGIC: [u8; 12] = [0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+/// The magic bytes at the start of a DDS file.
+pub(crate) const DDS_MAGIC: [u8; 4] = *b"DDS ";
+
+/// Texel data read from a KTX2 or DDS file, with the mip chains stored in the file.\
+/// Upload it with `TextureBuilder::from_container`.
+#[derive(Clone, Debug)]
+pub struct ContainerImage {
+    pub format: wgpu::TextureFormat,
+    pub width: u32,
+    pub height: u32,
+    /// The depth of a volume texture, 1 otherwise.
+    pub depth: u32,
+    /// The number of array layers times the number of faces.
+    pub layers: u32,
+    /// Whether every 6 layers are the faces of a cube map.
+    pub cube: bool,
+    pub mip_levels: u32,
+    /// Every mip level of the first layer from largest to smallest, then those of the next layer.\
+    /// Blocks of compressed formats are tightly packed, so are the rows of uncompressed ones.
+    pub data: Vec<u8>,
+}
+
+impl ContainerImage {
+
+    /// The size of a mip level in texels, and in bytes for one layer.
+    pub fn level_size(&self, level: u32) -> (wgpu::Extent3d, usize) {
+        level_size(self.format, self.width, self.height, self.depth, level)
+    }
+
+    /// The bytes of one layer and mip level.
+    pub fn level_data(&self, layer: u32, level: u32) -> &[u8] {
+        let layer_size: usize = (0..self.mip_levels).map(|level| self.level_size(level).1).sum();
+        let offset: usize = layer as usize * layer_size + (0..level).map(|level| self.level_size(level).1).sum::<usize>();
+        &self.data[offset..offset + self.level_size(level).1]
+    }
+
+    /// Check that the data holds every layer and level.
+    pub(crate) fn validate(&self) -> anyhow::Result<()> {
+        let layer_size: usize = (0..self.mip_levels).map(|level| self.level_size(level).1).sum();
+        let expected = layer_size * self.layers as usize;
+        if self.data.len() < expected {
+            anyhow::bail!("The {:?} texture data is {} bytes, {} are needed for {} layers and {} mip levels", self.format, self.data.len(), expected, self.layers, self.mip_levels);
+        }
+        Ok(())
+    }
+
+    /// Decompress the texels on the CPU, for devices without the compressed format's feature.\
+    /// BC1 to BC5, BC7, ETC2, EAC and ASTC LDR are supported, `None` is returned for other formats.\
+    /// Color formats decode to `Rgba8Unorm` or `Rgba8UnormSrgb`, one and two channel formats to `R8` or `Rg8`.
+    pub fn decompress(&self) -> Option<ContainerImage> {
+        let (decoded_format, channels) = decoded_format(self.format)?;
+        let (block_width, block_height) = self.format.describe().block_dimensions;
+        let (block_width, block_height) = (block_width as u32, block_height as u32);
+        let block_size = self.format.describe().block_size as usize;
+
+        let mut data = Vec::new();
+        let mut texels = [[0u8; 4]; astc::MAX_TEXELS];
+        for layer in 0..self.layers {
+            for level in 0..self.mip_levels {
+                let (size, _) = self.level_size(level);
+                let blocks_wide = size.width.div_ceil(block_width);
+                let blocks_high = size.height.div_ceil(block_height);
+                let slice_size = (blocks_wide * blocks_high) as usize * block_size;
+                for slice in self.level_data(layer, level).chunks_exact(slice_size) {
+                    let start = data.len();
+                    data.resize(start + (size.width * size.height * channels) as usize, 0);
+                    let decoded = &mut data[start..];
+                    for (block_index, block) in slice.chunks_exact(block_size).enumerate() {
+                        decode_block(self.format, block, &mut texels);
+                        let block_x = block_index as u32 % blocks_wide * block_width;
+                        let block_y = block_index as u32 / blocks_wide * block_height;
+                        for (i, texel) in texels[..(block_width * block_height) as usize].iter().enumerate() {
+                            let x = block_x + i as u32 % block_width;
+                            let y = block_y + i as u32 / block_width;
+                            // Blocks at the edge of small mip levels reach past the image.
+                            if x < size.width && y < size.height {
+                                let offset = ((y * size.width + x) * channels) as usize;
+                                decoded[offset..offset + channels as usize].copy_from_slice(&texel[..channels as usize]);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Some(ContainerImage {
+            format: decoded_format,
+            data,
+            ..*self
+        })
+    }
+}
+
+/// The size of a mip level in texels, and in bytes for one layer, with partial blocks rounded up.
+fn level_size(format: wgpu::TextureFormat, width: u32, height: u32, depth: u32, level: u32) -> (wgpu::Extent3d, usize) {
+    let info = format.describe();
+    let (block_width, block_height) = (info.block_dimensions.0 as u32, info.block_dimensions.1 as u32);
+    let size = wgpu::Extent3d {
+        width: (width >> level).max(1),
+        height: (height >> level).max(1),
+        depth_or_array_layers: (depth >> level).max(1),
+    };
+    let blocks_wide = size.width.div_ceil(block_width);
+    let blocks_high = size.height.div_ceil(block_height);
+    let bytes = (blocks_wide * blocks_high * size.depth_or_array_layers) as usize * info.block_size as usize;
+    (size, bytes)
+}
+
+/// The format and channel count a compressed format is decoded to.
+fn decoded_format(format: wgpu::TextureFormat) -> Option<(wgpu::TextureFormat, u32)> {
+    use wgpu::TextureFormat::*;
+    Some(match format {
+        Bc1RgbaUnorm | Bc2RgbaUnorm | Bc3RgbaUnorm | Bc7RgbaUnorm | Etc2Rgb8Unorm | Etc2Rgb8A1Unorm | Etc2Rgba8Unorm => (Rgba8Unorm, 4),
+        Bc1RgbaUnormSrgb | Bc2RgbaUnormSrgb | Bc3RgbaUnormSrgb | Bc7RgbaUnormSrgb | Etc2Rgb8UnormSrgb | Etc2Rgb8A1UnormSrgb | Etc2Rgba8UnormSrgb => (Rgba8UnormSrgb, 4),
+        Bc4RUnorm | EacR11Unorm => (R8Unorm, 1),
+        Bc4RSnorm | EacR11Snorm => (R8Snorm, 1),
+        Bc5RgUnorm | EacRg11Unorm => (Rg8Unorm, 2),
+        Bc5RgSnorm | EacRg11Snorm => (Rg8Snorm, 2),
+        Astc { channel: wgpu::AstcChannel::Unorm, .. } => (Rgba8Unorm, 4),
+        Astc { channel: wgpu::AstcChannel::UnormSrgb, .. } => (Rgba8UnormSrgb, 4),
+        _ => return None,
+    })
+}
+
+/// Decode one block of a format supported by `decoded_format`, row by row into the start of `texels`.
+fn decode_block(format: wgpu::TextureFormat, block: &[u8], texels: &mut [[u8; 4]; astc::MAX_TEXELS]) {
+    use wgpu::TextureFormat::*;
+    if let Astc { channel, .. } = format {
+        let (width, height) = format.describe().block_dimensions;
+        return astc::decode_astc(block, width as usize, height as usize, channel == wgpu::AstcChannel::UnormSrgb, texels);
+    }
+    let texels: &mut [[u8; 4]; 16] = (&mut texels[..16]).try_into().unwrap();
+    match format {
+        Bc1RgbaUnorm | Bc1RgbaUnormSrgb => bc::decode_bc1(block, texels),
+        Bc2RgbaUnorm | Bc2RgbaUnormSrgb => bc::decode_bc2(block, texels),
+        Bc3RgbaUnorm | Bc3RgbaUnormSrgb => bc::decode_bc3(block, texels),
+        Bc4RUnorm => bc::decode_bc4(block, false, texels),
+        Bc4RSnorm => bc::decode_bc4(block, true, texels),
+        Bc5RgUnorm => bc::decode_bc5(block, false, texels),
+        Bc5RgSnorm => bc::decode_bc5(block, true, texels),
+        Bc7RgbaUnorm | Bc7RgbaUnormSrgb => bc::decode_bc7(block, texels),
+        Etc2Rgb8Unorm | Etc2Rgb8UnormSrgb => etc::decode_etc2_rgb8(block, texels),
+        Etc2Rgb8A1Unorm | Etc2Rgb8A1UnormSrgb => etc::decode_etc2_rgb8a1(block, texels),
+        Etc2Rgba8Unorm | Etc2Rgba8UnormSrgb => etc::decode_etc2_rgba8(block, texels),
+        EacR11Unorm => etc::decode_eac_r11(block, false, texels),
+        EacR11Snorm => etc::decode_eac_r11(block, true, texels),
+        EacRg11Unorm => etc::decode_eac_rg11(block, false, texels),
+        EacRg11Snorm => etc::decode_eac_rg11(block, true, texels),
+        _ => unreachable!("{:?} has no block decoder", format),
+    }
+}
+
+/// The image itself if the device supports its format, otherwise a copy decompressed on the CPU.\
+/// Errors name the missing feature if there is no CPU decoder for the format.
+pub(crate) fn supported_image<'a>(state: &State, image: &'a ContainerImage) -> Result<Cow<'a, ContainerImage>> {
+    let info = image.format.describe();
+    let (block_width, block_height) = (info.block_dimensions.0 as u32, info.block_dimensions.1 as u32);
+    if !image.width.is_multiple_of(block_width) || !image.height.is_multiple_of(block_height) {
+        bail!("A {}x{} texture isn't a whole number of {:?} blocks of {}x{}", image.width, image.height, image.format, block_width, block_height);
+    }
+    image.validate()?;
+
+    let missing = info.required_features - state.device.features();
+    if missing.is_empty() {
+        return Ok(Cow::Borrowed(image));
+    }
+    if let Some(decompressed) = image.decompress() {
+        return Ok(Cow::Owned(decompressed));
+    }
+    let hint = if state.adapter.features().contains(missing) {
+        "the adapter supports it, request it with `StateBuilder::optional_features`"
+    } else {
+        "the adapter doesn't support it either"
+    };
+    bail!("{:?} textures need {:?}, which the device doesn't have and which can't be decompressed on the CPU ({})", image.format, missing, hint)
+}
+
+/// Write every layer and mip level of a container image to a texture of the same size and format.
+pub(crate) fn write_container(queue: &wgpu::Queue, texture: &wgpu::Texture, image: &ContainerImage) {
+    let info = image.format.describe();
+    let (block_width, block_height) = (info.block_dimensions.0 as u32, info.block_dimensions.1 as u32);
+    let volume = image.depth > 1;
+    for layer in 0..image.layers {
+        for level in 0..image.mip_levels {
+            let (size, _) = image.level_size(level);
+            let blocks_wide = size.width.div_ceil(block_width);
+            let blocks_high = size.height.div_ceil(block_height);
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture,
+                    mip_level: level,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: if volume { 0 } else { layer },
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                image.level_data(layer, level),
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(blocks_wide * info.block_size as u32),
+                    rows_per_image: std::num::NonZeroU32::new(blocks_high),
+                },
+                // Copies cover whole blocks, even where small mip levels end inside one.
+                wgpu::Extent3d {
+                    width: blocks_wide * block_width,
+                    height: blocks_high * block_height,
+                    depth_or_array_layers: if volume { size.depth_or_array_layers } else { 1 },
+                },
+            );
+        }
+    }
+}
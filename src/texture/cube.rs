@@ -0,0 +1,84 @@
+use std::borrow::Cow;
+
+use wgpu::util::DeviceExt;
+
+use super::Texture;
+
+/// Render the first mip level of all six faces of `cube` from an equirectangular panorama.
+pub(crate) fn render_equirectangular(device: &wgpu::Device, queue: &wgpu::Queue, source: &Texture, cube: &wgpu::Texture) {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("wgpu_quick equirectangular to cube"),
+        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("equirect.wgsl"))),
+    });
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("wgpu_quick equirectangular to cube"),
+        layout: None,
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(cube.format().into())],
+        }),
+        multiview: None,
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("wgpu_quick equirectangular to cube") });
+    let bind_layout = pipeline.get_bind_group_layout(0);
+    for face in 0..6u32 {
+        // Uniform buffers are padded to 16 bytes.
+        let face_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&[face, 0, 0, 0]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bind_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&source.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&source.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: face_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let face_view = cube.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            base_mip_level: 0,
+            mip_level_count: std::num::NonZeroU32::new(1),
+            base_array_layer: face,
+            array_layer_count: std::num::NonZeroU32::new(1),
+            ..Default::default()
+        });
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &face_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        rpass.set_pipeline(&pipeline);
+        rpass.set_bind_group(0, &bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+    queue.submit(Some(encoder.finish()));
+}
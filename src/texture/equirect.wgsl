@@ -0,0 +1,52 @@
+// Draws one fullscreen triangle per cube face, sampling the panorama in the face's directions.
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+struct Face {
+    index: u32,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+@group(0) @binding(0)
+var src_view: texture_2d<f32>;
+
+@group(0) @binding(1)
+var src_sampler: sampler;
+
+@group(0) @binding(2)
+var<uniform> face: Face;
+
+// The direction through a texel of a cube face, faces are ordered +X, -X, +Y, -Y, +Z, -Z.
+fn face_direction(face: u32, uv: vec2<f32>) -> vec3<f32> {
+    let s = uv.x * 2.0 - 1.0;
+    let t = uv.y * 2.0 - 1.0;
+    switch face {
+        case 0u: { return vec3<f32>(1.0, -t, -s); }
+        case 1u: { return vec3<f32>(-1.0, -t, s); }
+        case 2u: { return vec3<f32>(s, 1.0, t); }
+        case 3u: { return vec3<f32>(s, -1.0, -t); }
+        case 4u: { return vec3<f32>(s, -t, 1.0); }
+        default: { return vec3<f32>(-s, -t, -1.0); }
+    }
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let dir = normalize(face_direction(face.index, in.uv));
+    let pi = 3.14159265358979;
+    let longitude = atan2(dir.z, dir.x);
+    let latitude = asin(clamp(dir.y, -1.0, 1.0));
+    let uv = vec2<f32>(longitude / (2.0 * pi) + 0.5, 0.5 - latitude / pi);
+    return textureSampleLevel(src_view, src_sampler, uv, 0.0);
+}
@@ -0,0 +1,164 @@
+use std::borrow::Cow;
+
+use image::DynamicImage;
+use anyhow::*;
+
+use crate::State;
+use super::{format_features, image_data, write_level, Texture};
+
+/// The number of mip levels in a full chain down to 1x1.
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+pub(crate) fn can_render(state: &State, format: wgpu::TextureFormat) -> bool {
+    format_features(state, format).allowed_usages.contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+}
+
+/// Whether mip levels of `format` can be generated by rendering, which needs a renderable and filterable format.
+pub(crate) fn can_blit(state: &State, format: wgpu::TextureFormat) -> bool {
+    can_render(state, format) && format_features(state, format).flags.contains(wgpu::TextureFormatFeatureFlags::FILTERABLE)
+}
+
+/// Fill every mip level after the first by rendering a linearly filtered copy of the level above it.\
+/// Each layer of an array or cube map gets its own chain. Volumes can't be rendered to.\
+/// Levels are rendered between single level scratch textures and copied over, since the GL backend
+/// can't sample a view that starts past the first level or layer. The texture needs `COPY_SRC` usage.
+pub(crate) fn blit_mipmaps(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) {
+    let format = texture.format();
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("wgpu_quick mipmap blit"),
+        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("blit.wgsl"))),
+    });
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("wgpu_quick mipmap blit"),
+        layout: None,
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(format.into())],
+        }),
+        multiview: None,
+    });
+    let bind_layout = pipeline.get_bind_group_layout(0);
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("wgpu_quick mipmap blit"),
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    });
+
+    let level_size = |level: u32| wgpu::Extent3d {
+        width: (texture.width() >> level).max(1),
+        height: (texture.height() >> level).max(1),
+        depth_or_array_layers: 1,
+    };
+    // One scratch texture per level, shared by every layer.
+    let scratch: Vec<wgpu::Texture> = (0..texture.mip_level_count()).map(|level| device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("wgpu_quick mipmap scratch"),
+        size: level_size(level),
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    })).collect();
+    let views: Vec<wgpu::TextureView> = scratch.iter().map(|texture| texture.create_view(&wgpu::TextureViewDescriptor::default())).collect();
+    let bind_groups: Vec<wgpu::BindGroup> = views.iter().map(|view| device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &bind_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&sampler),
+            },
+        ],
+    })).collect();
+    let copy = |texture, mip_level, layer| wgpu::ImageCopyTexture {
+        texture,
+        mip_level,
+        origin: wgpu::Origin3d { x: 0, y: 0, z: layer },
+        aspect: wgpu::TextureAspect::All,
+    };
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("wgpu_quick mipmap blit") });
+    for layer in 0..texture.depth_or_array_layers() {
+        encoder.copy_texture_to_texture(copy(texture, 0, layer), copy(&scratch[0], 0, 0), level_size(0));
+        for level in 1..texture.mip_level_count() as usize {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &views[level],
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(&pipeline);
+            rpass.set_bind_group(0, &bind_groups[level - 1], &[]);
+            rpass.draw(0..3, 0..1);
+            drop(rpass);
+            encoder.copy_texture_to_texture(copy(&scratch[level], 0, 0), copy(texture, level as u32, layer), level_size(level as u32));
+        }
+    }
+    queue.submit(Some(encoder.finish()));
+}
+
+/// Fill every mip level of a layer after the first with a downscaled copy of `img`, for formats that can't be rendered to.
+pub(crate) fn upload_cpu_mipmaps(queue: &wgpu::Queue, texture: &wgpu::Texture, layer: u32, img: &DynamicImage) {
+    for level in 1..texture.mip_level_count() {
+        let width = (texture.width() >> level).max(1);
+        let height = (texture.height() >> level).max(1);
+        let resized = img.resize_exact(width, height, image::imageops::FilterType::Triangle);
+        let (_, bytes_per_pixel, pixels) = image_data(&resized);
+        write_level(queue, texture, level, layer, bytes_per_pixel, &pixels);
+    }
+}
+
+impl Texture {
+
+    /// Regenerate all mip levels from the first one, e.g. after rendering to it.\
+    /// Needs `COPY_SRC` usage and a format that is renderable and filterable.
+    pub fn generate_mipmaps(&self, state: &State) -> Result<()> {
+        if !self.texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+            bail!("Can't generate mipmaps for a texture without COPY_SRC usage");
+        }
+        if self.texture.dimension() == wgpu::TextureDimension::D3 {
+            bail!("Can't generate mipmaps for a volume texture");
+        }
+        if !can_blit(state, self.texture.format()) {
+            bail!("Can't generate mipmaps for {:?} textures, the format isn't renderable and filterable", self.texture.format());
+        }
+        blit_mipmaps(&state.device, &state.queue, &self.texture);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_chain_ends_at_one_texel() {
+        assert_eq!(mip_level_count(1, 1), 1);
+        assert_eq!(mip_level_count(256, 256), 9);
+        assert_eq!(mip_level_count(300, 20), 9);
+        assert_eq!(mip_level_count(1, 1024), 11);
+    }
+}
@@ -0,0 +1,315 @@
+mod atlas;
+mod builder;
+mod compressed;
+mod cube;
+mod mipmap;
+mod staging;
+mod update;
+
+use std::borrow::Cow;
+
+use image::DynamicImage;
+use anyhow::*;
+
+use crate::bindings::Binder;
+use crate::State;
+
+pub use atlas::{Atlas, AtlasSprite, SpriteHandle};
+pub use builder::TextureBuilder;
+pub use compressed::ContainerImage;
+pub use mipmap::mip_level_count;
+pub use staging::StagingRing;
+
+pub struct Texture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    /// How `view` sees the texture: `D2`, `D2Array`, `Cube`, `CubeArray` or `D3`.
+    pub view_dimension: wgpu::TextureViewDimension,
+    /// Other formats the texture's views may have, kept by `resize`.
+    pub view_formats: Vec<wgpu::TextureFormat>,
+    /// The debug label of the texture and its view, kept by `resize`.
+    pub label: Option<String>,
+}
+
+impl Texture {
+
+    /// An empty `Rgba8UnormSrgb` texture, see `TextureBuilder` for other formats, usages and samplers.
+    pub fn new(state: &State, width: u32, height: u32) -> Result<Self> {
+        TextureBuilder::new().build(state, width, height)
+    }
+
+    /// Upload an encoded image, or a KTX2 or DDS file.
+    pub fn from_bytes(
+        state: &State,
+        bytes: &[u8],
+    ) -> Result<Self> {
+        TextureBuilder::new().from_bytes(state, bytes)
+    }
+
+    /// Upload an image of any color type.\
+    /// 8-bit images become `Rgba8UnormSrgb`, 16-bit images `Rgba16Float` and float images `Rgba32Float`.\
+    /// 16-bit colors are decoded from sRGB on upload so they sample like 8-bit ones, float images are taken as linear.\
+    /// `Rgba32Float` can't be sampled with a filtering sampler unless the adapter supports it.
+    pub fn from_image(
+        state: &State,
+        img: &DynamicImage
+    ) -> Result<Self> {
+        TextureBuilder::new().from_image(state, img)
+    }
+
+    /// Upload images of the same size and precision as the layers of a `D2Array` texture.
+    pub fn array_from_images(state: &State, images: &[DynamicImage]) -> Result<Self> {
+        TextureBuilder::new().array_from_images(state, images)
+    }
+
+    /// Upload six square images as the faces of a cube map, in the order +X, -X, +Y, -Y, +Z, -Z.
+    pub fn cubemap_from_faces(state: &State, faces: &[DynamicImage; 6]) -> Result<Self> {
+        TextureBuilder::new().cubemap_from_faces(state, faces)
+    }
+
+    /// Render a cube map from an equirectangular panorama on the GPU.
+    pub fn cubemap_from_equirectangular(state: &State, img: &DynamicImage, face_size: u32) -> Result<Self> {
+        TextureBuilder::new().cubemap_from_equirectangular(state, img, face_size)
+    }
+
+    /// Upload images of the same size and precision as the depth slices of a 3D texture.
+    pub fn volume_from_slices(state: &State, slices: &[DynamicImage]) -> Result<Self> {
+        TextureBuilder::new().volume_from_slices(state, slices)
+    }
+
+    /// Upload a KTX2 file with its layers and mip levels, decompressing it on the CPU if the device can't sample its format.
+    pub fn from_ktx2(state: &State, bytes: &[u8]) -> Result<Self> {
+        TextureBuilder::new().from_ktx2(state, bytes)
+    }
+
+    /// Upload a DDS file with its layers and mip levels, decompressing it on the CPU if the device can't sample its format.
+    pub fn from_dds(state: &State, bytes: &[u8]) -> Result<Self> {
+        TextureBuilder::new().from_dds(state, bytes)
+    }
+
+    /// A `Binder` for the view, with its view dimension and sample type.
+    pub fn view_binder(&self, binding: u32, visibility: wgpu::ShaderStages) -> Binder<'_> {
+        Binder {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Texture {
+                sample_type: self.texture.format().describe().sample_type,
+                view_dimension: self.view_dimension,
+                multisampled: false
+            },
+            count: None,
+            resources: vec![
+                wgpu::BindingResource::TextureView(&self.view)
+            ]
+        }
+    }
+
+    /// A `Binder` for the sampler, filtering unless the format can't be filtered.\
+    /// Comparison samplers (`TextureBuilder::compare`) need `SamplerBindingType::Comparison` instead.
+    pub fn sampler_binder(&self, binding: u32, visibility: wgpu::ShaderStages) -> Binder<'_> {
+        let ty = match self.texture.format().describe().sample_type {
+            wgpu::TextureSampleType::Float { filterable: true } => wgpu::SamplerBindingType::Filtering,
+            _ => wgpu::SamplerBindingType::NonFiltering,
+        };
+        Binder {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Sampler(ty),
+            count: None,
+            resources: vec![
+                wgpu::BindingResource::Sampler(&self.sampler)
+            ]
+        }
+    }
+}
+
+/// The texture format, bytes per pixel and tightly packed pixels to upload an image with.\
+/// Color types without a matching format are expanded to RGBA.\
+/// 16-bit channels are sRGB encoded like 8-bit ones, so they are decoded to linear before being stored as half floats.
+fn image_data(img: &DynamicImage) -> (wgpu::TextureFormat, u32, Cow<'_, [u8]>) {
+    match img {
+        DynamicImage::ImageRgba8(rgba) => (wgpu::TextureFormat::Rgba8UnormSrgb, 4, Cow::Borrowed(rgba.as_raw())),
+        DynamicImage::ImageLuma16(_) | DynamicImage::ImageLumaA16(_) | DynamicImage::ImageRgb16(_) | DynamicImage::ImageRgba16(_) => {
+            (wgpu::TextureFormat::Rgba16Float, 8, Cow::Owned(half_floats(&srgb_to_linear(img.to_rgba32f()))))
+        }
+        DynamicImage::ImageRgba32F(rgba) => (wgpu::TextureFormat::Rgba32Float, 16, Cow::Borrowed(bytemuck::cast_slice(rgba.as_raw()))),
+        DynamicImage::ImageRgb32F(_) => (wgpu::TextureFormat::Rgba32Float, 16, Cow::Owned(bytemuck::cast_slice(img.to_rgba32f().as_raw()).to_vec())),
+        // Luma, luma alpha, RGB and any color types added later.
+        _ => (wgpu::TextureFormat::Rgba8UnormSrgb, 4, Cow::Owned(img.to_rgba8().into_raw())),
+    }
+}
+
+/// Decode the sRGB color channels of float pixels, as sampling an `Srgb` format does. Alpha is already linear.
+fn srgb_to_linear(mut rgba: image::Rgba32FImage) -> image::Rgba32FImage {
+    for pixel in rgba.pixels_mut() {
+        for v in &mut pixel.0[..3] {
+            *v = if *v <= 0.04045 { *v / 12.92 } else { ((*v + 0.055) / 1.055).powf(2.4) };
+        }
+    }
+    rgba
+}
+
+/// Convert float pixels to the bytes of `Rgba16Float` texels.
+fn half_floats(rgba: &image::Rgba32FImage) -> Vec<u8> {
+    let halves: Vec<u16> = rgba.as_raw().iter().map(|v| half::f16::from_f32(*v).to_bits()).collect();
+    bytemuck::cast_slice(&halves).to_vec()
+}
+
+/// The format features the device can use, the adapter's own if `TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES` is enabled.
+fn format_features(state: &State, format: wgpu::TextureFormat) -> wgpu::TextureFormatFeatures {
+    if state.device.features().contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) {
+        state.adapter.get_texture_format_features(format)
+    } else {
+        format.describe().guaranteed_format_features
+    }
+}
+
+/// Write tightly packed pixels to a whole mip level of one layer, or depth slice, of a texture.
+fn write_level(queue: &wgpu::Queue, texture: &wgpu::Texture, level: u32, layer: u32, bytes_per_pixel: u32, pixels: &[u8]) {
+    let width = (texture.width() >> level).max(1);
+    let height = (texture.height() >> level).max(1);
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: level,
+            origin: wgpu::Origin3d {
+                x: 0,
+                y: 0,
+                z: layer
+            },
+            aspect: wgpu::TextureAspect::All
+        },
+
+        pixels,
+
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: std::num::NonZeroU32::new(bytes_per_pixel * width),
+            rows_per_image: std::num::NonZeroU32::new(height),
+        },
+
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1
+        },
+    );
+}
+
+/// A depth buffer with the size of a render target, see `StateBuilder::depth_format`.
+pub struct DepthTexture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub format: wgpu::TextureFormat,
+}
+
+impl DepthTexture {
+
+    /// The depth format used when none is specified.
+    pub const DEFAULT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+    pub fn new(device: &wgpu::Device, width: u32, height: u32, format: wgpu::TextureFormat, sample_count: u32) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("wgpu_quick depth texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Self {
+            texture,
+            view,
+            format
+        }
+    }
+
+    /// A `DepthStencilState` for pipelines that draw with a depth buffer of `format`.\
+    /// Nearer fragments win and write their depth, stencil is unused.
+    pub fn depth_stencil_state(format: wgpu::TextureFormat) -> wgpu::DepthStencilState {
+        wgpu::DepthStencilState {
+            format,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }
+    }
+}
+
+/// A multisampled color target that resolves into a surface or texture, see `StateBuilder::sample_count`.
+pub struct MsaaTexture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sample_count: u32,
+}
+
+impl MsaaTexture {
+
+    pub fn new(device: &wgpu::Device, width: u32, height: u32, format: wgpu::TextureFormat, sample_count: u32) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("wgpu_quick multisampled target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Self {
+            texture,
+            view,
+            sample_count
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eight_bit_images_are_expanded_to_rgba() {
+        let img = DynamicImage::ImageLuma8(image::GrayImage::from_raw(2, 1, vec![10, 200]).unwrap());
+        let (format, bytes_per_pixel, pixels) = image_data(&img);
+        assert_eq!(format, wgpu::TextureFormat::Rgba8UnormSrgb);
+        assert_eq!(bytes_per_pixel, 4);
+        assert_eq!(&pixels[..], &[10, 10, 10, 255, 200, 200, 200, 255]);
+    }
+
+    #[test]
+    fn high_precision_images_keep_their_precision() {
+        let img = DynamicImage::ImageRgba16(image::ImageBuffer::from_raw(1, 1, vec![0u16, 32768, 65535, 32768]).unwrap());
+        let (format, bytes_per_pixel, pixels) = image_data(&img);
+        assert_eq!(format, wgpu::TextureFormat::Rgba16Float);
+        assert_eq!(pixels.len(), bytes_per_pixel as usize);
+        let halves: Vec<f32> = bytemuck::cast_slice::<u8, u16>(&pixels).iter().map(|v| half::f16::from_bits(*v).to_f32()).collect();
+        // Colors are decoded from sRGB, alpha is kept.
+        assert_eq!(halves[2], 1.0);
+        assert!((halves[1] - 0.214).abs() < 0.001, "{}", halves[1]);
+        assert!((halves[3] - 0.5).abs() < 0.001, "{}", halves[3]);
+
+        let img = DynamicImage::ImageRgb32F(image::ImageBuffer::from_raw(1, 1, vec![0.25f32, 2.0, 8.0]).unwrap());
+        let (format, _, pixels) = image_data(&img);
+        assert_eq!(format, wgpu::TextureFormat::Rgba32Float);
+        assert_eq!(bytemuck::cast_slice::<u8, f32>(&pixels), &[0.25, 2.0, 8.0, 1.0]);
+    }
+}
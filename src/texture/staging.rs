@@ -0,0 +1,172 @@
+use std::sync::mpsc;
+use std::sync::Arc;
+
+use anyhow::*;
+
+use crate::capture::padded_bytes_per_row;
+use crate::rect::ScreenRectangle;
+use super::Texture;
+
+/// A staging buffer, mapped while it is free or being written.
+struct Chunk {
+    buffer: Arc<wgpu::Buffer>,
+    offset: wgpu::BufferAddress,
+}
+
+/// A ring of mappable staging buffers for streaming texture uploads every frame.\
+/// Writes go straight into a mapped buffer and are copied to the texture by the command encoder,
+/// so a large upload never waits on the queue: if no buffer has come back from the GPU yet, the ring grows.
+///
+/// Call `finish` before submitting the encoder, and `recall` after, to map the used buffers again.
+pub struct StagingRing {
+    chunk_size: wgpu::BufferAddress,
+    created: usize,
+    /// The buffer being written to.
+    active: Option<Chunk>,
+    /// Buffers used by the encoder, unmapped by `finish` and waiting for `recall`.
+    closed: Vec<Chunk>,
+    /// Mapped buffers ready to be written to.
+    free: Vec<Chunk>,
+    sender: mpsc::Sender<Chunk>,
+    receiver: mpsc::Receiver<Chunk>,
+}
+
+impl StagingRing {
+
+    /// A ring of buffers of `chunk_size` bytes, created as they are needed.\
+    /// A row of a streamed region has to fit in a buffer, once padded to 256 bytes.
+    pub fn new(chunk_size: wgpu::BufferAddress) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            chunk_size,
+            created: 0,
+            active: None,
+            closed: vec![],
+            free: vec![],
+            sender,
+            receiver,
+        }
+    }
+
+    /// The number of buffers the ring has created.
+    pub fn buffer_count(&self) -> usize {
+        self.created
+    }
+
+    /// Reserve `size` bytes, aligned for a buffer to texture copy, in a mapped buffer.
+    fn allocate(&mut self, device: &wgpu::Device, size: wgpu::BufferAddress) -> (Arc<wgpu::Buffer>, wgpu::BufferAddress) {
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as wgpu::BufferAddress;
+        if let Some(active) = &self.active {
+            if active.offset + size > self.chunk_size {
+                let full = self.active.take().unwrap();
+                full.buffer.unmap();
+                self.closed.push(full);
+            }
+        }
+        if self.active.is_none() {
+            self.free.extend(self.receiver.try_iter());
+            let chunk = match self.free.pop() {
+                Some(chunk) => chunk,
+                None => {
+                    self.created += 1;
+                    Chunk {
+                        buffer: Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+                            label: Some("wgpu_quick staging buffer"),
+                            size: self.chunk_size,
+                            usage: wgpu::BufferUsages::MAP_WRITE | wgpu::BufferUsages::COPY_SRC,
+                            mapped_at_creation: true,
+                        })),
+                        offset: 0,
+                    }
+                }
+            };
+            self.active = Some(chunk);
+        }
+
+        let active = self.active.as_mut().unwrap();
+        let offset = active.offset;
+        active.offset = wgpu::util::align_to(offset + size, align);
+        (Arc::clone(&active.buffer), offset)
+    }
+
+    /// Copy tightly packed texels to a region of the texture's first layer and top mip level, through the ring.
+    pub fn write_texture(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, texture: &Texture, region: ScreenRectangle, data: &[u8]) -> Result<()> {
+        let bytes_per_pixel = texture.check_region(&region)?;
+        let row = region.width as usize * bytes_per_pixel as usize;
+        if data.len() != row * region.height as usize {
+            bail!("A {}x{} region of a {:?} texture is {} bytes, got {}", region.width, region.height, texture.texture.format(), row * region.height as usize, data.len());
+        }
+        if region.width == 0 || region.height == 0 {
+            return Ok(());
+        }
+        let padded_row = padded_bytes_per_row(region.width, bytes_per_pixel);
+        let rows_per_band = (self.chunk_size / padded_row as wgpu::BufferAddress) as u32;
+        if rows_per_band == 0 {
+            bail!("A row of {} bytes doesn't fit in the staging buffers of {} bytes", padded_row, self.chunk_size);
+        }
+
+        let mut y = 0;
+        while y < region.height {
+            let rows = rows_per_band.min(region.height - y);
+            let size = (padded_row * rows) as wgpu::BufferAddress;
+            let (buffer, offset) = self.allocate(device, size);
+            {
+                let mut mapped = buffer.slice(offset..offset + size).get_mapped_range_mut();
+                for (band_row, source) in data[y as usize * row..(y + rows) as usize * row].chunks_exact(row).enumerate() {
+                    let start = band_row * padded_row as usize;
+                    mapped[start..start + row].copy_from_slice(source);
+                }
+            }
+            encoder.copy_buffer_to_texture(
+                wgpu::ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: wgpu::ImageDataLayout {
+                        offset,
+                        bytes_per_row: std::num::NonZeroU32::new(padded_row),
+                        rows_per_image: std::num::NonZeroU32::new(rows),
+                    },
+                },
+                wgpu::ImageCopyTexture {
+                    texture: &texture.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: region.pos.x,
+                        y: region.pos.y + y,
+                        z: 0
+                    },
+                    aspect: wgpu::TextureAspect::All
+                },
+                wgpu::Extent3d {
+                    width: region.width,
+                    height: rows,
+                    depth_or_array_layers: 1
+                },
+            );
+            y += rows;
+        }
+        Ok(())
+    }
+
+    /// Unmap the buffers written since the last `finish`, call it before submitting their encoder.
+    pub fn finish(&mut self) {
+        if let Some(active) = self.active.take() {
+            active.buffer.unmap();
+            self.closed.push(active);
+        }
+    }
+
+    /// Start mapping the finished buffers again, call it after submitting their encoder.\
+    /// They return to the ring once the GPU is done with them, which `Device::poll` or the next submit drives.
+    pub fn recall(&mut self) {
+        for mut chunk in self.closed.drain(..) {
+            chunk.offset = 0;
+            let buffer = Arc::clone(&chunk.buffer);
+            let sender = self.sender.clone();
+            buffer.slice(..).map_async(wgpu::MapMode::Write, move |result| {
+                if result.is_ok() {
+                    let _ = sender.send(chunk);
+                }
+            });
+        }
+    }
+}
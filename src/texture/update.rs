@@ -0,0 +1,128 @@
+use anyhow::*;
+use image::{DynamicImage, GenericImageView};
+
+use crate::rect::{ScreenPoint, ScreenRectangle};
+use super::{image_data, mip_level_count, StagingRing, Texture};
+
+impl Texture {
+
+    /// The bytes per texel, or an error if the format is compressed or the region leaves the texture.
+    pub(crate) fn check_region(&self, region: &ScreenRectangle) -> Result<u32> {
+        let info = self.texture.format().describe();
+        if info.block_dimensions != (1, 1) {
+            bail!("Can't write a region of a {:?} texture, only uncompressed formats can be updated", self.texture.format());
+        }
+        let (width, height) = (self.texture.width(), self.texture.height());
+        let fits = |pos: u32, len: u32, size: u32| pos.checked_add(len).is_some_and(|end| end <= size);
+        if !fits(region.pos.x, region.width, width) || !fits(region.pos.y, region.height, height) {
+            bail!(
+                "The region {}x{} at {},{} is outside of the {}x{} texture",
+                region.width, region.height, region.pos.x, region.pos.y, width, height
+            );
+        }
+        Ok(info.block_size as u32)
+    }
+
+    /// Write tightly packed texels to a rectangle of the first layer's top mip level.\
+    /// The data is copied into the queue's own staging memory, see `stream_region` for large uploads every frame.
+    pub fn write_region(&self, queue: &wgpu::Queue, region: ScreenRectangle, data: &[u8]) -> Result<()> {
+        let bytes_per_pixel = self.check_region(&region)?;
+        let expected = region.width as u64 * region.height as u64 * bytes_per_pixel as u64;
+        if data.len() as u64 != expected {
+            bail!("A {}x{} region of a {:?} texture is {} bytes, got {}", region.width, region.height, self.texture.format(), expected, data.len());
+        }
+        if region.width == 0 || region.height == 0 {
+            return Ok(());
+        }
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: region.pos.x,
+                    y: region.pos.y,
+                    z: 0
+                },
+                aspect: wgpu::TextureAspect::All
+            },
+            data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(region.width * bytes_per_pixel),
+                rows_per_image: std::num::NonZeroU32::new(region.height),
+            },
+            wgpu::Extent3d {
+                width: region.width,
+                height: region.height,
+                depth_or_array_layers: 1
+            },
+        );
+        Ok(())
+    }
+
+    /// Write an image with its top left corner at `pos`.\
+    /// The image is converted like in `Texture::from_image` and has to end up in the texture's format, apart from sRGB.
+    pub fn write_image_at(&self, queue: &wgpu::Queue, pos: ScreenPoint, img: &DynamicImage) -> Result<()> {
+        let (image_format, _, pixels) = image_data(img);
+        let format = self.texture.format();
+        if image_format.remove_srgb_suffix() != format.remove_srgb_suffix() {
+            bail!("A {:?} image is uploaded as {:?}, it can't be written to a {:?} texture", img.color(), image_format, format);
+        }
+        let (width, height) = img.dimensions();
+        let region = ScreenRectangle {
+            pos,
+            width,
+            height
+        };
+        self.write_region(queue, region, &pixels)
+    }
+
+    /// Like `write_region`, but copies through a buffer of `ring` with a command recorded into `encoder`.\
+    /// Regions larger than the ring's buffers are split into bands of rows.
+    pub fn stream_region(&self, ring: &mut StagingRing, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, region: ScreenRectangle, data: &[u8]) -> Result<()> {
+        ring.write_texture(device, encoder, self, region, data)
+    }
+
+    /// Recreate the texture and view with a new size, keeping the format, usage, layers, label and sampler.\
+    /// A mip chain is regenerated to the full length for the new size. The contents are lost and bind groups
+    /// that hold the old view have to be recreated.
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) -> Result<()> {
+        if width == 0 || height == 0 {
+            bail!("Can't resize a texture to {}x{}", width, height);
+        }
+        let cube = matches!(self.view_dimension, wgpu::TextureViewDimension::Cube | wgpu::TextureViewDimension::CubeArray);
+        if cube && width != height {
+            bail!("Cube map faces must be square, not {}x{}", width, height);
+        }
+        let limit = match self.texture.dimension() {
+            wgpu::TextureDimension::D3 => device.limits().max_texture_dimension_3d,
+            _ => device.limits().max_texture_dimension_2d,
+        };
+        if width > limit || height > limit {
+            bail!("A {}x{} texture is larger than the device's maximum of {} texels per side", width, height, limit);
+        }
+
+        let mip_level_count = if self.texture.mip_level_count() > 1 { mip_level_count(width, height) } else { 1 };
+        self.texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: self.label.as_deref(),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: self.texture.depth_or_array_layers()
+            },
+            mip_level_count,
+            sample_count: self.texture.sample_count(),
+            dimension: self.texture.dimension(),
+            format: self.texture.format(),
+            usage: self.texture.usage(),
+            view_formats: &self.view_formats,
+        });
+        self.view = self.texture.create_view(&wgpu::TextureViewDescriptor {
+            label: self.label.as_deref(),
+            dimension: Some(self.view_dimension),
+            ..Default::default()
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+use winit::dpi::{LogicalSize, PhysicalSize};
+use winit::event::WindowEvent;
+use winit::window::WindowId;
+
+use crate::context::GpuContext;
+use crate::texture::{DepthTexture, MsaaTexture};
+use crate::{Attachments, Error};
+
+/// The `WindowId` that a window created from a raw window handle is registered under by `State::new_raw`.
+pub fn raw_window_id() -> WindowId {
+    WindowId::from(0)
+}
+
+/// A window's surface, its configuration and its size.
+pub struct WindowSurface {
+    pub surface: wgpu::Surface,
+    pub config: wgpu::SurfaceConfiguration,
+    /// The ratio of physical to logical pixels of the window.
+    pub scalefactor: f64,
+    /// The current physical size of the window, which may be zero while it is minimized.\
+    /// `config` keeps the last non-zero size.
+    pub size: PhysicalSize<u32>,
+    /// The depth buffer, if the `State` was built with a depth format.
+    pub depth: Option<DepthTexture>,
+    /// The multisampled color target, if the `State` was built with a sample count above 1.
+    pub msaa: Option<MsaaTexture>,
+}
+
+impl WindowSurface {
+
+    /// Wrap a surface and configure it, unless the window has no area yet.
+    pub(crate) fn new(device: &wgpu::Device, surface: wgpu::Surface, config: wgpu::SurfaceConfiguration, scalefactor: f64, attachments: &Attachments) -> Self {
+        let size = PhysicalSize::new(config.width, config.height);
+        let mut depth = None;
+        let mut msaa = None;
+        // A surface can't be configured with a zero size, wait for the first resize instead.
+        if size.width != 0 && size.height != 0 {
+            surface.configure(device, &config);
+            depth = attachments.depth_texture(device, &config);
+            msaa = attachments.msaa_texture(device, &config);
+        }
+        Self {
+            surface,
+            config,
+            scalefactor,
+            size,
+            depth,
+            msaa
+        }
+    }
+
+    /// Configure a new surface to match `template`, so pipelines made for the template's format can draw to it.\
+    /// The present and alpha modes fall back to ones the surface supports.
+    pub(crate) fn matching(context: &GpuContext, surface: wgpu::Surface, template: &wgpu::SurfaceConfiguration, size: PhysicalSize<u32>, scalefactor: f64, attachments: &Attachments) -> Result<Self, Error> {
+        let capabilities = surface.get_capabilities(&context.adapter);
+        if capabilities.formats.is_empty() || capabilities.alpha_modes.is_empty() {
+            return Err(Error::EmptySurfaceCapabilities);
+        }
+        if !capabilities.formats.contains(&template.format) {
+            return Err(Error::UnsupportedSurfaceFormat {
+                requested: template.format,
+                supported: capabilities.formats,
+            });
+        }
+
+        let config = wgpu::SurfaceConfiguration {
+            // A headless template also has `TEXTURE_BINDING`, which surfaces don't take.
+            usage: template.usage & (wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC),
+            format: template.format,
+            width: size.width,
+            height: size.height,
+            present_mode: if capabilities.present_modes.contains(&template.present_mode) {
+                template.present_mode
+            } else {
+                wgpu::PresentMode::Fifo
+            },
+            alpha_mode: if capabilities.alpha_modes.contains(&template.alpha_mode) {
+                template.alpha_mode
+            } else {
+                capabilities.alpha_modes[0]
+            },
+            view_formats: template.view_formats.clone(),
+        };
+
+        Ok(Self::new(&context.device, surface, config, scalefactor, attachments))
+    }
+
+    /// Resize the surface and its attachments to a new physical size.\
+    /// Zero-sized resizes (e.g. a minimized window) are recorded in `size` but don't reconfigure the surface.
+    pub fn resize(&mut self, device: &wgpu::Device, attachments: &Attachments, size: PhysicalSize<u32>) {
+        self.size = size;
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
+        self.config.width = size.width;
+        self.config.height = size.height;
+        self.surface.configure(device, &self.config);
+        self.depth = attachments.depth_texture(device, &self.config);
+        self.msaa = attachments.msaa_texture(device, &self.config);
+    }
+
+    /// Update the size and scale factor from a window event.\
+    /// Handles `Resized` and `ScaleFactorChanged`, returns `true` if the event was one of them.
+    pub fn handle_window_event(&mut self, device: &wgpu::Device, attachments: &Attachments, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::Resized(size) => {
+                self.resize(device, attachments, *size);
+                true
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size } => {
+                self.scalefactor = *scale_factor;
+                self.resize(device, attachments, **new_inner_size);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The current size of the window in logical pixels.
+    pub fn logical_size(&self) -> LogicalSize<f64> {
+        self.size.to_logical(self.scalefactor)
+    }
+}
@@ -0,0 +1,65 @@
+//! Sprites are packed into atlas pages with extruded padding, and a full atlas repacks onto a new page.
+
+use wgpu_quick::testing::{headless_state, solid};
+use wgpu_quick::texture::{Atlas, TextureBuilder};
+
+#[test]
+fn sprites_are_packed_and_repacked() {
+    let state = match headless_state(16, 16) {
+        Some(state) => state,
+        None => return,
+    };
+    let mut atlas = Atlas::new(16, 16)
+        .padding(1)
+        .texture_builder(TextureBuilder::new().usage(wgpu::TextureUsages::COPY_SRC));
+
+    // Four 6x6 sprites with their padding fill a page exactly.
+    let colors = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255], [255, 255, 0, 255]];
+    let handles: Vec<_> = colors.iter().map(|color| atlas.insert(&state, &solid(6, 6, *color)).unwrap()).collect();
+    assert_eq!(atlas.pages().len(), 1);
+    assert_eq!(atlas.revision(), 1);
+
+    let page = atlas.pages()[0].read_to_image(&state).unwrap();
+    for (handle, color) in handles.iter().zip(colors) {
+        let sprite = atlas.sprite(*handle).unwrap();
+        assert_eq!(sprite.page, 0);
+        assert_eq!((sprite.uv.pos.x * 16.0) as u32, sprite.rect.pos.x);
+        assert_eq!(sprite.uv.width, 6.0 / 16.0);
+        assert_eq!(page.get_pixel(sprite.rect.pos.x + 3, sprite.rect.pos.y + 3).0, color);
+        // The padding repeats the sprite's edge.
+        assert_eq!(page.get_pixel(sprite.rect.pos.x - 1, sprite.rect.pos.y - 1).0, color);
+        assert_eq!(page.get_pixel(sprite.rect.pos.x + 6, sprite.rect.pos.y + 6).0, color);
+    }
+
+    let extra = atlas.insert(&state, &solid(10, 4, [255, 255, 255, 255])).unwrap();
+    assert_eq!(atlas.len(), 5);
+    assert_eq!(atlas.pages().len(), 2);
+    assert_eq!(atlas.revision(), 2);
+    // The repacked sprites are still where their handles say.
+    for (handle, color) in handles.iter().zip(colors).chain([(&extra, [255; 4])]) {
+        let sprite = atlas.sprite(*handle).unwrap();
+        let page = atlas.pages()[sprite.page].read_to_image(&state).unwrap();
+        assert_eq!(page.get_pixel(sprite.rect.pos.x, sprite.rect.pos.y).0, color);
+    }
+
+    assert!(atlas.insert(&state, &solid(15, 15, [0; 4])).is_err());
+    assert_eq!(atlas.len(), 5);
+    assert!(Atlas::new(16, 16).sprite(extra).is_none());
+}
+
+#[test]
+fn handles_belong_to_their_atlas() {
+    let state = match headless_state(16, 16) {
+        Some(state) => state,
+        None => return,
+    };
+    let mut first = Atlas::new(16, 16);
+    let mut second = Atlas::new(16, 16);
+    let first_handle = first.insert(&state, &solid(4, 4, [255, 0, 0, 255])).unwrap();
+    let second_handle = second.insert(&state, &solid(4, 4, [0, 255, 0, 255])).unwrap();
+
+    assert!(first.sprite(first_handle).is_some());
+    assert!(second.sprite(second_handle).is_some());
+    assert!(first.sprite(second_handle).is_none());
+    assert!(second.sprite(first_handle).is_none());
+}
@@ -0,0 +1,268 @@
+//! KTX2 and DDS files are parsed into layer-major data, and the CPU decoders match the GPU's.
+
+use wgpu::TextureFormat::*;
+use wgpu_quick::bindings::Bindings;
+use wgpu_quick::testing::{compare, headless_state, headless_state_with, render_fullscreen, Tolerance};
+use wgpu_quick::texture::{ContainerImage, Texture, TextureBuilder};
+use wgpu_quick::{State, StateBuilder};
+
+const SIZE: u32 = 64;
+
+/// Deterministic noise, every bit pattern is a valid (if odd looking) block.
+fn noise(len: usize, mut seed: u32) -> Vec<u8> {
+    (0..len).map(|_| {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        (seed >> 24) as u8
+    }).collect()
+}
+
+fn image(format: wgpu::TextureFormat, width: u32, height: u32, layers: u32, cube: bool, mip_levels: u32, seed: u32) -> ContainerImage {
+    let mut image = ContainerImage {
+        format,
+        width,
+        height,
+        depth: 1,
+        layers,
+        cube,
+        mip_levels,
+        data: vec![],
+    };
+    let len: usize = (0..mip_levels).map(|level| image.level_size(level).1).sum::<usize>() * layers as usize;
+    image.data = noise(len, seed);
+    image
+}
+
+/// Noise is mostly reserved ASTC encodings, so all but every 8th block get one of a few valid block modes,\
+/// with weight grids from 2x2 to 8x5. Partitions and endpoint modes stay random.
+fn valid_astc_modes(data: &mut [u8]) {
+    const MODES: [u16; 7] = [0x051, 0x251, 0x451, 0x3BF, 0x213, 0x104, 0x065];
+    for (i, block) in data.chunks_exact_mut(16).enumerate() {
+        if i % 8 != 0 {
+            let mode = MODES[block[15] as usize % MODES.len()];
+            block[0] = mode as u8;
+            block[1] = (block[1] & !0x07) | (mode >> 8) as u8;
+        }
+    }
+}
+
+const LOAD_SHADER: &str = "
+@group(0) @binding(0)
+var texels: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) p: vec4<f32>) -> @location(0) vec4<f32> {
+    let v = textureLoad(texels, vec2<i32>(p.xy), 0);
+    return MAP;
+}
+";
+
+/// Render the texels of a texture 1:1 to a linear target, with `map` turning the loaded `v` into the output color.
+fn render_texels(state: &State, texture: &Texture, map: &str) -> image::RgbaImage {
+    let bindings = Bindings::make(&state.device, vec![texture.view_binder(0, wgpu::ShaderStages::FRAGMENT)]);
+    render_fullscreen(state, &LOAD_SHADER.replace("MAP", map), &bindings).unwrap()
+}
+
+/// What `render_texels` draws for CPU decoded texels.
+fn expected_texels(decoded: &ContainerImage, signed: bool) -> image::RgbaImage {
+    let channels = decoded.data.len() / (decoded.width * decoded.height) as usize;
+    image::RgbaImage::from_fn(decoded.width, decoded.height, |x, y| {
+        let texel = &decoded.data[(y * decoded.width + x) as usize * channels..][..channels];
+        let mut v = [0.0, 0.0, 0.0, 1.0];
+        for (v, &c) in v.iter_mut().zip(texel) {
+            *v = if signed { (c as i8 as f32 / 127.0).max(-1.0) } else { c as f32 / 255.0 };
+        }
+        if signed {
+            for v in &mut v[..3] {
+                *v = *v * 0.5 + 0.5;
+            }
+        }
+        image::Rgba(v.map(|v| (v * 255.0).round() as u8))
+    })
+}
+
+#[test]
+fn cpu_decoders_match_the_gpu() {
+    let state = pollster::block_on(
+        StateBuilder::new()
+            .force_fallback_adapter(true)
+            .optional_features(wgpu::Features::TEXTURE_COMPRESSION_BC | wgpu::Features::TEXTURE_COMPRESSION_ETC2)
+            .build_headless(SIZE, SIZE, wgpu::TextureFormat::Rgba8Unorm)
+    );
+    let state = match state {
+        Ok(state) => state,
+        Err(_) => return,
+    };
+
+    // The GL backend creates `Etc2Rgba8Unorm` textures as sRGB, so that decoder is checked through its sRGB variant.
+    let formats = [
+        Bc1RgbaUnorm, Bc2RgbaUnorm, Bc3RgbaUnorm, Bc4RUnorm, Bc4RSnorm, Bc5RgUnorm, Bc5RgSnorm, Bc7RgbaUnorm,
+        Etc2Rgb8Unorm, Etc2Rgb8A1Unorm, Etc2Rgba8UnormSrgb, EacR11Unorm, EacR11Snorm, EacRg11Unorm, EacRg11Snorm,
+    ];
+    let mut failures = vec![];
+    for (seed, format) in formats.into_iter().enumerate() {
+        if !state.device.features().contains(format.describe().required_features) {
+            eprintln!("Skipping {:?}, the adapter can't sample it", format);
+            continue;
+        }
+        let signed = matches!(format, Bc4RSnorm | Bc5RgSnorm | EacR11Snorm | EacRg11Snorm);
+        let compressed = image(format, SIZE, SIZE, 1, false, 1, seed as u32 + 1);
+        let texture = TextureBuilder::new().from_container(&state, &compressed).unwrap();
+        assert_eq!(texture.texture.format(), format);
+
+        let map = if signed {
+            "vec4<f32>(v.rgb * 0.5 + 0.5, v.a)"
+        } else if format.describe().srgb {
+            // Encode the sampled linear colors back to the stored sRGB values.
+            "vec4<f32>(select(1.055 * pow(v.rgb, vec3<f32>(1.0 / 2.4)) - 0.055, v.rgb * 12.92, v.rgb <= vec3<f32>(0.0031308)), v.a)"
+        } else {
+            "v"
+        };
+        let actual = render_texels(&state, &texture, map);
+        let expected = expected_texels(&compressed.decompress().unwrap(), signed);
+        let tolerance = Tolerance { per_channel: 3, max_mismatched_percent: 0.0 };
+        let comparison = compare(&actual, &expected, &tolerance).unwrap();
+        if !comparison.passes(&tolerance) {
+            let (x, y, _) = actual.enumerate_pixels()
+                .find(|(x, y, p)| p.0.iter().zip(expected.get_pixel(*x, *y).0).any(|(a, e)| a.abs_diff(e) > 3))
+                .unwrap();
+            failures.push(format!(
+                "{:?}: {} texels differ, first at {},{}: GPU {:?}, CPU {:?}",
+                format, comparison.mismatched, x, y, actual.get_pixel(x, y).0, expected.get_pixel(x, y).0
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn compression_features_are_requested_by_default() {
+    let state = match headless_state(16, 16) {
+        Some(state) => state,
+        None => return,
+    };
+    let compression = wgpu::Features::TEXTURE_COMPRESSION_BC | wgpu::Features::TEXTURE_COMPRESSION_ETC2 | wgpu::Features::TEXTURE_COMPRESSION_ASTC_LDR;
+    assert_eq!(state.device.features() & compression, state.adapter.features() & compression);
+}
+
+#[test]
+fn containers_are_parsed_and_decompressed_without_features() {
+    let state = match headless_state_with(StateBuilder::new().optional_features(wgpu::Features::empty()), 16, 16) {
+        Some(state) => state,
+        None => return,
+    };
+    // Without compression features, compressed files take the CPU path.
+    assert!(!state.device.features().contains(wgpu::Features::TEXTURE_COMPRESSION_BC));
+
+    let mut dds = ddsfile::Dds::new_dxgi(ddsfile::NewDxgiParams {
+        height: 8,
+        width: 8,
+        depth: None,
+        format: ddsfile::DxgiFormat::BC1_UNorm,
+        mipmap_levels: Some(4),
+        array_layers: Some(6),
+        caps2: None,
+        is_cubemap: true,
+        resource_dimension: ddsfile::D3D10ResourceDimension::Texture2D,
+        alpha_mode: ddsfile::AlphaMode::Unknown,
+    }).unwrap();
+    let len = dds.data.len();
+    dds.data = noise(len, 7);
+    let mut bytes = vec![];
+    dds.write(&mut bytes).unwrap();
+
+    let parsed = ContainerImage::from_dds(&bytes).unwrap();
+    assert_eq!((parsed.format, parsed.width, parsed.height, parsed.layers, parsed.cube, parsed.mip_levels), (Bc1RgbaUnorm, 8, 8, 6, true, 4));
+    // Per face: 4 blocks for 8x8, then one block each for 4x4, 2x2 and 1x1.
+    assert_eq!(parsed.level_data(1, 0), &dds.data[56..88]);
+
+    let cube = Texture::from_bytes(&state, &bytes).unwrap();
+    assert_eq!(cube.texture.format(), Rgba8Unorm);
+    assert_eq!(cube.view_dimension, wgpu::TextureViewDimension::Cube);
+    assert_eq!(cube.texture.mip_level_count(), 4);
+
+    // A two layer ETC2 array with two levels, each level storing both layers.
+    let source = image(Etc2Rgb8Unorm, 8, 4, 2, false, 2, 3);
+    let levels = [
+        [source.level_data(0, 0), source.level_data(1, 0)].concat(),
+        [source.level_data(0, 1), source.level_data(1, 1)].concat(),
+    ];
+    let mut ktx2 = vec![0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+    let data_offset = 80 + 2 * 24 + 4;
+    // vkFormat, typeSize, width, height, depth, layers, faces, levels, supercompression, then the DFD offset and length.
+    for value in [147u32, 1, 8, 4, 0, 2, 1, 2, 0, 80 + 2 * 24, 4, 0, 0] {
+        ktx2.extend_from_slice(&value.to_le_bytes());
+    }
+    ktx2.extend_from_slice(&[0; 16]);
+    let mut offset = data_offset;
+    for level in &levels {
+        for value in [offset as u64, level.len() as u64, level.len() as u64] {
+            ktx2.extend_from_slice(&value.to_le_bytes());
+        }
+        offset += level.len();
+    }
+    ktx2.extend_from_slice(&4u32.to_le_bytes());
+    ktx2.extend(levels.concat());
+
+    let parsed = ContainerImage::from_ktx2(&ktx2).unwrap();
+    assert_eq!((parsed.format, parsed.layers, parsed.cube, parsed.mip_levels), (Etc2Rgb8Unorm, 2, false, 2));
+    assert_eq!(parsed.data, source.data);
+
+    let array = Texture::from_bytes(&state, &ktx2).unwrap();
+    assert_eq!(array.texture.format(), Rgba8Unorm);
+    assert_eq!(array.view_dimension, wgpu::TextureViewDimension::D2Array);
+
+    let error = TextureBuilder::new().from_container(&state, &image(Bc6hRgbUfloat, 4, 4, 1, false, 1, 5)).err().unwrap();
+    assert!(error.to_string().contains("TEXTURE_COMPRESSION_BC"), "{}", error);
+    assert!(TextureBuilder::new().from_container(&state, &image(Bc1RgbaUnorm, 6, 6, 1, false, 1, 5)).is_err());
+}
+
+#[test]
+fn astc_decoder_matches_the_gpu() {
+    use wgpu::AstcBlock::*;
+    // Every ASTC block size divides 120.
+    const ASTC_SIZE: u32 = 120;
+    let state = pollster::block_on(
+        StateBuilder::new()
+            .force_fallback_adapter(true)
+            .optional_features(wgpu::Features::TEXTURE_COMPRESSION_ASTC_LDR)
+            .build_headless(ASTC_SIZE, ASTC_SIZE, wgpu::TextureFormat::Rgba8Unorm)
+    );
+    let state = match state {
+        Ok(state) if state.device.features().contains(wgpu::Features::TEXTURE_COMPRESSION_ASTC_LDR) => state,
+        _ => return,
+    };
+
+    let blocks = [B4x4, B5x4, B5x5, B6x5, B6x6, B8x5, B8x6, B8x8, B10x5, B10x6, B10x8, B10x10, B12x10, B12x12];
+    let mut failures = vec![];
+    for (seed, block) in blocks.into_iter().enumerate() {
+        let channel = if seed % 3 == 0 { wgpu::AstcChannel::UnormSrgb } else { wgpu::AstcChannel::Unorm };
+        let format = Astc { block, channel };
+        let mut compressed = image(format, ASTC_SIZE, ASTC_SIZE, 1, false, 1, seed as u32 + 1);
+        valid_astc_modes(&mut compressed.data);
+        let texture = TextureBuilder::new().from_container(&state, &compressed).unwrap();
+        assert_eq!(texture.texture.format(), format);
+
+        let map = if channel == wgpu::AstcChannel::UnormSrgb {
+            "vec4<f32>(select(1.055 * pow(v.rgb, vec3<f32>(1.0 / 2.4)) - 0.055, v.rgb * 12.92, v.rgb <= vec3<f32>(0.0031308)), v.a)"
+        } else {
+            "v"
+        };
+        let actual = render_texels(&state, &texture, map);
+        let expected = expected_texels(&compressed.decompress().unwrap(), false);
+        // Mesa decodes the HDR endpoint modes the LDR profile reserves, so only texels the CPU didn't reject are compared.
+        let decoded: Vec<(u32, u32)> = expected.enumerate_pixels().filter(|(_, _, p)| p.0 != [255, 0, 255, 255]).map(|(x, y, _)| (x, y)).collect();
+        assert!(decoded.len() > (ASTC_SIZE * ASTC_SIZE / 8) as usize, "{:?}: only {} texels decoded", format, decoded.len());
+        let mismatched: Vec<&(u32, u32)> = decoded.iter()
+            .filter(|(x, y)| actual.get_pixel(*x, *y).0.iter().zip(expected.get_pixel(*x, *y).0).any(|(a, e)| a.abs_diff(e) > 3))
+            .collect();
+        if let Some(&&(x, y)) = mismatched.first() {
+            failures.push(format!(
+                "{:?}: {} texels differ, first at {},{}: GPU {:?}, CPU {:?}",
+                format, mismatched.len(), x, y, actual.get_pixel(x, y).0, expected.get_pixel(x, y).0
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,98 @@
+//! A compute pipeline doubles a buffer of numbers, dispatched by a `ComputeObject`.
+
+use std::borrow::Cow;
+use std::sync::Arc;
+use wgpu::util::DeviceExt;
+use wgpu_quick::bindings::{Binder, Bindings};
+use wgpu_quick::pipelines::{dispatch_size, make_compute_pipeline, ComputePipeline};
+use wgpu_quick::renderable::ComputeObject;
+use wgpu_quick::testing::headless_compute_state;
+use wgpu_quick::State;
+
+struct DoublePipe;
+
+impl ComputePipeline for DoublePipe {
+    fn shader_src<'a>(_state: &State) -> wgpu::ShaderSource<'a> {
+        wgpu::ShaderSource::Wgsl(Cow::Borrowed("
+@group(0) @binding(0) var<storage, read_write> numbers: array<u32>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x < arrayLength(&numbers)) {
+        numbers[id.x] = numbers[id.x] * 2u;
+    }
+}"))
+    }
+
+    fn entry_point<'a>(_state: &State) -> &'a str {
+        "main"
+    }
+}
+
+struct MissingEntryPipe;
+
+impl ComputePipeline for MissingEntryPipe {
+    fn shader_src<'a>(state: &State) -> wgpu::ShaderSource<'a> {
+        DoublePipe::shader_src(state)
+    }
+
+    fn entry_point<'a>(_state: &State) -> &'a str {
+        "double"
+    }
+}
+
+#[test]
+fn reflection_errors_are_returned() {
+    let state = match headless_compute_state(16, 16) {
+        Some(state) => state,
+        None => return,
+    };
+    assert!(make_compute_pipeline::<MissingEntryPipe>(&state, &[], &[]).is_err());
+}
+
+#[test]
+fn compute_objects_dispatch_over_every_element() {
+    let state = match headless_compute_state(16, 16) {
+        Some(state) => state,
+        None => return,
+    };
+    let numbers: Vec<u32> = (0..1000).collect();
+    let size = (numbers.len() * 4) as u64;
+    let storage = state.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: bytemuck::cast_slice(&numbers),
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+    });
+    let readback = state.device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let bindings = Bindings::make(&state.device, vec![Binder {
+        binding: 0,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+        resources: vec![storage.as_entire_binding()],
+    }]);
+    let double_pipe = make_compute_pipeline::<DoublePipe>(&state, &[&bindings.bind_layout], &[]).unwrap();
+    assert_eq!(double_pipe.workgroup_size, [64, 1, 1]);
+    assert_eq!(double_pipe.workgroups_for([1000, 1, 1]), [dispatch_size(1000, 64), 1, 1]);
+
+    let double_obj = ComputeObject::new(&double_pipe.pipeline, vec![Arc::clone(&bindings.bind_groups[0])], double_pipe.workgroups_for([1000, 1, 1]));
+    let mut encoder = state.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    double_obj.dispatch(&mut encoder);
+    encoder.copy_buffer_to_buffer(&storage, 0, &readback, 0, size);
+    state.queue.submit(Some(encoder.finish()));
+
+    readback.slice(..).map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    state.device.poll(wgpu::Maintain::Wait);
+    let doubled: Vec<u32> = bytemuck::cast_slice(&readback.slice(..).get_mapped_range()).to_vec();
+    assert_eq!(doubled, numbers.iter().map(|n| n * 2).collect::<Vec<_>>());
+}
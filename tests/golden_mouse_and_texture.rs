@@ -0,0 +1,75 @@
+//! Golden test for the `mouse-and-texture` example, with the mouse in the middle of the window.
+
+#[allow(unused)]
+#[path = "../examples/mouse-and-texture/shader/mod.rs"]
+mod shader;
+
+use std::sync::Arc;
+use wgpu_quick::bindings::{Bindings, Binder};
+use wgpu_quick::buffer::uniform::Uniform;
+use wgpu_quick::pipelines::make_pipline;
+use wgpu_quick::renderable::{model::Model, Indices, RenderObject};
+use wgpu_quick::testing::{assert_golden, headless_state, render_object, Tolerance};
+use wgpu_quick::texture::Texture;
+use crate::shader::MouseTexPipeline;
+
+#[test]
+fn mouse_and_texture() {
+    let state = match headless_state(64, 64) {
+        Some(state) => state,
+        None => return,
+    };
+
+    let texture = Texture::from_bytes(&state, include_bytes!("../examples/mouse-and-texture/kermit.png")).expect("Could not load texture");
+    let mouse_pos = Uniform::new(&state.device, [0.5f32, 0.5]);
+
+    let bindings = Bindings::make(&state.device, vec![
+        Binder {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None
+            },
+            count: None,
+            resources: vec![
+                mouse_pos.get_buffer().as_entire_binding()
+            ]
+        },
+        Binder {
+            binding: 1,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float {
+                    filterable: true
+                },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false
+            },
+            count: None,
+            resources: vec![
+                wgpu::BindingResource::TextureView(&texture.view)
+            ]
+        },
+        Binder {
+            binding: 2,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+            resources: vec![
+                wgpu::BindingResource::Sampler(&texture.sampler)
+            ]
+        }
+    ]);
+
+    let mousetex_pipe = make_pipline::<MouseTexPipeline>(&state, &[&bindings.bind_layout], &[]);
+    let mousetex_obj = RenderObject {
+        pipeline: Arc::clone(&mousetex_pipe.cell),
+        bind_groups: vec![Arc::clone(&bindings.bind_groups[0])],
+        model: Model::<()>::empty(Indices::Ranged { vertices: 0..3, instances: 0..1 })
+    };
+
+    let image = render_object(&state, &mousetex_obj, wgpu::Color::GREEN).unwrap();
+    assert_golden(&image, concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/mouse_and_texture.png"), &Tolerance::default()).unwrap();
+}
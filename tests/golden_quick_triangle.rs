@@ -0,0 +1,41 @@
+//! Golden test for the `quick-triangle` example.
+
+#[allow(unused)]
+#[path = "../examples/quick-triangle/shader/mod.rs"]
+mod shader;
+
+use std::sync::Arc;
+use wgpu_quick::pipelines::make_pipline;
+use wgpu_quick::renderable::{model::Model, Indices, RenderObject};
+use wgpu_quick::testing::{assert_golden, headless_state, render_object, Tolerance};
+use crate::shader::TrianglePipe;
+
+#[test]
+fn quick_triangle() {
+    let state = match headless_state(64, 64) {
+        Some(state) => state,
+        None => return,
+    };
+
+    let triangle_pipe = make_pipline::<TrianglePipe>(&state, &[], &[]);
+    let triangle_obj = RenderObject {
+        pipeline: Arc::clone(&triangle_pipe.cell),
+        bind_groups: vec![],
+        model: Model::<()>::empty(Indices::Ranged { vertices: 0..3, instances: 0..1 })
+    };
+
+    let image = render_object(&state, &triangle_obj, wgpu::Color::BLACK).unwrap();
+    assert_golden(&image, concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/quick_triangle.png"), &Tolerance::default()).unwrap();
+}
+
+#[test]
+fn missing_golden_is_an_error() {
+    if std::env::var_os(wgpu_quick::testing::BLESS_ENV).is_some() {
+        return;
+    }
+    let path = std::env::temp_dir().join("wgpu_quick_missing_golden.png");
+    let _ = std::fs::remove_file(&path);
+    let error = assert_golden(&image::RgbaImage::new(4, 4), &path, &Tolerance::default()).unwrap_err();
+    assert!(error.to_string().contains("WGPU_QUICK_BLESS=1"), "{}", error);
+    assert!(!path.exists());
+}
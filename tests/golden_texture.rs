@@ -0,0 +1,78 @@
+//! Golden test for the `texture` example.
+
+#[allow(unused)]
+#[path = "../examples/texture/shader/mod.rs"]
+mod shader;
+#[allow(unused)]
+#[path = "../examples/texture/vertex/mod.rs"]
+mod vertex;
+
+use std::sync::Arc;
+use wgpu_quick::bindings::{Bindings, Binder};
+use wgpu_quick::pipelines::make_pipline_preprocessed;
+use wgpu_quick::renderable::{model::Model, Indices, RenderObject};
+use wgpu_quick::testing::{assert_golden, headless_state, render_object, Tolerance};
+use wgpu_quick::texture::Texture;
+use crate::shader::TexPipeline;
+use crate::vertex::Vertex;
+
+const VERTICES: [Vertex; 6] = [
+    Vertex { pos : [1.0, 1.0]},
+    Vertex { pos : [1.0, -1.0]},
+    Vertex { pos : [-1.0, -1.0]},
+    Vertex { pos : [-1.0, -1.0]},
+    Vertex { pos : [-1.0, 1.0]},
+    Vertex { pos : [1.0, 1.0]},
+];
+
+const INDICES: [u16; 6] = [
+    0,1,2,
+    3,4,5
+];
+
+#[test]
+fn texture() {
+    let state = match headless_state(64, 64) {
+        Some(state) => state,
+        None => return,
+    };
+
+    let texture = Texture::from_bytes(&state, include_bytes!("../examples/texture/tree.png")).expect("Could not load texture");
+
+    let bindings = Bindings::make(&state.device, vec![
+        Binder {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float {
+                    filterable: true
+                },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false
+            },
+            count: None,
+            resources: vec![
+                wgpu::BindingResource::TextureView(&texture.view)
+            ]
+        },
+        Binder {
+            binding: 1,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+            resources: vec![
+                wgpu::BindingResource::Sampler(&texture.sampler)
+            ]
+        }
+    ]);
+
+    let tex_pipe = make_pipline_preprocessed::<TexPipeline>(&state, &shader::processed().unwrap(), &[&bindings.bind_layout], &[]).unwrap();
+    let tex_obj = RenderObject {
+        pipeline: Arc::clone(&tex_pipe.cell),
+        bind_groups: vec![Arc::clone(&bindings.bind_groups[0])],
+        model: Model::from_vertices(&state, &VERTICES, Indices::from_indices(&state, &INDICES, wgpu::IndexFormat::Uint16, 0..1)),
+    };
+
+    let image = render_object(&state, &tex_obj, wgpu::Color::GREEN).unwrap();
+    assert_golden(&image, concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/texture.png"), &Tolerance::default()).unwrap();
+}
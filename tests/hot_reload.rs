@@ -0,0 +1,98 @@
+//! A watched shader file rebuilds the pipeline in place, and broken shaders keep the old pipeline.
+
+#[allow(unused)]
+#[path = "../examples/quick-triangle/shader/mod.rs"]
+mod shader;
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use wgpu_quick::pipelines::{make_pipline, ShaderPreprocessor};
+use wgpu_quick::renderable::{model::Model, Indices, RenderObject};
+use wgpu_quick::testing::{headless_state, render_object};
+use crate::shader::TrianglePipe;
+
+const TRIANGLE: &str = include_str!("../examples/quick-triangle/shader/shader.wgsl");
+
+/// Write the shader file with a modification time that differs from the last write's.
+fn write_shader(path: &std::path::Path, source: &str, seconds: u64) {
+    std::fs::write(path, source).unwrap();
+    let file = std::fs::File::options().write(true).open(path).unwrap();
+    file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000 + seconds)).unwrap();
+}
+
+#[test]
+fn watched_shaders_reload_in_place() {
+    let state = match headless_state(16, 16) {
+        Some(state) => state,
+        None => return,
+    };
+    let path = std::env::temp_dir().join(format!("wgpu_quick_hot_reload_{}.wgsl", std::process::id()));
+    write_shader(&path, TRIANGLE, 0);
+
+    let mut triangle_pipe = make_pipline::<TrianglePipe>(&state, &[], &[]);
+    triangle_pipe.watch(&path);
+    let triangle_obj = RenderObject {
+        pipeline: Arc::clone(&triangle_pipe.cell),
+        bind_groups: vec![],
+        model: Model::<()>::empty(Indices::Ranged { vertices: 0..3, instances: 0..1 })
+    };
+    assert!(!triangle_pipe.reload_if_changed(&state).unwrap());
+    assert_eq!(render_object(&state, &triangle_obj, wgpu::Color::BLACK).unwrap().get_pixel(8, 8).0, [255, 0, 0, 255]);
+
+    // The render object draws with the rebuilt pipeline from the cell without being updated.
+    let first = triangle_pipe.cell.current();
+    write_shader(&path, &TRIANGLE.replace("vec4<f32>(1.0, 0.0, 0.0, 1.0)", "vec4<f32>(0.0, 1.0, 0.0, 1.0)"), 1);
+    assert!(triangle_pipe.reload_if_changed(&state).unwrap());
+    assert_eq!(triangle_pipe.cell.revision(), 1);
+    assert!(Arc::ptr_eq(&triangle_pipe.pipeline, &triangle_pipe.cell.current()));
+    assert!(!Arc::ptr_eq(&first, &triangle_pipe.cell.current()));
+    assert_eq!(render_object(&state, &triangle_obj, wgpu::Color::BLACK).unwrap().get_pixel(8, 8).0, [0, 255, 0, 255]);
+
+    // Syntax errors are reported by naga with their location.
+    write_shader(&path, &TRIANGLE.replace("return vec4<f32>(1.0", "return vec4<f32>(1.0,,"), 2);
+    let error = triangle_pipe.reload_if_changed(&state).unwrap_err().to_string();
+    assert!(error.contains("wgpu_quick_hot_reload"), "{}", error);
+    // Valid WGSL that doesn't fit the pipeline is caught when the pipeline is created.
+    write_shader(&path, &TRIANGLE.replace("fs_main", "fs_renamed"), 3);
+    assert!(triangle_pipe.reload_if_changed(&state).is_err());
+    // Failed reloads don't retry until the file changes again.
+    assert!(!triangle_pipe.reload_if_changed(&state).unwrap());
+
+    assert_eq!(triangle_pipe.cell.revision(), 1);
+    assert_eq!(render_object(&state, &triangle_obj, wgpu::Color::BLACK).unwrap().get_pixel(8, 8).0, [0, 255, 0, 255]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn preprocessed_shaders_reload_when_an_include_changes() {
+    let state = match headless_state(16, 16) {
+        Some(state) => state,
+        None => return,
+    };
+    let dir = std::env::temp_dir().join(format!("wgpu_quick_hot_reload_includes_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let main = dir.join("main.wgsl");
+    let color = dir.join("color.wgsl");
+    write_shader(&color, "fn color() -> vec4<f32> { return vec4<f32>(COLOR, 1.0); }", 0);
+    write_shader(&main, &TRIANGLE.replace("return vec4<f32>(1.0, 0.0, 0.0, 1.0);", "return color();").replace("@fragment", "#include \"color.wgsl\"\n@fragment"), 0);
+
+    let mut triangle_pipe = make_pipline::<TrianglePipe>(&state, &[], &[]);
+    triangle_pipe.watch_preprocessed(&main, ShaderPreprocessor::new().define("COLOR", "0.0, 0.0, 1.0"));
+    let triangle_obj = RenderObject {
+        pipeline: Arc::clone(&triangle_pipe.cell),
+        bind_groups: vec![],
+        model: Model::<()>::empty(Indices::Ranged { vertices: 0..3, instances: 0..1 })
+    };
+    assert!(!triangle_pipe.reload_if_changed(&state).unwrap());
+
+    write_shader(&color, "fn color() -> vec4<f32> { return vec4<f32>(COLOR, 1.0); }\n", 1);
+    assert!(triangle_pipe.reload_if_changed(&state).unwrap());
+    assert_eq!(render_object(&state, &triangle_obj, wgpu::Color::BLACK).unwrap().get_pixel(8, 8).0, [0, 0, 255, 255]);
+
+    // Errors in an included file point at that file.
+    write_shader(&color, "\nfn color() -> vec4<f32> { return vec4<f32>(COLOR 1.0); }", 2);
+    let error = triangle_pipe.reload_if_changed(&state).unwrap_err().to_string();
+    assert!(error.contains("color.wgsl:2:"), "{}", error);
+    assert_eq!(triangle_pipe.cell.revision(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
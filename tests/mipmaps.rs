@@ -0,0 +1,42 @@
+//! Mip chains are generated on the GPU for renderable formats and on the CPU otherwise.
+
+use wgpu_quick::testing::headless_state;
+use wgpu_quick::texture::TextureBuilder;
+
+#[test]
+fn mipmaps_on_gpu_and_cpu() {
+    let state = match headless_state(16, 16) {
+        Some(state) => state,
+        None => return,
+    };
+
+    let img = image::load_from_memory(include_bytes!("../examples/texture/tree.png")).unwrap();
+
+    // Rgba8UnormSrgb is renderable, its levels are blitted through scratch textures it's copied to.
+    let texture = TextureBuilder::new().mipmaps(true).from_image(&state, &img).unwrap();
+    assert_eq!(texture.texture.mip_level_count(), wgpu_quick::texture::mip_level_count(img.width(), img.height()));
+    assert!(texture.texture.usage().contains(wgpu::TextureUsages::COPY_SRC));
+
+    // Rgba32Float isn't filterable, its levels are downscaled by `image`.
+    let texture = TextureBuilder::new().mipmaps(true).from_image(&state, &image::DynamicImage::ImageRgba32F(img.to_rgba32f())).unwrap();
+    assert!(texture.texture.mip_level_count() > 1);
+    assert!(!texture.texture.usage().contains(wgpu::TextureUsages::COPY_SRC));
+
+    // Red on the left, blue on the right, so the last level is their average on both paths.
+    let halves = image::RgbaImage::from_fn(8, 8, |x, _| if x < 4 { image::Rgba([255, 0, 0, 255]) } else { image::Rgba([0, 0, 255, 255]) });
+    let builder = TextureBuilder::new().mipmaps(true).usage(wgpu::TextureUsages::COPY_SRC);
+
+    // Blitted levels are filtered in linear space and stored as sRGB, half of red is 188.
+    let blitted = builder.from_image(&state, &image::DynamicImage::ImageRgba8(halves.clone())).unwrap();
+    let last = blitted.read_level_to_image(&state, blitted.texture.mip_level_count() - 1).unwrap();
+    assert_eq!(last.dimensions(), (1, 1));
+    assert_close(last.get_pixel(0, 0).0, [188, 0, 188, 255]);
+
+    let downscaled = builder.from_image(&state, &image::DynamicImage::ImageRgba32F(image::DynamicImage::ImageRgba8(halves).to_rgba32f())).unwrap();
+    let last = downscaled.read_level_to_image(&state, downscaled.texture.mip_level_count() - 1).unwrap();
+    assert_close(last.get_pixel(0, 0).0, [128, 0, 128, 255]);
+}
+
+fn assert_close(actual: [u8; 4], expected: [u8; 4]) {
+    assert!(actual.iter().zip(expected).all(|(a, e)| a.abs_diff(e) <= 2), "{:?} isn't {:?}", actual, expected);
+}
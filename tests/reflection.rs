@@ -0,0 +1,80 @@
+//! Pipelines create their bind group layouts from the shader, or check the given ones against it.
+
+#[allow(unused)]
+#[path = "../examples/texture/shader/mod.rs"]
+mod shader;
+#[allow(unused)]
+#[path = "../examples/texture/vertex/mod.rs"]
+mod vertex;
+
+use std::sync::Arc;
+use wgpu_quick::bindings::Bindings;
+use wgpu_quick::pipelines::{make_pipline_checked, make_pipline_reflected, LayoutMismatch};
+use wgpu_quick::renderable::{model::Model, Indices, RenderObject};
+use wgpu_quick::testing::{assert_golden, headless_state, render_object, Tolerance};
+use wgpu_quick::texture::Texture;
+use crate::shader::TexPipeline;
+use crate::vertex::Vertex;
+
+const VERTICES: [Vertex; 6] = [
+    Vertex { pos : [1.0, 1.0]},
+    Vertex { pos : [1.0, -1.0]},
+    Vertex { pos : [-1.0, -1.0]},
+    Vertex { pos : [-1.0, -1.0]},
+    Vertex { pos : [-1.0, 1.0]},
+    Vertex { pos : [1.0, 1.0]},
+];
+
+#[test]
+fn reflected_layouts_render_like_hand_written_ones() {
+    let state = match headless_state(64, 64) {
+        Some(state) => state,
+        None => return,
+    };
+    let texture = Texture::from_bytes(&state, include_bytes!("../examples/texture/tree.png")).expect("Could not load texture");
+
+    let tex_pipe = make_pipline_reflected::<TexPipeline>(&state, &[]).unwrap();
+    assert_eq!(tex_pipe.bind_group_layouts.len(), 1);
+    let bind_group = state.device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &tex_pipe.bind_group_layouts[0],
+        entries: &[
+            wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&texture.view) },
+            wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(&texture.sampler) },
+        ],
+    });
+    let tex_obj = RenderObject {
+        pipeline: Arc::clone(&tex_pipe.cell),
+        bind_groups: vec![Arc::new(bind_group)],
+        model: Model::from_vertices(&state, &VERTICES, Indices::Ranged { vertices: 0..6, instances: 0..1 }),
+    };
+
+    let image = render_object(&state, &tex_obj, wgpu::Color::GREEN).unwrap();
+    assert_golden(&image, concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/texture.png"), &Tolerance::default()).unwrap();
+}
+
+#[test]
+fn checked_layouts_report_every_difference() {
+    let state = match headless_state(64, 64) {
+        Some(state) => state,
+        None => return,
+    };
+    let texture = Texture::from_bytes(&state, include_bytes!("../examples/texture/tree.png")).expect("Could not load texture");
+
+    let bindings = Bindings::make(&state.device, vec![
+        texture.view_binder(0, wgpu::ShaderStages::FRAGMENT),
+        texture.sampler_binder(1, wgpu::ShaderStages::FRAGMENT),
+    ]);
+    assert!(make_pipline_checked::<TexPipeline>(&state, &[&bindings], &[]).is_ok());
+
+    // The sampler is missing and the texture is only visible to the vertex stage.
+    let wrong = Bindings::make(&state.device, vec![texture.view_binder(0, wgpu::ShaderStages::VERTEX)]);
+    let error = match make_pipline_checked::<TexPipeline>(&state, &[&wrong], &[]) {
+        Ok(_) => panic!("The layout doesn't match the shader"),
+        Err(error) => error,
+    };
+    let mismatch = error.downcast_ref::<LayoutMismatch>().unwrap();
+    assert_eq!(mismatch.differences.len(), 2, "{}", mismatch);
+    assert!(mismatch.differences[0].starts_with("group 0 binding 0: visible to VERTEX"), "{}", mismatch);
+    assert!(mismatch.differences[1].starts_with("group 0 binding 1: missing from the layout"), "{}", mismatch);
+}
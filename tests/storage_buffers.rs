@@ -0,0 +1,86 @@
+//! Storage buffers are written from the CPU, used by a compute shader and read back.
+
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use wgpu_quick::bindings::Bindings;
+use wgpu_quick::buffer::storage::{StorageAccess, StorageBuffer, StorageChunk, StorageRemote};
+use wgpu_quick::pipelines::{make_compute_pipeline, ComputePipeline};
+use wgpu_quick::renderable::ComputeObject;
+use wgpu_quick::testing::headless_compute_state;
+use wgpu_quick::State;
+
+struct ScalePipe;
+
+impl ComputePipeline for ScalePipe {
+    fn shader_src<'a>(_state: &State) -> wgpu::ShaderSource<'a> {
+        wgpu::ShaderSource::Wgsl(Cow::Borrowed("
+@group(0) @binding(0) var<storage, read> factor: f32;
+@group(0) @binding(1) var<storage, read> input: array<f32>;
+@group(0) @binding(2) var<storage, read_write> output: array<f32>;
+
+@compute @workgroup_size(8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x < arrayLength(&output)) {
+        output[id.x] = input[id.x] * factor;
+    }
+}"))
+    }
+
+    fn entry_point<'a>(_state: &State) -> &'a str {
+        "main"
+    }
+}
+
+#[test]
+fn storage_buffers_round_trip_through_a_compute_shader() {
+    let state = match headless_compute_state(16, 16) {
+        Some(state) => state,
+        None => return,
+    };
+    let mut factor = StorageBuffer::new(&state.device, 2.0f32, StorageAccess::ReadOnly);
+    let mut input = StorageChunk::new(&state.device, (0..20).map(|i| i as f32).collect(), StorageAccess::ReadOnly);
+    let output = StorageRemote::<f32>::zeroed(&state.device, 20, StorageAccess::ReadWrite);
+
+    let bindings = Bindings::make(&state.device, vec![
+        factor.binder(0, wgpu::ShaderStages::COMPUTE),
+        input.binder(1, wgpu::ShaderStages::COMPUTE),
+        output.binder(2, wgpu::ShaderStages::COMPUTE),
+    ]);
+    let scale_pipe = make_compute_pipeline::<ScalePipe>(&state, &[&bindings.bind_layout], &[]).unwrap();
+    let scale_obj = ComputeObject::new(&scale_pipe.pipeline, vec![Arc::clone(&bindings.bind_groups[0])], scale_pipe.workgroups_for([20, 1, 1]));
+    let dispatch = || {
+        let mut encoder = state.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        scale_obj.dispatch(&mut encoder);
+        state.queue.submit(Some(encoder.finish()));
+    };
+
+    dispatch();
+    let scaled = output.read_back_blocking(&state.device, &state.queue).unwrap();
+    assert_eq!(scaled, (0..20).map(|i| i as f32 * 2.0).collect::<Vec<_>>());
+
+    // Synced ranges reach the shader, the rest of the buffer keeps its old values.
+    *factor = 3.0;
+    factor.sync(&state.queue);
+    input[4] = 100.0;
+    input[5] = 200.0;
+    input[6] = 300.0;
+    input.sync_range(&state.queue, 4..6, 4..6);
+    dispatch();
+    let scaled = output.read_back_blocking(&state.device, &state.queue).unwrap();
+    assert_eq!(&scaled[3..7], &[9.0, 300.0, 600.0, 18.0]);
+
+    assert_eq!(factor.read_back_blocking(&state.device, &state.queue).unwrap(), 3.0);
+    // The async read resolves once anything polls the device, here another thread.
+    let done = AtomicBool::new(false);
+    let gpu_input = std::thread::scope(|scope| {
+        scope.spawn(|| while !done.load(Ordering::Acquire) {
+            state.device.poll(wgpu::Maintain::Poll);
+        });
+        let gpu_input = pollster::block_on(input.read_back(&state.device, &state.queue)).unwrap();
+        done.store(true, Ordering::Release);
+        gpu_input
+    });
+    assert_eq!(gpu_input[6], 6.0);
+    assert_eq!(gpu_input.len(), 20);
+}
@@ -0,0 +1,116 @@
+//! Arrays, cube maps and volumes get the matching view dimension and layer count.
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use wgpu_quick::bindings::Bindings;
+use wgpu_quick::testing::{compare, headless_state, render_fullscreen, solid, Tolerance};
+use wgpu_quick::texture::{Texture, TextureBuilder};
+
+#[test]
+fn texture_shapes() {
+    let state = match headless_state(16, 16) {
+        Some(state) => state,
+        None => return,
+    };
+
+    let layers = [solid(8, 8, [255, 0, 0, 255]), solid(8, 8, [0, 255, 0, 255]), solid(8, 8, [0, 0, 255, 255])];
+    let array = TextureBuilder::new().mipmaps(true).array_from_images(&state, &layers).unwrap();
+    assert_eq!(array.view_dimension, wgpu::TextureViewDimension::D2Array);
+    assert_eq!(array.texture.depth_or_array_layers(), 3);
+    assert_eq!(array.texture.mip_level_count(), 4);
+
+    let faces = [0, 1, 2, 3, 4, 5].map(|i| solid(4, 4, [i * 40, 0, 0, 255]));
+    let cube = Texture::cubemap_from_faces(&state, &faces).unwrap();
+    assert_eq!(cube.view_dimension, wgpu::TextureViewDimension::Cube);
+    assert_eq!(cube.texture.depth_or_array_layers(), 6);
+
+    let volume = Texture::volume_from_slices(&state, &layers).unwrap();
+    assert_eq!(volume.view_dimension, wgpu::TextureViewDimension::D3);
+    assert_eq!(volume.texture.dimension(), wgpu::TextureDimension::D3);
+
+    assert!(Texture::array_from_images(&state, &[solid(8, 8, [0; 4]), solid(4, 4, [0; 4])]).is_err());
+    assert!(Texture::cubemap_from_faces(&state, &[0; 6].map(|_| solid(4, 2, [0; 4]))).is_err());
+
+    let binder = cube.view_binder(0, wgpu::ShaderStages::FRAGMENT);
+    match binder.ty {
+        wgpu::BindingType::Texture { view_dimension, .. } => assert_eq!(view_dimension, wgpu::TextureViewDimension::Cube),
+        _ => panic!("Expected a texture binding"),
+    }
+}
+
+// Looks along +X, tilted from 45 degrees up at the top of the target to 45 degrees down at the bottom.
+const CUBE_VIEW_SHADER: &str = "
+@group(0) @binding(0)
+var cube: texture_cube<f32>;
+
+@group(0) @binding(1)
+var cube_sampler: sampler;
+
+@fragment
+fn fs_main(@builtin(position) p: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureSampleLevel(cube, cube_sampler, vec3<f32>(1.0, 1.0 - p.y / 8.0, 0.0), 0.0);
+}
+";
+
+#[test]
+fn equirectangular_to_cube() {
+    let state = match headless_state(16, 16) {
+        Some(state) => state,
+        None => return,
+    };
+
+    // A red sky over blue ground.
+    let panorama = DynamicImage::ImageRgba8(RgbaImage::from_fn(64, 32, |_, y| {
+        if y < 16 { Rgba([255, 0, 0, 255]) } else { Rgba([0, 0, 255, 255]) }
+    }));
+    let cube = TextureBuilder::new()
+        .mipmaps(true)
+        .cubemap_from_equirectangular(&state, &panorama, 16)
+        .unwrap();
+    assert_eq!(cube.view_dimension, wgpu::TextureViewDimension::Cube);
+    assert_eq!(cube.texture.mip_level_count(), 5);
+
+    let bindings = Bindings::make(&state.device, vec![
+        cube.view_binder(0, wgpu::ShaderStages::FRAGMENT),
+        cube.sampler_binder(1, wgpu::ShaderStages::FRAGMENT),
+    ]);
+    let image = render_fullscreen(&state, CUBE_VIEW_SHADER, &bindings).unwrap();
+    assert_eq!(image.get_pixel(8, 2).0, [255, 0, 0, 255]);
+    assert_eq!(image.get_pixel(8, 13).0, [0, 0, 255, 255]);
+}
+
+const LOAD_SHADER: &str = "
+@group(0) @binding(0)
+var texels: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) p: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureLoad(texels, vec2<i32>(p.xy), 0);
+}
+";
+
+/// Draw the texels of a texture 1:1 to the state's sRGB target.
+fn draw_texels(state: &wgpu_quick::State, texture: &Texture) -> RgbaImage {
+    let bindings = Bindings::make(&state.device, vec![texture.view_binder(0, wgpu::ShaderStages::FRAGMENT)]);
+    render_fullscreen(state, LOAD_SHADER, &bindings).unwrap()
+}
+
+#[test]
+fn sixteen_bit_images_sample_like_eight_bit_ones() {
+    let state = match headless_state(16, 16) {
+        Some(state) => state,
+        None => return,
+    };
+
+    // The same gradient, once with 8-bit and once with 16-bit channels.
+    let eight = RgbaImage::from_fn(16, 16, |x, y| Rgba([(x * 17) as u8, (y * 17) as u8, 128, ((x + y) * 8) as u8]));
+    let sixteen = image::ImageBuffer::from_fn(16, 16, |x, y| image::Rgba(eight.get_pixel(x, y).0.map(|c| c as u16 * 257)));
+    let eight = Texture::from_image(&state, &DynamicImage::ImageRgba8(eight)).unwrap();
+    let sixteen = Texture::from_image(&state, &DynamicImage::ImageRgba16(sixteen)).unwrap();
+    assert_eq!(sixteen.texture.format(), wgpu::TextureFormat::Rgba16Float);
+
+    let expected = draw_texels(&state, &eight);
+    let actual = draw_texels(&state, &sixteen);
+    let tolerance = Tolerance { per_channel: 1, max_mismatched_percent: 0.0 };
+    let comparison = compare(&actual, &expected, &tolerance).unwrap();
+    assert!(comparison.passes(&tolerance), "{} texels differ", comparison.mismatched);
+}
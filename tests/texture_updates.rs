@@ -0,0 +1,88 @@
+//! Regions of a texture can be overwritten directly or streamed through a staging ring, and textures can be resized.
+
+use image::DynamicImage;
+use wgpu_quick::rect::{ScreenPoint, ScreenRectangle};
+use wgpu_quick::testing::{headless_state, solid};
+use wgpu_quick::texture::{StagingRing, TextureBuilder};
+
+fn region(x: u32, y: u32, width: u32, height: u32) -> ScreenRectangle {
+    ScreenRectangle {
+        pos: ScreenPoint::new(x, y),
+        width,
+        height
+    }
+}
+
+#[test]
+fn write_regions_and_images() {
+    let state = match headless_state(16, 16) {
+        Some(state) => state,
+        None => return,
+    };
+    let texture = TextureBuilder::new().usage(wgpu::TextureUsages::COPY_SRC).build(&state, 8, 8).unwrap();
+
+    texture.write_region(&state.queue, region(2, 2, 4, 2), &[255, 0, 0, 255].repeat(8)).unwrap();
+    let green = solid(2, 2, [0, 255, 0, 255]);
+    texture.write_image_at(&state.queue, ScreenPoint::new(6, 6), &green).unwrap();
+
+    let pixels = texture.read_to_image(&state).unwrap();
+    assert_eq!(pixels.get_pixel(2, 2).0, [255, 0, 0, 255]);
+    assert_eq!(pixels.get_pixel(5, 3).0, [255, 0, 0, 255]);
+    assert_eq!(pixels.get_pixel(5, 4).0, [0, 0, 0, 0]);
+    assert_eq!(pixels.get_pixel(7, 7).0, [0, 255, 0, 255]);
+
+    assert!(texture.write_region(&state.queue, region(6, 6, 4, 4), &[0; 64]).is_err());
+    assert!(texture.write_region(&state.queue, region(0, 0, 2, 2), &[0; 15]).is_err());
+    assert!(texture.write_region(&state.queue, region(u32::MAX, 0, 2, 1), &[0; 8]).is_err());
+    assert!(texture.write_region(&state.queue, region(0, 1, 1, u32::MAX), &[0; 4]).is_err());
+    let float = DynamicImage::ImageRgba32F(image::Rgba32FImage::new(1, 1));
+    assert!(texture.write_image_at(&state.queue, ScreenPoint::new(0, 0), &float).is_err());
+}
+
+#[test]
+fn streamed_regions_reuse_the_ring() {
+    let state = match headless_state(16, 16) {
+        Some(state) => state,
+        None => return,
+    };
+    let texture = TextureBuilder::new().usage(wgpu::TextureUsages::COPY_SRC).build(&state, 16, 16).unwrap();
+    // Every padded row takes a whole buffer, so a 16 row region is split into 4 bands.
+    let mut ring = StagingRing::new(1024);
+
+    for frame in 0..3u8 {
+        let data: Vec<u8> = (0..16 * 16).flat_map(|i| [frame, i as u8, 0, 255]).collect();
+        let mut encoder = state.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        texture.stream_region(&mut ring, &state.device, &mut encoder, region(0, 0, 16, 16), &data).unwrap();
+        ring.finish();
+        state.queue.submit(Some(encoder.finish()));
+        ring.recall();
+        state.device.poll(wgpu::Maintain::Wait);
+
+        let pixels = texture.read_to_image(&state).unwrap();
+        assert_eq!(pixels.get_pixel(3, 0).0, [frame, 3, 0, 255]);
+        assert_eq!(pixels.get_pixel(15, 15).0, [frame, 255, 0, 255]);
+    }
+    assert_eq!(ring.buffer_count(), 4);
+
+    let mut encoder = state.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    assert!(StagingRing::new(128).write_texture(&state.device, &mut encoder, &texture, region(0, 0, 16, 1), &[0; 64]).is_err());
+}
+
+#[test]
+fn resize_recreates_the_texture() {
+    let state = match headless_state(16, 16) {
+        Some(state) => state,
+        None => return,
+    };
+    let mut texture = TextureBuilder::new().mipmaps(true).label("resized").build(&state, 8, 8).unwrap();
+    texture.resize(&state.device, 32, 16).unwrap();
+    assert_eq!(texture.label.as_deref(), Some("resized"));
+    assert_eq!((texture.texture.width(), texture.texture.height()), (32, 16));
+    assert_eq!(texture.texture.mip_level_count(), 6);
+    assert_eq!(texture.texture.format(), wgpu::TextureFormat::Rgba8UnormSrgb);
+
+    let mut cube = TextureBuilder::new().build_cube(&state, 4).unwrap();
+    assert!(cube.resize(&state.device, 8, 4).is_err());
+    cube.resize(&state.device, 8, 8).unwrap();
+    assert_eq!(cube.texture.depth_or_array_layers(), 6);
+}
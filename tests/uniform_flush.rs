@@ -0,0 +1,83 @@
+//! Indexed writes to a `UniformChunk` are tracked and flushed in merged ranges.
+
+use wgpu_quick::buffer::uniform::{Flush, UniformChunk};
+use wgpu_quick::testing::headless_state;
+use wgpu_quick::State;
+
+/// Copy the chunk's buffer out and read it back as elements.
+fn read_back(state: &State, instances: &UniformChunk<[f32; 4]>) -> Vec<[f32; 4]> {
+    let size = instances.get_buffer().size();
+    let readback = state.device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let mut encoder = state.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_buffer_to_buffer(instances.get_buffer(), 0, &readback, 0, size);
+    state.queue.submit(Some(encoder.finish()));
+
+    readback.slice(..).map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    state.device.poll(wgpu::Maintain::Wait);
+    let elements = bytemuck::cast_slice(&readback.slice(..).get_mapped_range()).to_vec();
+    elements
+}
+
+#[test]
+fn flush_uploads_only_changed_ranges() {
+    let state = match headless_state(16, 16) {
+        Some(state) => state,
+        None => return,
+    };
+    let mut instances = UniformChunk::with_usage(&state.device, vec![[0.0f32; 4]; 10_000], wgpu::BufferUsages::COPY_SRC).merge_gap(2);
+    assert_eq!(instances.flush(&state.device, &state.queue), Flush::Written(0));
+
+    instances[10] = [1.0; 4];
+    instances[12] = [2.0; 4];
+    instances[5000..5003].copy_from_slice(&[[3.0; 4]; 3]);
+    instances[9999][0] = 4.0;
+    assert_eq!(instances.dirty_ranges(), vec![10..13, 5000..5003, 9999..10000]);
+    assert_eq!(instances.flush(&state.device, &state.queue), Flush::Written(3));
+    assert!(instances.dirty_ranges().is_empty());
+    assert_eq!(read_back(&state, &instances), instances.to_vec());
+
+    // Reading doesn't mark anything, mutable access through the `Vec` marks everything.
+    assert_eq!(instances[12], [2.0; 4]);
+    assert_eq!(instances.len(), 10_000);
+    assert!(instances.dirty_ranges().is_empty());
+    instances.iter_mut().for_each(|instance| instance[3] = 1.0);
+    assert_eq!(instances.dirty_ranges(), vec![0..10_000]);
+    instances.sync_all(&state.queue);
+    instances.clear_dirty();
+    assert!(instances.dirty_ranges().is_empty());
+    assert_eq!(read_back(&state, &instances), instances.to_vec());
+}
+
+#[test]
+fn pushed_elements_are_flushed() {
+    let state = match headless_state(16, 16) {
+        Some(state) => state,
+        None => return,
+    };
+    let mut instances = UniformChunk::with_usage(&state.device, vec![[0.0f32; 4]; 64], wgpu::BufferUsages::COPY_SRC);
+    instances.truncate(32);
+    instances.flush(&state.device, &state.queue);
+
+    instances.push([5.0; 4]);
+    instances.extend([[6.0; 4]; 2]);
+    assert_eq!(instances.dirty_ranges(), vec![0..35]);
+    assert_eq!(instances.flush(&state.device, &state.queue), Flush::Written(1));
+
+    let uploaded = read_back(&state, &instances);
+    assert_eq!(uploaded[..35], instances[0..35]);
+    assert_eq!(uploaded[35..], [[0.0; 4]; 29]);
+
+    // Past the capacity the buffer is replaced by a larger one with every element.
+    instances.extend([[7.0; 4]; 40]);
+    assert_eq!(instances.flush(&state.device, &state.queue), Flush::Reallocated);
+    assert_eq!(instances.capacity(), 128);
+    assert!(instances.dirty_ranges().is_empty());
+    let uploaded = read_back(&state, &instances);
+    assert_eq!(uploaded[..75], instances[0..75]);
+    assert_eq!(uploaded[75..], [[0.0; 4]; 53]);
+}
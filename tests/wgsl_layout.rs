@@ -0,0 +1,111 @@
+//! `wgsl_struct!` layouts match the offsets naga computes for the same WGSL structs.
+
+use std::mem::{offset_of, size_of};
+
+use wgpu_quick::buffer::layout::{Mat3x3, Mat4x4, Vec2, Vec3, Vec4, WgslType};
+use wgpu_quick::buffer::uniform::{Flush, Uniform, UniformChunk};
+use wgpu_quick::testing::headless_state;
+
+wgpu_quick::wgsl_struct! {
+    pub struct Light {
+        pub position: Vec3<f32>,
+        pub range: f32,
+        pub color: Vec3<f32>,
+    }
+}
+
+wgpu_quick::wgsl_struct! {
+    pub struct Globals {
+        pub view: Mat4x4,
+        pub normal: Mat3x3,
+        pub time: f32,
+        pub lights: [Light; 3],
+        pub resolution: Vec2<f32>,
+        pub frame: u32,
+        pub tint: Vec4<f32>,
+    }
+}
+
+const SHADER: &str = "
+struct Light {
+    position: vec3<f32>,
+    range: f32,
+    color: vec3<f32>,
+}
+
+struct Globals {
+    view: mat4x4<f32>,
+    normal: mat3x3<f32>,
+    time: f32,
+    lights: array<Light, 3>,
+    resolution: vec2<f32>,
+    frame: u32,
+    tint: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> globals: Globals;
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return globals.tint * globals.time;
+}
+";
+
+/// The offsets and size naga gives the struct named `name`.
+fn naga_layout(module: &naga::Module, name: &str) -> (Vec<u32>, u32) {
+    module.types.iter()
+        .find_map(|(_, ty)| match &ty.inner {
+            naga::TypeInner::Struct { members, span } if ty.name.as_deref() == Some(name) => {
+                Some((members.iter().map(|member| member.offset).collect(), *span))
+            }
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn layouts_match_naga() {
+    let module = naga::front::wgsl::parse_str(SHADER).unwrap();
+    naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::empty())
+        .validate(&module)
+        .unwrap();
+
+    let (offsets, size) = naga_layout(&module, "Light");
+    assert_eq!(offsets, [offset_of!(Light, position), offset_of!(Light, range), offset_of!(Light, color)].map(|offset| offset as u32));
+    assert_eq!(size as usize, size_of::<Light>());
+
+    let (offsets, size) = naga_layout(&module, "Globals");
+    assert_eq!(offsets, [
+        offset_of!(Globals, view),
+        offset_of!(Globals, normal),
+        offset_of!(Globals, time),
+        offset_of!(Globals, lights),
+        offset_of!(Globals, resolution),
+        offset_of!(Globals, frame),
+        offset_of!(Globals, tint),
+    ].map(|offset| offset as u32));
+    assert_eq!(size as usize, size_of::<Globals>());
+    const _: () = assert!(<Globals as WgslType>::UNIFORM);
+
+    let normal = Mat3x3::from([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 9.0]]);
+    assert_eq!(normal.columns()[2], [7.0, 8.0, 9.0]);
+}
+
+#[test]
+fn uniforms_accept_layout_checked_types() {
+    let state = match headless_state(16, 16) {
+        Some(state) => state,
+        None => return,
+    };
+    let light = Light::new([0.0, 2.0, 0.0].into(), 10.0, [1.0, 0.5, 0.0].into());
+    let globals = Globals::new(Mat4x4::default(), Mat3x3::default(), 0.0, [light; 3], [16.0, 16.0].into(), 0, Vec4([1.0; 4]));
+
+    let mut uniform = Uniform::new_wgsl(&state.device, globals);
+    uniform.time = 1.5;
+    uniform.sync(&state.queue);
+    assert_eq!(uniform.get_buffer().size() as usize, size_of::<Globals>());
+
+    let mut lights = UniformChunk::new_wgsl(&state.device, vec![light; 4]);
+    lights[1].range = 5.0;
+    assert_eq!(lights.flush(&state.device, &state.queue), Flush::Written(1));
+}